@@ -1,10 +1,14 @@
 use anyhow::anyhow;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, FromSample, Host, Sample, Stream, SupportedStreamConfig};
-use std::sync::{Arc, Mutex};
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 use tracing::{debug, error, info};
 
-use crate::devices::AudioDevice;
+use crate::{capture::CaptureBuffer, devices::AudioDevice};
 
 pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
 
@@ -18,22 +22,25 @@ pub fn avg_threshold(samples: &[f32]) -> f32 {
 }
 
 pub fn trim_silence(samples: &mut Vec<f32>, threshold: f32) {
-    // Find start and end positions
-    let start = samples.iter().position(|&x| x.abs() >= threshold);
-    let end = samples.iter().rposition(|&x| x.abs() >= threshold);
-
-    match (start, end) {
-        (Some(s), Some(e)) => {
+    match silence_trim_range(samples, threshold) {
+        Some(range) => {
             // Truncate from the end first
-            samples.truncate(e + 1);
+            samples.truncate(range.end);
             // Remove leading silence
-            samples.drain(0..s);
+            samples.drain(0..range.start);
         }
         // Handle all-silent or empty cases
-        _ => samples.clear(),
+        None => samples.clear(),
     }
 }
 
+/// The samples `trim_silence` would keep, or `None` if all are silent.
+pub fn silence_trim_range(samples: &[f32], threshold: f32) -> Option<Range<usize>> {
+    let start = samples.iter().position(|&x| x.abs() >= threshold)?;
+    let end = samples.iter().rposition(|&x| x.abs() >= threshold)?;
+    Some(start..end + 1)
+}
+
 pub fn mono_from_interleaved(samples: &[f32], channels: u16) -> Vec<f32> {
     if channels <= 1 {
         return samples.to_vec();
@@ -147,20 +154,24 @@ pub struct AudioManager {
 }
 
 impl AudioManager {
-    pub fn new_with_default_input(buffer: Arc<Mutex<Vec<f32>>>) -> Result<Self, anyhow::Error> {
+    pub fn new_with_default_input(
+        buffer: Arc<Mutex<CaptureBuffer>>,
+    ) -> Result<Self, anyhow::Error> {
         let device = get_default_input_device()?;
 
         Self::new(buffer, device)
     }
 
-    pub fn new_with_default_output(buffer: Arc<Mutex<Vec<f32>>>) -> Result<Self, anyhow::Error> {
+    pub fn new_with_default_output(
+        buffer: Arc<Mutex<CaptureBuffer>>,
+    ) -> Result<Self, anyhow::Error> {
         let device = get_default_output_device()?;
 
         Self::new(buffer, device)
     }
 
     pub fn new_with_device(
-        buffer: Arc<Mutex<Vec<f32>>>,
+        buffer: Arc<Mutex<CaptureBuffer>>,
         audio_device: AudioDevice,
     ) -> Result<Self, anyhow::Error> {
         debug!(
@@ -172,7 +183,10 @@ impl AudioManager {
         Self::new(buffer, raw_device)
     }
 
-    pub fn new(buffer: Arc<Mutex<Vec<f32>>>, raw_device: Device) -> Result<Self, anyhow::Error> {
+    pub fn new(
+        buffer: Arc<Mutex<CaptureBuffer>>,
+        raw_device: Device,
+    ) -> Result<Self, anyhow::Error> {
         debug!("input device: {:?}", raw_device.description()?);
 
         let host = cpal::default_host();
@@ -254,13 +268,15 @@ impl AudioManager {
 
     fn write_input_data<T, U>(
         input: &[T],
-        buffer: Arc<Mutex<Vec<f32>>>,
+        buffer: Arc<Mutex<CaptureBuffer>>,
         sample_rate: u32,
         channels: u16,
     ) where
         T: Sample,
         U: Sample + hound::Sample + FromSample<T>,
     {
+        let captured_at = Instant::now();
+
         // Convert the input samples to f32
         let samples: Vec<f32> = input
             .iter()
@@ -276,7 +292,7 @@ impl AudioManager {
         }
 
         if let Ok(mut guard) = buffer.lock() {
-            guard.append(&mut resampled_audio);
+            guard.push(&mut resampled_audio, captured_at);
         }
     }
 
@@ -0,0 +1,174 @@
+use crate::audio::{silence_trim_range, WHISPER_SAMPLE_RATE};
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+/// Resampled input audio together with when each stream callback delivered
+/// it, so latency can be measured from the moment sound was captured.
+#[derive(Debug, Default)]
+pub struct CaptureBuffer {
+    audio: CapturedAudio,
+}
+
+impl CaptureBuffer {
+    pub fn push(&mut self, samples: &mut Vec<f32>, captured_at: Instant) {
+        if samples.is_empty() {
+            return;
+        }
+        self.audio.samples.append(samples);
+        self.audio
+            .chunk_ends
+            .push((self.audio.samples.len(), captured_at));
+    }
+
+    pub fn len(&self) -> usize {
+        self.audio.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.audio.samples.is_empty()
+    }
+
+    /// Takes everything captured so far, leaving the buffer empty.
+    pub fn take(&mut self) -> CapturedAudio {
+        std::mem::take(&mut self.audio)
+    }
+}
+
+/// A run of captured samples. `chunk_ends` holds the end offset of every
+/// callback chunk and when that callback fired.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CapturedAudio {
+    samples: Vec<f32>,
+    chunk_ends: Vec<(usize, Instant)>,
+}
+
+impl CapturedAudio {
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn into_samples(self) -> Vec<f32> {
+        self.samples
+    }
+
+    /// Estimated capture time of the sample at `offset`. A callback delivers
+    /// the audio recorded just before it fired, so earlier samples in a chunk
+    /// are placed further back by their distance from the chunk end.
+    pub fn captured_at(&self, offset: usize) -> Option<Instant> {
+        let (end, fired_at) = self
+            .chunk_ends
+            .iter()
+            .find(|(end, _)| offset < *end)
+            .copied()?;
+        let samples_before_end = (end - offset - 1) as f64;
+        let before = Duration::from_secs_f64(samples_before_end / WHISPER_SAMPLE_RATE as f64);
+        Some(fired_at.checked_sub(before).unwrap_or(fired_at))
+    }
+
+    pub fn first_captured_at(&self) -> Option<Instant> {
+        self.captured_at(0)
+    }
+
+    /// Drops leading and trailing silence, keeping the capture times of the
+    /// samples that remain.
+    pub fn trim_silence(&mut self, threshold: f32) {
+        match silence_trim_range(&self.samples, threshold) {
+            Some(range) => self.retain(range),
+            None => *self = Self::default(),
+        }
+    }
+
+    fn retain(&mut self, range: Range<usize>) {
+        // A chunk whose tail is trimmed away moves its callback time back by
+        // the dropped samples, so the kept ones keep their capture times.
+        let mut chunk_ends = Vec::new();
+        for (end, fired_at) in &self.chunk_ends {
+            if *end <= range.start {
+                continue;
+            }
+            let kept_end = (*end).min(range.end);
+            let trimmed = (end - kept_end) as f64 / WHISPER_SAMPLE_RATE as f64;
+            let fired_at = fired_at
+                .checked_sub(Duration::from_secs_f64(trimmed))
+                .unwrap_or(*fired_at);
+            chunk_ends.push((kept_end - range.start, fired_at));
+            if *end >= range.end {
+                break;
+            }
+        }
+
+        self.samples.truncate(range.end);
+        self.samples.drain(..range.start);
+        self.chunk_ends = chunk_ends;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn places_samples_before_their_callback_time() {
+        let start = Instant::now();
+        let mut buffer = CaptureBuffer::default();
+        buffer.push(&mut vec![0.5; 1600], start + ms(100));
+        buffer.push(&mut vec![0.5; 1600], start + ms(200));
+
+        let audio = buffer.take();
+
+        assert!(buffer.is_empty());
+        assert_eq!(audio.len(), 3200);
+        assert_eq!(audio.captured_at(1599), Some(start + ms(100)));
+        assert_eq!(audio.captured_at(3199), Some(start + ms(200)));
+        let first = audio.first_captured_at().unwrap();
+        assert!((start + ms(100)).duration_since(first) > ms(99));
+        assert_eq!(audio.captured_at(3200), None);
+    }
+
+    #[test]
+    fn trimming_keeps_capture_times_of_remaining_samples() {
+        let start = Instant::now();
+        let mut buffer = CaptureBuffer::default();
+        let mut first = vec![0.0; 1600];
+        first[800..].fill(0.5);
+        let mut second = vec![0.0; 1600];
+        second[..800].fill(0.5);
+        buffer.push(&mut first, start + ms(100));
+        buffer.push(&mut second, start + ms(200));
+        let mut audio = buffer.take();
+        let speech_started = audio.captured_at(800).unwrap();
+        let speech_ended = audio.captured_at(2399).unwrap();
+
+        audio.trim_silence(0.01);
+
+        assert_eq!(audio.len(), 1600);
+        assert_eq!(audio.first_captured_at(), Some(speech_started));
+        assert_eq!(audio.captured_at(1599), Some(speech_ended));
+    }
+
+    #[test]
+    fn trimming_silence_clears_timeline() {
+        let mut buffer = CaptureBuffer::default();
+        buffer.push(&mut vec![0.0; 160], Instant::now());
+        let mut audio = buffer.take();
+
+        audio.trim_silence(0.01);
+
+        assert!(audio.is_empty());
+        assert_eq!(audio.first_captured_at(), None);
+    }
+}
@@ -1,6 +1,11 @@
 pub mod audio;
+pub mod capture;
 pub mod devices;
 pub mod metrics;
+pub mod scc;
+pub mod scheduler;
 pub mod segments;
+pub mod subtitles;
 pub mod validation;
+pub mod wav;
 pub mod whisper;
@@ -1,3 +1,31 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::segments::{SegmentEmissionDecision, SegmentSuppressionReason};
+
+/// Latency observations kept per metric for percentiles.
+const LATENCY_WINDOW: usize = 512;
+/// Durations at or below this share the first histogram bucket.
+const HISTOGRAM_MIN_MS: f64 = 0.1;
+/// Each histogram bucket is this much wider than the previous one, which
+/// bounds the relative error of a percentile to about 2%.
+const HISTOGRAM_GROWTH: f64 = 1.02;
+/// Enough buckets to reach roughly 12 minutes; longer durations are clamped.
+const HISTOGRAM_BUCKETS: usize = 800;
+/// Granularity of the windowed views, and how many slots are kept.
+const WINDOW_SLOT: Duration = Duration::from_secs(10);
+const WINDOW_SLOTS: u32 = 30;
+const ONE_MINUTE: Duration = Duration::from_secs(60);
+const FIVE_MINUTES: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bounds of the cumulative inference histogram published in
+/// `AudioMetricsState::inference_histogram`.
+pub const INFERENCE_HISTOGRAM_BOUNDS_MS: [f64; 10] = [
+    50.0, 100.0, 200.0, 300.0, 500.0, 750.0, 1000.0, 1500.0, 2500.0, 5000.0,
+];
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, specta::Type)]
 #[serde(default)]
 pub struct AudioMetricsState {
@@ -9,9 +37,56 @@ pub struct AudioMetricsState {
     pub inference_p90_ms: f64,
     pub inference_p95_ms: f64,
     pub inference_p99_ms: f64,
+    /// Inference percentiles over roughly the last one and five minutes.
+    pub inference_p50_1m_ms: f64,
+    pub inference_p95_1m_ms: f64,
+    pub inference_p50_5m_ms: f64,
+    pub inference_p95_5m_ms: f64,
+    pub inference_sum_ms: f64,
+    /// Inference runs at or below each of `INFERENCE_HISTOGRAM_BOUNDS_MS`.
+    pub inference_histogram: Vec<u64>,
+    pub inference_cancelled_count: u64,
     pub gate_total_evaluations: u64,
     pub gate_total_emits: u64,
     pub gate_emit_rate: f64,
+    /// Transcription loop passes per second, smoothed.
+    pub effective_step_rate_hz: f64,
+    /// Captured audio still waiting to be processed when the last step ended.
+    pub step_lag_ms: f64,
+    /// Delay the scheduler chose before the next step.
+    pub next_step_delay_ms: f64,
+    /// Time from capturing the oldest speech in a caption to emitting it.
+    pub last_speech_to_emit_ms: f64,
+    pub speech_to_emit_p50_ms: f64,
+    pub speech_to_emit_p90_ms: f64,
+    pub speech_to_emit_p99_ms: f64,
+    /// Captions held back by `PendingDrasticChange` and for how long.
+    pub gate_hold_count: u64,
+    pub last_gate_hold_ms: f64,
+    pub gate_hold_p95_ms: f64,
+    /// Time to queue a segment update for every WebSocket client.
+    pub last_ws_fanout_ms: f64,
+    pub ws_fanout_p95_ms: f64,
+    pub system: SystemMetricsState,
+}
+
+/// Resource usage of the scrybe process and the machine it runs on, to tell
+/// scrybe's own load apart from OBS or a game competing for the same CPU.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize, Clone, PartialEq, specta::Type)]
+#[serde(default)]
+pub struct SystemMetricsState {
+    /// Share of one core, so a busy process can exceed 100.
+    pub process_cpu_percent: f64,
+    pub process_rss_bytes: u64,
+    /// Unavailable on macOS.
+    pub process_threads: Option<u64>,
+    /// Average across all cores.
+    pub system_cpu_percent: f64,
+    pub logical_cpus: u64,
+    pub total_memory_bytes: u64,
+    pub available_memory_bytes: u64,
+    /// Fraction of memory in use, from 0 to 1.
+    pub memory_pressure: f64,
 }
 
 impl Default for AudioMetricsState {
@@ -25,48 +100,314 @@ impl Default for AudioMetricsState {
             inference_p90_ms: 0.0,
             inference_p95_ms: 0.0,
             inference_p99_ms: 0.0,
+            inference_p50_1m_ms: 0.0,
+            inference_p95_1m_ms: 0.0,
+            inference_p50_5m_ms: 0.0,
+            inference_p95_5m_ms: 0.0,
+            inference_sum_ms: 0.0,
+            inference_histogram: vec![0; INFERENCE_HISTOGRAM_BOUNDS_MS.len()],
+            inference_cancelled_count: 0,
             gate_total_evaluations: 0,
             gate_total_emits: 0,
             gate_emit_rate: 0.0,
+            effective_step_rate_hz: 0.0,
+            step_lag_ms: 0.0,
+            next_step_delay_ms: 0.0,
+            last_speech_to_emit_ms: 0.0,
+            speech_to_emit_p50_ms: 0.0,
+            speech_to_emit_p90_ms: 0.0,
+            speech_to_emit_p99_ms: 0.0,
+            gate_hold_count: 0,
+            last_gate_hold_ms: 0.0,
+            gate_hold_p95_ms: 0.0,
+            last_ws_fanout_ms: 0.0,
+            ws_fanout_p95_ms: 0.0,
+            system: SystemMetricsState::default(),
         }
     }
 }
 
+/// Running inference timing statistics in constant memory: a histogram over
+/// the whole run plus ten-second slots for the recent windows.
 #[derive(Debug, Default)]
 pub struct InferenceTimingStats {
-    samples: Vec<f64>,
+    all: DurationHistogram,
+    recent: WindowedHistogram,
+    count: u64,
+    sum_ms: f64,
+    at_or_below: [u64; INFERENCE_HISTOGRAM_BOUNDS_MS.len()],
     mean: f64,
     m2: f64,
 }
 
 impl InferenceTimingStats {
     pub fn record(&mut self, duration_ms: f64, metrics: &mut AudioMetricsState) {
+        self.record_at(Instant::now(), duration_ms, metrics);
+    }
+
+    pub fn record_at(&mut self, now: Instant, duration_ms: f64, metrics: &mut AudioMetricsState) {
         if !duration_ms.is_finite() || duration_ms < 0.0 {
             return;
         }
 
-        self.samples.push(duration_ms);
+        self.all.record(duration_ms);
+        self.recent.record(now, duration_ms);
+        self.count += 1;
+        self.sum_ms += duration_ms;
+        for (count, bound) in self
+            .at_or_below
+            .iter_mut()
+            .zip(INFERENCE_HISTOGRAM_BOUNDS_MS)
+        {
+            if duration_ms <= bound {
+                *count += 1;
+            }
+        }
 
-        let count = self.samples.len() as f64;
+        let count = self.count as f64;
         let delta = duration_ms - self.mean;
         self.mean += delta / count;
         let delta2 = duration_ms - self.mean;
         self.m2 += delta * delta2;
 
+        let last_minute = self.recent.snapshot(now, ONE_MINUTE);
+        let last_five_minutes = self.recent.snapshot(now, FIVE_MINUTES);
+
         metrics.last_inference_ms = duration_ms;
-        metrics.inference_sample_count = self.samples.len() as u64;
+        metrics.inference_sample_count = self.count;
         metrics.inference_std_dev_ms = self.std_dev();
-        metrics.inference_p90_ms = nearest_rank_percentile(&self.samples, 0.90);
-        metrics.inference_p95_ms = nearest_rank_percentile(&self.samples, 0.95);
-        metrics.inference_p99_ms = nearest_rank_percentile(&self.samples, 0.99);
+        metrics.inference_p90_ms = self.all.percentile_ms(0.90);
+        metrics.inference_p95_ms = self.all.percentile_ms(0.95);
+        metrics.inference_p99_ms = self.all.percentile_ms(0.99);
+        metrics.inference_p50_1m_ms = last_minute.percentile_ms(0.50);
+        metrics.inference_p95_1m_ms = last_minute.percentile_ms(0.95);
+        metrics.inference_p50_5m_ms = last_five_minutes.percentile_ms(0.50);
+        metrics.inference_p95_5m_ms = last_five_minutes.percentile_ms(0.95);
+        metrics.inference_sum_ms = self.sum_ms;
+        metrics.inference_histogram = self.at_or_below.to_vec();
     }
 
     fn std_dev(&self) -> f64 {
-        if self.samples.is_empty() {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        (self.m2 / self.count as f64).sqrt()
+    }
+}
+
+/// Fixed-size histogram over log-spaced buckets. Percentiles follow the
+/// nearest-rank definition and report the upper edge of the bucket holding
+/// that rank, clamped to the observed range so small samples stay exact at
+/// the extremes.
+#[derive(Debug, Clone)]
+pub struct DurationHistogram {
+    counts: Vec<u32>,
+    total: u64,
+    min_ms: f64,
+    max_ms: f64,
+}
+
+impl Default for DurationHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BUCKETS],
+            total: 0,
+            min_ms: f64::INFINITY,
+            max_ms: 0.0,
+        }
+    }
+}
+
+impl DurationHistogram {
+    pub fn record(&mut self, duration_ms: f64) {
+        let bucket = Self::bucket(duration_ms);
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+        self.total += 1;
+        self.min_ms = self.min_ms.min(duration_ms);
+        self.max_ms = self.max_ms.max(duration_ms);
+    }
+
+    pub fn merge(&mut self, other: &DurationHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count = count.saturating_add(*other_count);
+        }
+        self.total += other.total;
+        self.min_ms = self.min_ms.min(other.min_ms);
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn percentile_ms(&self, percentile: f64) -> f64 {
+        if self.total == 0 {
             return 0.0;
         }
 
-        (self.m2 / self.samples.len() as f64).sqrt()
+        let percentile = percentile.clamp(0.0, 1.0);
+        let rank = ((percentile * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += *count as u64;
+            if seen >= rank {
+                return Self::upper_edge(bucket).clamp(self.min_ms, self.max_ms);
+            }
+        }
+
+        self.max_ms
+    }
+
+    fn bucket(duration_ms: f64) -> usize {
+        if duration_ms <= HISTOGRAM_MIN_MS {
+            return 0;
+        }
+
+        let bucket = ((duration_ms / HISTOGRAM_MIN_MS).ln() / HISTOGRAM_GROWTH.ln()).ceil();
+        (bucket as usize).min(HISTOGRAM_BUCKETS - 1)
+    }
+
+    fn upper_edge(bucket: usize) -> f64 {
+        HISTOGRAM_MIN_MS * HISTOGRAM_GROWTH.powi(bucket as i32)
+    }
+}
+
+/// Histograms for consecutive `WINDOW_SLOT`s, so a window view covers the
+/// requested duration plus up to one slot.
+#[derive(Debug, Default)]
+struct WindowedHistogram {
+    slots: VecDeque<(Instant, DurationHistogram)>,
+}
+
+impl WindowedHistogram {
+    fn record(&mut self, now: Instant, duration_ms: f64) {
+        let needs_slot = self
+            .slots
+            .back()
+            .is_none_or(|(started, _)| now.saturating_duration_since(*started) >= WINDOW_SLOT);
+        if needs_slot {
+            self.slots.push_back((now, DurationHistogram::default()));
+        }
+        while self.slots.front().is_some_and(|(started, _)| {
+            now.saturating_duration_since(*started) >= WINDOW_SLOT * WINDOW_SLOTS
+        }) {
+            self.slots.pop_front();
+        }
+
+        if let Some((_, histogram)) = self.slots.back_mut() {
+            histogram.record(duration_ms);
+        }
+    }
+
+    fn snapshot(&self, now: Instant, window: Duration) -> DurationHistogram {
+        let mut merged = DurationHistogram::default();
+        for (started, histogram) in &self.slots {
+            if now.saturating_duration_since(*started) < window + WINDOW_SLOT {
+                merged.merge(histogram);
+            }
+        }
+        merged
+    }
+}
+
+/// The most recent `LATENCY_WINDOW` durations of one kind.
+#[derive(Debug, Default, Clone)]
+pub struct LatencyWindow {
+    samples: VecDeque<f64>,
+    count: u64,
+}
+
+impl LatencyWindow {
+    pub fn record(&mut self, duration: Duration) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration.as_secs_f64() * 1000.0);
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn last_ms(&self) -> f64 {
+        self.samples.back().copied().unwrap_or(0.0)
+    }
+
+    pub fn percentile_ms(&self, percentile: f64) -> f64 {
+        let samples: Vec<f64> = self.samples.iter().copied().collect();
+        nearest_rank_percentile(&samples, percentile)
+    }
+}
+
+/// Follows captured speech through inference and the emission gate.
+///
+/// Speech-to-emit is measured from the oldest speech not yet reflected in a
+/// caption. Speech that only produced an empty or duplicate candidate counts
+/// as handled, since the caption on screen already covers it. A hold starts
+/// when the gate first suppresses a candidate as `PendingDrasticChange` and
+/// ends at the next emit or segment rollover.
+#[derive(Debug, Default)]
+pub struct CaptionLatencyTracker {
+    oldest_pending_speech: Option<Instant>,
+    hold_started: Option<Instant>,
+    speech_to_emit: LatencyWindow,
+    gate_hold: LatencyWindow,
+}
+
+impl CaptionLatencyTracker {
+    pub fn speech_captured(&mut self, captured_at: Instant) {
+        if self
+            .oldest_pending_speech
+            .is_none_or(|oldest| captured_at < oldest)
+        {
+            self.oldest_pending_speech = Some(captured_at);
+        }
+    }
+
+    pub fn gate_evaluated(&mut self, now: Instant, decision: &SegmentEmissionDecision) {
+        match decision {
+            SegmentEmissionDecision::Emit(_) => {
+                if let Some(captured_at) = self.oldest_pending_speech.take() {
+                    self.speech_to_emit
+                        .record(now.saturating_duration_since(captured_at));
+                }
+                self.end_hold(now);
+            }
+            SegmentEmissionDecision::Suppress(SegmentSuppressionReason::PendingDrasticChange) => {
+                self.hold_started.get_or_insert(now);
+            }
+            SegmentEmissionDecision::Suppress(_) => {
+                self.oldest_pending_speech = None;
+            }
+        }
+    }
+
+    /// The segment was finalized; audio that never reached inference and any
+    /// held candidate are dropped with it.
+    pub fn segment_rolled_over(&mut self, now: Instant) {
+        self.oldest_pending_speech = None;
+        self.end_hold(now);
+    }
+
+    fn end_hold(&mut self, now: Instant) {
+        if let Some(started) = self.hold_started.take() {
+            self.gate_hold
+                .record(now.saturating_duration_since(started));
+        }
+    }
+
+    pub fn update_metrics(&self, ws_fanout: &LatencyWindow, metrics: &mut AudioMetricsState) {
+        metrics.last_speech_to_emit_ms = self.speech_to_emit.last_ms();
+        metrics.speech_to_emit_p50_ms = self.speech_to_emit.percentile_ms(0.50);
+        metrics.speech_to_emit_p90_ms = self.speech_to_emit.percentile_ms(0.90);
+        metrics.speech_to_emit_p99_ms = self.speech_to_emit.percentile_ms(0.99);
+        metrics.gate_hold_count = self.gate_hold.count();
+        metrics.last_gate_hold_ms = self.gate_hold.last_ms();
+        metrics.gate_hold_p95_ms = self.gate_hold.percentile_ms(0.95);
+        metrics.last_ws_fanout_ms = ws_fanout.last_ms();
+        metrics.ws_fanout_p95_ms = ws_fanout.percentile_ms(0.95);
     }
 }
 
@@ -149,4 +490,137 @@ mod tests {
         assert_eq!(metrics.inference_p95_ms, 300.0);
         assert_eq!(metrics.inference_p99_ms, 300.0);
     }
+
+    #[test]
+    fn inference_timing_stats_publishes_cumulative_histogram() {
+        let mut stats = InferenceTimingStats::default();
+        let mut metrics = AudioMetricsState::default();
+
+        stats.record(100.0, &mut metrics);
+        stats.record(400.0, &mut metrics);
+        stats.record(9000.0, &mut metrics);
+
+        assert_eq!(metrics.inference_sum_ms, 9500.0);
+        assert_eq!(
+            metrics.inference_histogram,
+            vec![0, 1, 1, 1, 2, 2, 2, 2, 2, 2]
+        );
+    }
+
+    #[test]
+    fn duration_histogram_percentiles_stay_within_bucket_error() {
+        let mut histogram = DurationHistogram::default();
+        let samples: Vec<f64> = (1..=1000).map(|ms| ms as f64).collect();
+
+        for sample in &samples {
+            histogram.record(*sample);
+        }
+
+        for percentile in [0.5, 0.9, 0.95, 0.99] {
+            let exact = nearest_rank_percentile(&samples, percentile);
+            let estimate = histogram.percentile_ms(percentile);
+            assert!(
+                (estimate - exact).abs() / exact <= HISTOGRAM_GROWTH - 1.0,
+                "p{}: {} vs {}",
+                percentile,
+                estimate,
+                exact
+            );
+        }
+        assert_eq!(histogram.percentile_ms(1.0), 1000.0);
+    }
+
+    #[test]
+    fn inference_timing_stats_memory_is_bounded() {
+        let start = Instant::now();
+        let mut stats = InferenceTimingStats::default();
+        let mut metrics = AudioMetricsState::default();
+
+        for step in 0..2_000 {
+            let now = start + Duration::from_millis(step * 500);
+            stats.record_at(now, 100.0 + (step % 50) as f64, &mut metrics);
+        }
+
+        assert_eq!(metrics.inference_sample_count, 2_000);
+        assert_eq!(stats.all.counts.len(), HISTOGRAM_BUCKETS);
+        assert!(stats.recent.slots.len() <= WINDOW_SLOTS as usize);
+    }
+
+    #[test]
+    fn inference_timing_stats_reports_recent_windows() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut stats = InferenceTimingStats::default();
+        let mut metrics = AudioMetricsState::default();
+
+        for secs in 0..60 {
+            stats.record_at(at(secs), 900.0, &mut metrics);
+        }
+        for secs in 180..240 {
+            stats.record_at(at(secs), 100.0, &mut metrics);
+        }
+
+        assert_eq!(metrics.inference_p95_1m_ms, 100.0);
+        assert_eq!(metrics.inference_p95_5m_ms, 900.0);
+        assert_eq!(metrics.inference_p95_ms, 900.0);
+
+        stats.record_at(at(420), 100.0, &mut metrics);
+
+        assert_eq!(metrics.inference_p95_5m_ms, 100.0);
+        assert_eq!(metrics.inference_p95_ms, 900.0);
+    }
+
+    #[test]
+    fn latency_window_keeps_recent_samples_and_total_count() {
+        let mut window = LatencyWindow::default();
+
+        for ms in 0..(LATENCY_WINDOW as u64 + 10) {
+            window.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(window.count(), LATENCY_WINDOW as u64 + 10);
+        assert_eq!(window.last_ms(), (LATENCY_WINDOW + 9) as f64);
+        assert_eq!(window.percentile_ms(0.0), 10.0);
+    }
+
+    #[test]
+    fn caption_latency_measures_from_oldest_speech_through_a_gate_hold() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let emit = SegmentEmissionDecision::Emit(crate::whisper::WhisperSegment::default());
+        let hold =
+            SegmentEmissionDecision::Suppress(SegmentSuppressionReason::PendingDrasticChange);
+        let mut tracker = CaptionLatencyTracker::default();
+        let mut metrics = AudioMetricsState::default();
+
+        tracker.speech_captured(at(100));
+        tracker.gate_evaluated(at(400), &hold);
+        tracker.speech_captured(at(600));
+        tracker.gate_evaluated(at(900), &emit);
+        tracker.update_metrics(&LatencyWindow::default(), &mut metrics);
+
+        assert_eq!(metrics.last_speech_to_emit_ms, 800.0);
+        assert_eq!(metrics.gate_hold_count, 1);
+        assert_eq!(metrics.last_gate_hold_ms, 500.0);
+    }
+
+    #[test]
+    fn duplicate_candidates_clear_pending_speech() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let duplicate =
+            SegmentEmissionDecision::Suppress(SegmentSuppressionReason::DuplicateNormalizedText);
+        let emit = SegmentEmissionDecision::Emit(crate::whisper::WhisperSegment::default());
+        let mut tracker = CaptionLatencyTracker::default();
+        let mut metrics = AudioMetricsState::default();
+
+        tracker.speech_captured(at(0));
+        tracker.gate_evaluated(at(300), &duplicate);
+        tracker.speech_captured(at(5000));
+        tracker.gate_evaluated(at(5250), &emit);
+        tracker.update_metrics(&LatencyWindow::default(), &mut metrics);
+
+        assert_eq!(metrics.last_speech_to_emit_ms, 250.0);
+        assert_eq!(metrics.gate_hold_count, 0);
+    }
 }
@@ -0,0 +1,345 @@
+use std::fmt::Write;
+
+use crate::subtitles::{Cue, SubtitleAlign};
+
+/// Columns in a CEA-608 caption row.
+pub const MAX_ROW_CHARS: u32 = 32;
+/// Rows a pop-on caption may use; more crowd the picture.
+pub const MAX_ROWS: u32 = 4;
+/// Captions are stacked upwards from the last of the 15 rows.
+const BOTTOM_ROW: usize = 15;
+
+const RESUME_CAPTION_LOADING: [u8; 2] = [0x14, 0x20];
+const ERASE_NON_DISPLAYED_MEMORY: [u8; 2] = [0x14, 0x2e];
+const END_OF_CAPTION: [u8; 2] = [0x14, 0x2f];
+const ERASE_DISPLAYED_MEMORY: [u8; 2] = [0x14, 0x2c];
+const TAB_OFFSET: u8 = 0x17;
+const SPECIAL_CHARACTER: u8 = 0x11;
+
+/// Preamble address code bytes for rows 1-15 on channel 1, before the
+/// indent bits are added to the second byte.
+const ROW_PREAMBLES: [[u8; 2]; 15] = [
+    [0x11, 0x40],
+    [0x11, 0x60],
+    [0x12, 0x40],
+    [0x12, 0x60],
+    [0x15, 0x40],
+    [0x15, 0x60],
+    [0x16, 0x40],
+    [0x16, 0x60],
+    [0x17, 0x40],
+    [0x17, 0x60],
+    [0x10, 0x40],
+    [0x13, 0x40],
+    [0x13, 0x60],
+    [0x14, 0x40],
+    [0x14, 0x60],
+];
+
+/// Writes cues as pop-on captions on channel 1 with 29.97 fps drop-frame
+/// timecodes. Each caption is loaded off screen so that its end-of-caption
+/// code lands on the cue's start, and erased at the cue's end unless the
+/// next caption's load has begun by then; that caption then replaces it
+/// when it pops on, rather than being blanked by a late erase.
+pub fn write_scc(cues: &[Cue], align: SubtitleAlign) -> String {
+    let loads: Vec<(u64, Vec<[u8; 2]>)> = cues
+        .iter()
+        .map(|cue| {
+            let load = load_caption(cue, align);
+            let start = ms_to_frames(cue.start_ms);
+            (start.saturating_sub(load.len() as u64 - 1), load)
+        })
+        .collect();
+
+    let mut events: Vec<(u64, Vec<[u8; 2]>)> = Vec::new();
+    for (index, cue) in cues.iter().enumerate() {
+        let end = ms_to_frames(cue.end_ms);
+        let erase = doubled(ERASE_DISPLAYED_MEMORY);
+        let replaced_at_end = loads
+            .get(index + 1)
+            .is_some_and(|(next_load_frame, _)| *next_load_frame < end + erase.len() as u64);
+        if !replaced_at_end {
+            events.push((end, erase));
+        }
+    }
+    events.extend(loads);
+    events.sort_by_key(|(frame, _)| *frame);
+
+    let mut out = String::from("Scenarist_SCC V1.0\n\n");
+    // Codes go out one pair per frame, so an event can't start before the
+    // previous one has been sent.
+    let mut next_free_frame = 0;
+    for (frame, words) in events {
+        let frame = frame.max(next_free_frame);
+        next_free_frame = frame + words.len() as u64;
+        let words: Vec<String> = words
+            .iter()
+            .map(|[first, second]| {
+                format!("{:02x}{:02x}", with_parity(*first), with_parity(*second))
+            })
+            .collect();
+        let _ = write!(
+            out,
+            "{}\t{}\n\n",
+            drop_frame_timecode(frame),
+            words.join(" ")
+        );
+    }
+    out
+}
+
+fn load_caption(cue: &Cue, align: SubtitleAlign) -> Vec<[u8; 2]> {
+    let mut words = doubled(RESUME_CAPTION_LOADING);
+    words.extend(doubled(ERASE_NON_DISPLAYED_MEMORY));
+
+    let lines = cue.labelled_lines();
+    let lines = &lines[lines.len().saturating_sub(MAX_ROWS as usize)..];
+    let first_row = BOTTOM_ROW + 1 - lines.len();
+    for (offset, line) in lines.iter().enumerate() {
+        let characters: Vec<Character> = line
+            .chars()
+            .map(encode_char)
+            .take(MAX_ROW_CHARS as usize)
+            .collect();
+        let free_columns = MAX_ROW_CHARS as usize - characters.len();
+        let column = match align {
+            SubtitleAlign::Left => 0,
+            SubtitleAlign::Center => free_columns / 2,
+            SubtitleAlign::Right => free_columns,
+        };
+
+        let [first, second] = ROW_PREAMBLES[first_row + offset - 1];
+        words.extend(doubled([
+            first,
+            second | 0x10 | (((column / 4) as u8) << 1),
+        ]));
+        if column % 4 > 0 {
+            words.extend(doubled([TAB_OFFSET, 0x20 + (column % 4) as u8]));
+        }
+        push_characters(&mut words, &characters);
+    }
+
+    words.extend(doubled(END_OF_CAPTION));
+    words
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Character {
+    Basic(u8),
+    Special(u8),
+}
+
+/// Basic characters are packed two to a pair; special characters take a
+/// pair of their own and, like control codes, are sent twice.
+fn push_characters(words: &mut Vec<[u8; 2]>, characters: &[Character]) {
+    let mut pending = None;
+    for character in characters {
+        match *character {
+            Character::Basic(byte) => match pending.take() {
+                Some(first) => words.push([first, byte]),
+                None => pending = Some(byte),
+            },
+            Character::Special(byte) => {
+                if let Some(first) = pending.take() {
+                    words.push([first, 0x00]);
+                }
+                words.extend(doubled([SPECIAL_CHARACTER, byte]));
+            }
+        }
+    }
+    if let Some(first) = pending {
+        words.push([first, 0x00]);
+    }
+}
+
+/// Maps a character onto the CEA-608 basic and special character sets.
+/// Anything else becomes `?`.
+fn encode_char(character: char) -> Character {
+    let basic = match character {
+        'á' => Some(0x2a),
+        'é' => Some(0x5c),
+        'í' => Some(0x5e),
+        'ó' => Some(0x5f),
+        'ú' => Some(0x60),
+        'ç' => Some(0x7b),
+        '÷' => Some(0x7c),
+        'Ñ' => Some(0x7d),
+        'ñ' => Some(0x7e),
+        '‘' | '’' => Some(b'\''),
+        '“' | '”' => Some(b'"'),
+        '–' | '—' => Some(b'-'),
+        // These ASCII codes are taken by the accented letters above.
+        '*' | '\\' | '^' | '_' | '`' | '{' | '|' | '}' | '~' => None,
+        ' '..='}' => Some(character as u8),
+        _ => None,
+    };
+    if let Some(byte) = basic {
+        return Character::Basic(byte);
+    }
+
+    let special = match character {
+        '®' => 0x30,
+        '°' => 0x31,
+        '½' => 0x32,
+        '¿' => 0x33,
+        '™' => 0x34,
+        '¢' => 0x35,
+        '£' => 0x36,
+        '♪' => 0x37,
+        'à' => 0x38,
+        'è' => 0x3a,
+        'â' => 0x3b,
+        'ê' => 0x3c,
+        'î' => 0x3d,
+        'ô' => 0x3e,
+        'û' => 0x3f,
+        _ => return Character::Basic(b'?'),
+    };
+    Character::Special(special)
+}
+
+fn doubled(code: [u8; 2]) -> Vec<[u8; 2]> {
+    vec![code, code]
+}
+
+/// Sets the high bit when needed to give the byte odd parity.
+fn with_parity(byte: u8) -> u8 {
+    let byte = byte & 0x7f;
+    if byte.count_ones().is_multiple_of(2) {
+        byte | 0x80
+    } else {
+        byte
+    }
+}
+
+/// Frames at 30000/1001 fps, rounded to the nearest.
+fn ms_to_frames(ms: u64) -> u64 {
+    (ms * 30 + 500) / 1001
+}
+
+/// `hh:mm:ss;ff`, skipping frame numbers 0 and 1 at the start of every
+/// minute except each tenth.
+fn drop_frame_timecode(frame: u64) -> String {
+    const FRAMES_PER_10_MINUTES: u64 = 17982;
+    const FRAMES_PER_MINUTE: u64 = 1798;
+
+    let tens = frame / FRAMES_PER_10_MINUTES;
+    let remainder = frame % FRAMES_PER_10_MINUTES;
+    let dropped = 18 * tens
+        + if remainder < 2 {
+            0
+        } else {
+            2 * ((remainder - 2) / FRAMES_PER_MINUTE)
+        };
+    let frame = frame + dropped;
+    format!(
+        "{:02}:{:02}:{:02};{:02}",
+        frame / 108_000,
+        frame / 1800 % 60,
+        frame / 30 % 60,
+        frame % 30
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_ms: u64, end_ms: u64, lines: &[&str]) -> Cue {
+        Cue {
+            start_ms,
+            end_ms,
+            speaker: None,
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn applies_odd_parity() {
+        assert_eq!(with_parity(0x14), 0x94);
+        assert_eq!(with_parity(0x20), 0x20);
+        assert_eq!(with_parity(0x2c), 0x2c);
+        assert_eq!(with_parity(0x2e), 0xae);
+        assert_eq!(with_parity(0x00), 0x80);
+    }
+
+    #[test]
+    fn formats_drop_frame_timecodes() {
+        assert_eq!(drop_frame_timecode(0), "00:00:00;00");
+        assert_eq!(drop_frame_timecode(1799), "00:00:59;29");
+        assert_eq!(drop_frame_timecode(1800), "00:01:00;02");
+        assert_eq!(drop_frame_timecode(17982), "00:10:00;00");
+        assert_eq!(ms_to_frames(60_000), 1798);
+    }
+
+    #[test]
+    fn encodes_rows_with_alignment_and_special_characters() {
+        let centered = load_caption(&cue(0, 1000, &["Hi ♪"]), SubtitleAlign::Center);
+        let right = load_caption(&cue(0, 1000, &["a", "b"]), SubtitleAlign::Right);
+
+        assert_eq!(
+            centered[4..],
+            [
+                // Row 15, indent 12, then tab offset 2 for column 14.
+                [0x14, 0x76],
+                [0x14, 0x76],
+                [0x17, 0x22],
+                [0x17, 0x22],
+                [b'H', b'i'],
+                [b' ', 0x00],
+                [0x11, 0x37],
+                [0x11, 0x37],
+                [0x14, 0x2f],
+                [0x14, 0x2f],
+            ]
+        );
+        // Row 14, indent 28, then tab offset 3 for column 31.
+        assert_eq!(right[4], [0x14, 0x5e]);
+        assert_eq!(right[6], [0x17, 0x23]);
+        assert_eq!(right[8], [b'a', 0x00]);
+        assert_eq!(right[9], [0x14, 0x7e]);
+    }
+
+    #[test]
+    fn erases_only_when_no_caption_follows_immediately() {
+        let scc = write_scc(
+            &[
+                cue(1000, 2000, &["one"]),
+                cue(2000, 3000, &["two"]),
+                cue(5000, 6000, &["three"]),
+            ],
+            SubtitleAlign::Left,
+        );
+
+        assert_eq!(scc.matches("942c 942c").count(), 2);
+        assert!(scc.starts_with("Scenarist_SCC V1.0\n\n00:00:00;21\t9420 9420 94ae 94ae"));
+    }
+
+    #[test]
+    fn never_erases_a_caption_after_the_next_one_pops_on() {
+        let line = "a fairly long line of dialogue";
+        let scc = write_scc(
+            &[
+                cue(1000, 2000, &["short"]),
+                // Its load starts before the first caption ends.
+                cue(2300, 4000, &[line]),
+            ],
+            SubtitleAlign::Left,
+        );
+
+        let events: Vec<&str> = scc.lines().filter(|line| line.contains('\t')).collect();
+        let last_end_of_caption = events
+            .iter()
+            .rposition(|event| event.contains("942f 942f"))
+            .unwrap();
+        let erases: Vec<usize> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.contains("942c 942c"))
+            .map(|(index, _)| index)
+            .collect();
+        // Only the second caption is erased, once it has been shown.
+        assert_eq!(erases, [events.len() - 1]);
+        assert!(erases[0] > last_end_of_caption);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{audio::WHISPER_SAMPLE_RATE, metrics::AudioMetricsState};
+use std::time::{Duration, Instant};
+
+/// Least audio worth running inference on (4000 samples at 16 kHz).
+pub const MIN_INFERENCE_AUDIO_MS: u64 = 250;
+/// Step intervals are never stretched beyond this many base steps in silence.
+const MAX_SILENCE_BACKOFF: u32 = 4;
+/// The step interval is kept this far above the smoothed inference time so
+/// inference can't occupy the whole loop.
+const INFERENCE_HEADROOM: f64 = 1.25;
+/// Weight of the newest observation in the smoothed inference time and step
+/// interval.
+const SMOOTHING: f64 = 0.2;
+
+pub fn samples_to_ms(samples: usize) -> f64 {
+    samples as f64 * 1000.0 / WHISPER_SAMPLE_RATE as f64
+}
+
+/// What happened during one pass of the transcription loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepOutcome {
+    /// Samples that survived silence trimming and were added to the segment.
+    pub new_samples: usize,
+    /// Duration of the inference run, if one ran.
+    pub inference_ms: Option<f64>,
+    /// Audio still waiting in the capture buffer when the step finished.
+    pub backlog_samples: usize,
+}
+
+/// Paces the transcription loop.
+///
+/// The nominal interval is the configured `audio_step_size`. It is stretched
+/// when inference is slower than that, doubled per consecutive silent step
+/// up to `MAX_SILENCE_BACKOFF` base steps, and collapsed to zero when more
+/// than one step of audio is already waiting so the loop catches up.
+#[derive(Debug)]
+pub struct StepScheduler {
+    base_step: Duration,
+    smoothed_inference_ms: Option<f64>,
+    silent_steps: u32,
+    last_step_at: Option<Instant>,
+    smoothed_interval_ms: Option<f64>,
+    lag_ms: f64,
+    next_delay: Duration,
+}
+
+impl StepScheduler {
+    pub fn new(base_step: Duration) -> Self {
+        Self {
+            base_step,
+            smoothed_inference_ms: None,
+            silent_steps: 0,
+            last_step_at: None,
+            smoothed_interval_ms: None,
+            lag_ms: 0.0,
+            next_delay: base_step,
+        }
+    }
+
+    pub fn set_base_step(&mut self, base_step: Duration) {
+        self.base_step = base_step;
+    }
+
+    /// How long to wait before the next step.
+    pub fn next_delay(&self) -> Duration {
+        self.next_delay
+    }
+
+    /// Whether the segment has enough audio, and enough new audio since the
+    /// last pass, to be worth an inference run.
+    pub fn should_infer(&self, segment_samples: usize, new_samples: usize) -> bool {
+        new_samples > 0 && samples_to_ms(segment_samples) >= MIN_INFERENCE_AUDIO_MS as f64
+    }
+
+    /// Records a finished step started at `started` and plans the next one.
+    pub fn finish_step(&mut self, started: Instant, outcome: StepOutcome) -> Duration {
+        if let Some(last) = self.last_step_at {
+            let interval_ms = started.duration_since(last).as_secs_f64() * 1000.0;
+            self.smoothed_interval_ms = Some(smooth(self.smoothed_interval_ms, interval_ms));
+        }
+        self.last_step_at = Some(started);
+
+        if let Some(inference_ms) = outcome.inference_ms {
+            self.smoothed_inference_ms = Some(smooth(self.smoothed_inference_ms, inference_ms));
+        }
+        if outcome.new_samples == 0 {
+            self.silent_steps = self.silent_steps.saturating_add(1);
+        } else {
+            self.silent_steps = 0;
+        }
+        self.lag_ms = samples_to_ms(outcome.backlog_samples);
+
+        let base_ms = self.base_step.as_secs_f64() * 1000.0;
+        self.next_delay = if self.lag_ms > base_ms {
+            Duration::ZERO
+        } else {
+            let backoff = 2_u32
+                .saturating_pow(self.silent_steps)
+                .min(MAX_SILENCE_BACKOFF);
+            let interval = self
+                .base_step
+                .mul_f64(backoff as f64)
+                .max(Duration::from_secs_f64(
+                    self.smoothed_inference_ms.unwrap_or(0.0) * INFERENCE_HEADROOM / 1000.0,
+                ));
+            interval.saturating_sub(started.elapsed())
+        };
+        self.next_delay
+    }
+
+    pub fn update_metrics(&self, metrics: &mut AudioMetricsState) {
+        metrics.effective_step_rate_hz = self
+            .smoothed_interval_ms
+            .filter(|interval_ms| *interval_ms > 0.0)
+            .map(|interval_ms| 1000.0 / interval_ms)
+            .unwrap_or(0.0);
+        metrics.step_lag_ms = self.lag_ms;
+        metrics.next_step_delay_ms = self.next_delay.as_secs_f64() * 1000.0;
+    }
+}
+
+fn smooth(previous: Option<f64>, value: f64) -> f64 {
+    match previous {
+        Some(previous) => previous + SMOOTHING * (value - previous),
+        None => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(500);
+
+    fn samples_for_ms(ms: u64) -> usize {
+        (ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+    }
+
+    fn spoken_step(inference_ms: f64, backlog_ms: u64) -> StepOutcome {
+        StepOutcome {
+            new_samples: samples_for_ms(500),
+            inference_ms: Some(inference_ms),
+            backlog_samples: samples_for_ms(backlog_ms),
+        }
+    }
+
+    #[test]
+    fn requires_new_audio_and_a_minimum_segment_to_infer() {
+        let scheduler = StepScheduler::new(STEP);
+
+        assert!(!scheduler.should_infer(samples_for_ms(100), samples_for_ms(100)));
+        assert!(!scheduler.should_infer(samples_for_ms(1000), 0));
+        assert!(scheduler.should_infer(samples_for_ms(300), samples_for_ms(50)));
+    }
+
+    #[test]
+    fn keeps_base_step_when_inference_is_fast() {
+        let mut scheduler = StepScheduler::new(STEP);
+
+        let delay = scheduler.finish_step(Instant::now(), spoken_step(50.0, 0));
+
+        assert!(delay <= STEP && delay > STEP - Duration::from_millis(50));
+    }
+
+    #[test]
+    fn stretches_interval_when_inference_is_slow() {
+        let mut scheduler = StepScheduler::new(STEP);
+
+        let delay = scheduler.finish_step(Instant::now(), spoken_step(800.0, 0));
+
+        assert!(delay > Duration::from_millis(950), "{:?}", delay);
+    }
+
+    #[test]
+    fn runs_immediately_when_behind() {
+        let mut scheduler = StepScheduler::new(STEP);
+
+        let delay = scheduler.finish_step(Instant::now(), spoken_step(800.0, 900));
+
+        assert_eq!(delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn backs_off_during_silence_up_to_a_limit() {
+        let mut scheduler = StepScheduler::new(STEP);
+        let silent = StepOutcome::default();
+
+        let first = scheduler.finish_step(Instant::now(), silent);
+        let second = scheduler.finish_step(Instant::now(), silent);
+        for _ in 0..10 {
+            scheduler.finish_step(Instant::now(), silent);
+        }
+        let capped = scheduler.next_delay();
+        let resumed = scheduler.finish_step(Instant::now(), spoken_step(50.0, 0));
+
+        assert!(first > Duration::from_millis(950));
+        assert!(second > Duration::from_millis(1950));
+        assert!(capped <= STEP * MAX_SILENCE_BACKOFF);
+        assert!(resumed <= STEP);
+    }
+
+    #[test]
+    fn reports_step_rate_and_lag() {
+        let mut scheduler = StepScheduler::new(STEP);
+        let start = Instant::now();
+        let mut metrics = AudioMetricsState::default();
+
+        scheduler.finish_step(start, spoken_step(50.0, 0));
+        scheduler.finish_step(start + Duration::from_millis(250), spoken_step(50.0, 100));
+        scheduler.update_metrics(&mut metrics);
+
+        assert!((metrics.effective_step_rate_hz - 4.0).abs() < 1e-9);
+        assert!((metrics.step_lag_ms - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,674 @@
+use std::fmt::Write;
+
+use crate::{
+    scc,
+    whisper::{WhisperText, SPEAKER_CHANGE_MARKER},
+};
+
+/// Shortest time a cue is kept on screen, unless the next cue starts sooner.
+const MIN_CUE_DISPLAY_MS: u64 = 700;
+/// Silence longer than this between words always starts a new cue.
+const MAX_CUE_GAP_MS: u64 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    /// TTML 1, also accepted where DFXP is asked for.
+    Ttml,
+    /// YouTube's SubViewer format.
+    Sbv,
+    /// Scenarist CEA-608 pop-on captions.
+    Scc,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::WebVtt => "vtt",
+            SubtitleFormat::Ttml => "ttml",
+            SubtitleFormat::Sbv => "sbv",
+            SubtitleFormat::Scc => "scc",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "application/x-subrip",
+            SubtitleFormat::WebVtt => "text/vtt",
+            SubtitleFormat::Ttml => "application/ttml+xml",
+            SubtitleFormat::Sbv | SubtitleFormat::Scc => "text/plain",
+        }
+    }
+
+    /// Tightens `options` to what the format can display.
+    fn limit_options(&self, options: &CueOptions) -> CueOptions {
+        match self {
+            SubtitleFormat::Scc => CueOptions {
+                max_line_chars: options.max_line_chars.min(scc::MAX_ROW_CHARS),
+                max_lines: options.max_lines.min(scc::MAX_ROWS),
+                ..options.clone()
+            },
+            _ => options.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(default)]
+pub struct CueOptions {
+    pub max_line_chars: u32,
+    pub max_lines: u32,
+    pub max_duration_ms: u64,
+    /// Labels cues with "Speaker N", counting the speaker turns whisper
+    /// reports when tinydiarize is enabled.
+    pub speaker_labels: bool,
+}
+
+impl Default for CueOptions {
+    fn default() -> Self {
+        Self {
+            max_line_chars: 42,
+            max_lines: 2,
+            max_duration_ms: 7000,
+            speaker_labels: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Presentation for the formats that carry styling: TTML uses all of it,
+/// SCC only the alignment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleStyle {
+    pub align: SubtitleAlign,
+    pub font_size_px: i32,
+    /// `#rrggbb`
+    pub text_color: String,
+    /// `#rrggbb`
+    pub background_color: String,
+    /// 0-100
+    pub background_opacity: i32,
+    pub canvas_width: i32,
+    pub canvas_height: i32,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            align: SubtitleAlign::Center,
+            font_size_px: 44,
+            text_color: "#ffffff".to_owned(),
+            background_color: "#000000".to_owned(),
+            background_opacity: 55,
+            canvas_width: 1920,
+            canvas_height: 1080,
+        }
+    }
+}
+
+/// A finalized caption segment placed on the transcript's timeline. Item
+/// timestamps are whisper's centiseconds relative to `start_ms`.
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub items: Vec<WhisperText>,
+}
+
+/// One subtitle cue, shared by every export format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub speaker: Option<String>,
+    pub lines: Vec<String>,
+}
+
+impl Cue {
+    /// The cue's lines with the speaker label, if any, leading the first.
+    pub fn labelled_lines(&self) -> Vec<String> {
+        let mut lines = self.lines.clone();
+        if let (Some(speaker), Some(first)) = (&self.speaker, lines.first_mut()) {
+            *first = format!("{}: {}", speaker, first);
+        }
+        lines
+    }
+}
+
+pub fn export(
+    format: SubtitleFormat,
+    segments: &[TranscriptSegment],
+    options: &CueOptions,
+    style: &SubtitleStyle,
+) -> String {
+    let cues = build_cues(segments, &format.limit_options(options));
+    match format {
+        SubtitleFormat::Srt => write_srt(&cues),
+        SubtitleFormat::WebVtt => write_webvtt(&cues),
+        SubtitleFormat::Ttml => write_ttml(&cues, style),
+        SubtitleFormat::Sbv => write_sbv(&cues),
+        SubtitleFormat::Scc => scc::write_scc(&cues, style.align),
+    }
+}
+
+/// Splits a transcript into cues that respect the line, line count and
+/// duration limits in `options`, and never overlap.
+pub fn build_cues(segments: &[TranscriptSegment], options: &CueOptions) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut current: Option<CueBuilder> = None;
+    for word in timed_words(segments, options.speaker_labels) {
+        if let Some(cue) = current.take() {
+            if cue.accepts(&word, options) {
+                current = Some(cue);
+            } else {
+                cues.push(cue.finish());
+            }
+        }
+        current
+            .get_or_insert_with(|| CueBuilder::new(&word))
+            .push(word, options);
+    }
+    cues.extend(current.map(CueBuilder::finish));
+
+    remove_overlaps(&mut cues);
+    cues
+}
+
+pub fn write_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        let _ = writeln!(out, "{}", index + 1);
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            format_timestamp(cue.start_ms, ','),
+            format_timestamp(cue.end_ms, ',')
+        );
+        for line in cue.labelled_lines() {
+            let _ = writeln!(out, "{}", line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_webvtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            format_timestamp(cue.start_ms, '.'),
+            format_timestamp(cue.end_ms, '.')
+        );
+        let text = cue
+            .lines
+            .iter()
+            .map(|line| escape_webvtt(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match &cue.speaker {
+            Some(speaker) => {
+                let _ = writeln!(out, "<v {}>{}", escape_webvtt(speaker), text);
+            }
+            None => {
+                let _ = writeln!(out, "{}", text);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_ttml(cues: &[Cue], style: &SubtitleStyle) -> String {
+    let align = match style.align {
+        SubtitleAlign::Left => "left",
+        SubtitleAlign::Center => "center",
+        SubtitleAlign::Right => "right",
+    };
+    let background_alpha = (style.background_opacity.clamp(0, 100) * 255 + 50) / 100;
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<tt xmlns=\"http://www.w3.org/ns/ttml\" \
+         xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+         xml:lang=\"\" tts:extent=\"{}px {}px\">",
+        style.canvas_width, style.canvas_height
+    );
+    out.push_str("  <head>\n    <styling>\n");
+    let _ = writeln!(
+        out,
+        "      <style xml:id=\"caption\" tts:textAlign=\"{}\" tts:fontSize=\"{}px\" \
+         tts:color=\"{}\" tts:backgroundColor=\"{}{:02x}\"/>",
+        align,
+        style.font_size_px,
+        escape_xml(&style.text_color),
+        escape_xml(&style.background_color),
+        background_alpha
+    );
+    out.push_str("    </styling>\n  </head>\n  <body style=\"caption\">\n    <div>\n");
+    for cue in cues {
+        let text = cue
+            .labelled_lines()
+            .iter()
+            .map(|line| escape_xml(line))
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        let _ = writeln!(
+            out,
+            "      <p begin=\"{}\" end=\"{}\">{}</p>",
+            format_timestamp(cue.start_ms, '.'),
+            format_timestamp(cue.end_ms, '.'),
+            text
+        );
+    }
+    out.push_str("    </div>\n  </body>\n</tt>\n");
+    out
+}
+
+pub fn write_sbv(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for cue in cues {
+        let _ = writeln!(
+            out,
+            "{},{}",
+            format_sbv_timestamp(cue.start_ms),
+            format_sbv_timestamp(cue.end_ms)
+        );
+        for line in cue.labelled_lines() {
+            let _ = writeln!(out, "{}", line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `hh:mm:ss<separator>mmm`, as used by SRT (`,`), WebVTT and TTML (`.`).
+fn format_timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// `h:mm:ss.mmm`; SBV doesn't pad the hours.
+fn format_sbv_timestamp(ms: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    escape_webvtt(text)
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_webvtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TimedWord {
+    text: String,
+    start_ms: u64,
+    end_ms: u64,
+    speaker: Option<u32>,
+}
+
+/// Places every word on the transcript timeline. Whisper only times whole
+/// items, so an item's duration is shared among its words by length.
+fn timed_words(segments: &[TranscriptSegment], speaker_labels: bool) -> Vec<TimedWord> {
+    let mut speaker = speaker_labels.then_some(1);
+    let mut words = Vec::new();
+    for segment in segments {
+        let end_ms = segment.end_ms.max(segment.start_ms);
+        let place =
+            |centiseconds: i64| (segment.start_ms + centiseconds.max(0) as u64 * 10).min(end_ms);
+        for item in &segment.items {
+            if item.text.trim() == SPEAKER_CHANGE_MARKER {
+                speaker = speaker.map(|speaker| speaker + 1);
+                continue;
+            }
+            let item_words: Vec<&str> = item.text.split_whitespace().collect();
+            if item_words.is_empty() {
+                continue;
+            }
+            let item_start = place(item.start_time);
+            let item_end = place(item.end_time).max(item_start);
+            let total_chars: usize = item_words.iter().map(|word| word.chars().count()).sum();
+            let mut chars_before = 0;
+            for word in item_words {
+                let at = |chars: usize| {
+                    item_start + (item_end - item_start) * chars as u64 / total_chars as u64
+                };
+                let start_ms = at(chars_before);
+                chars_before += word.chars().count();
+                words.push(TimedWord {
+                    text: word.to_owned(),
+                    start_ms,
+                    end_ms: at(chars_before),
+                    speaker,
+                });
+            }
+        }
+    }
+    words
+}
+
+struct CueBuilder {
+    start_ms: u64,
+    end_ms: u64,
+    speaker: Option<u32>,
+    lines: Vec<String>,
+}
+
+impl CueBuilder {
+    fn new(word: &TimedWord) -> Self {
+        Self {
+            start_ms: word.start_ms,
+            end_ms: word.end_ms,
+            speaker: word.speaker,
+            lines: Vec::new(),
+        }
+    }
+
+    fn accepts(&self, word: &TimedWord, options: &CueOptions) -> bool {
+        word.speaker == self.speaker
+            && word.start_ms.saturating_sub(self.end_ms) <= MAX_CUE_GAP_MS
+            && word.end_ms.saturating_sub(self.start_ms) <= options.max_duration_ms
+            && (self.fits_on_last_line(&word.text, options)
+                || self.lines.len() < options.max_lines.max(1) as usize)
+    }
+
+    fn push(&mut self, word: TimedWord, options: &CueOptions) {
+        self.end_ms = self.end_ms.max(word.end_ms);
+        if self.lines.is_empty() || !self.fits_on_last_line(&word.text, options) {
+            self.lines.push(word.text);
+        } else if let Some(line) = self.lines.last_mut() {
+            line.push(' ');
+            line.push_str(&word.text);
+        }
+    }
+
+    fn fits_on_last_line(&self, word: &str, options: &CueOptions) -> bool {
+        let Some(line) = self.lines.last() else {
+            return true;
+        };
+        // The speaker label shares the first line in every format.
+        let label_chars = match self.speaker {
+            Some(speaker) if self.lines.len() == 1 => speaker_label(speaker).chars().count() + 2,
+            _ => 0,
+        };
+        label_chars + line.chars().count() + 1 + word.chars().count()
+            <= options.max_line_chars as usize
+    }
+
+    fn finish(self) -> Cue {
+        Cue {
+            start_ms: self.start_ms,
+            end_ms: self.end_ms,
+            speaker: self.speaker.map(speaker_label),
+            lines: self.lines,
+        }
+    }
+}
+
+fn speaker_label(speaker: u32) -> String {
+    format!("Speaker {}", speaker)
+}
+
+/// Starts every cue at or after the end of the previous one, and stretches
+/// short cues towards `MIN_CUE_DISPLAY_MS` only as far as the next cue.
+fn remove_overlaps(cues: &mut [Cue]) {
+    let mut previous_end = 0;
+    for index in 0..cues.len() {
+        let next_start = cues.get(index + 1).map(|next| next.start_ms);
+        let cue = &mut cues[index];
+        cue.start_ms = cue.start_ms.max(previous_end);
+        let min_end = cue.start_ms + MIN_CUE_DISPLAY_MS;
+        let limit = next_start.map_or(min_end, |next_start| next_start.min(min_end));
+        cue.end_ms = cue.end_ms.max(limit).max(cue.start_ms + 1);
+        if let Some(next_start) = next_start {
+            if cue.end_ms > next_start && next_start > cue.start_ms {
+                cue.end_ms = next_start;
+            }
+        }
+        previous_end = cue.end_ms;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(start_time: i64, end_time: i64, text: &str) -> WhisperText {
+        WhisperText {
+            index: 0,
+            start_time,
+            end_time,
+            text: text.to_owned(),
+        }
+    }
+
+    fn segment(start_ms: u64, end_ms: u64, items: Vec<WhisperText>) -> TranscriptSegment {
+        TranscriptSegment {
+            start_ms,
+            end_ms,
+            items,
+        }
+    }
+
+    #[test]
+    fn writes_srt_and_webvtt() {
+        let segments = vec![segment(
+            1_000,
+            16_000,
+            vec![
+                text(0, 250, " Hello there."),
+                text(300, 450, " Fish & chips"),
+            ],
+        )];
+
+        let srt = export(
+            SubtitleFormat::Srt,
+            &segments,
+            &CueOptions::default(),
+            &SubtitleStyle::default(),
+        );
+        let vtt = export(
+            SubtitleFormat::WebVtt,
+            &segments,
+            &CueOptions::default(),
+            &SubtitleStyle::default(),
+        );
+
+        assert_eq!(
+            srt,
+            "1\n00:00:01,000 --> 00:00:05,500\nHello there. Fish & chips\n\n"
+        );
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\n00:00:01.000 --> 00:00:05.500\nHello there. Fish &amp; chips\n\n"
+        );
+    }
+
+    #[test]
+    fn splits_cues_by_line_length_and_line_count() {
+        let options = CueOptions {
+            max_line_chars: 12,
+            max_lines: 2,
+            ..Default::default()
+        };
+        let segments = vec![segment(
+            0,
+            15_000,
+            vec![text(0, 400, "one two three four five six seven")],
+        )];
+
+        let cues = build_cues(&segments, &options);
+
+        let lines: Vec<Vec<String>> = cues.iter().map(|cue| cue.lines.clone()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec!["one two".to_owned(), "three four".to_owned()],
+                vec!["five six".to_owned(), "seven".to_owned()],
+            ]
+        );
+        assert!(cues[0].end_ms <= cues[1].start_ms);
+    }
+
+    #[test]
+    fn splits_cues_by_duration() {
+        let options = CueOptions {
+            max_duration_ms: 2_000,
+            ..Default::default()
+        };
+        let segments = vec![segment(0, 15_000, vec![text(0, 600, "a b c d e f")])];
+
+        let cues = build_cues(&segments, &options);
+
+        assert_eq!(cues.len(), 3);
+        assert!(cues
+            .iter()
+            .all(|cue| cue.end_ms - cue.start_ms <= options.max_duration_ms));
+    }
+
+    #[test]
+    fn cues_never_overlap() {
+        let segments = vec![
+            segment(0, 1_000, vec![text(0, 500, "first"), text(0, 0, "late")]),
+            segment(1_000, 2_000, vec![text(0, 10, "second")]),
+            segment(5_000, 5_000, vec![text(0, 0, "third")]),
+            segment(5_000, 9_000, vec![text(0, 0, "fourth")]),
+        ];
+
+        let options = CueOptions {
+            max_line_chars: 5,
+            max_lines: 1,
+            ..Default::default()
+        };
+
+        let cues = build_cues(&segments, &options);
+
+        assert_eq!(cues.len(), 5);
+        for pair in cues.windows(2) {
+            assert!(pair[0].end_ms <= pair[1].start_ms, "{:?}", pair);
+        }
+        assert!(cues.iter().all(|cue| cue.end_ms > cue.start_ms));
+    }
+
+    #[test]
+    fn labels_speakers_and_splits_on_turns() {
+        let options = CueOptions {
+            speaker_labels: true,
+            ..Default::default()
+        };
+        let segments = vec![segment(
+            0,
+            10_000,
+            vec![
+                text(0, 100, "Are you there?"),
+                text(0, 0, SPEAKER_CHANGE_MARKER),
+                text(100, 200, "Yes <here>"),
+            ],
+        )];
+
+        let cues = build_cues(&segments, &options);
+
+        assert_eq!(
+            write_srt(&cues),
+            "1\n00:00:00,000 --> 00:00:01,000\nSpeaker 1: Are you there?\n\n\
+             2\n00:00:01,000 --> 00:00:02,000\nSpeaker 2: Yes <here>\n\n"
+        );
+        assert!(write_webvtt(&cues).contains("<v Speaker 2>Yes &lt;here&gt;\n"));
+    }
+
+    fn golden_transcript() -> Vec<TranscriptSegment> {
+        vec![
+            segment(
+                0,
+                15_000,
+                vec![
+                    text(0, 300, " Welcome back to the stream, everyone."),
+                    text(
+                        350,
+                        700,
+                        " Today we’re building a caption exporter for the VOD.",
+                    ),
+                ],
+            ),
+            segment(
+                15_000,
+                30_000,
+                vec![
+                    text(100, 400, " It handles SRT, WebVTT & TTML <finally>."),
+                    text(0, 0, SPEAKER_CHANGE_MARKER),
+                    text(500, 650, " Does it do SCC too?"),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn matches_golden_files() {
+        let goldens = [
+            (
+                SubtitleFormat::Srt,
+                include_str!("../../../fixtures/subtitles/session.srt"),
+            ),
+            (
+                SubtitleFormat::WebVtt,
+                include_str!("../../../fixtures/subtitles/session.vtt"),
+            ),
+            (
+                SubtitleFormat::Ttml,
+                include_str!("../../../fixtures/subtitles/session.ttml"),
+            ),
+            (
+                SubtitleFormat::Sbv,
+                include_str!("../../../fixtures/subtitles/session.sbv"),
+            ),
+            (
+                SubtitleFormat::Scc,
+                include_str!("../../../fixtures/subtitles/session.scc"),
+            ),
+        ];
+        let style = SubtitleStyle {
+            align: SubtitleAlign::Left,
+            ..Default::default()
+        };
+
+        for (format, expected) in goldens {
+            let actual = export(format, &golden_transcript(), &CueOptions::default(), &style);
+            assert_eq!(actual, expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn formats_long_timestamps() {
+        assert_eq!(format_timestamp(3_723_004, ','), "01:02:03,004");
+        assert_eq!(format_timestamp(0, '.'), "00:00:00.000");
+    }
+}
@@ -1,10 +1,10 @@
-use std::{fs, path::Path};
+use std::{fs, io::Read, path::Path};
 
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    audio::{mono_from_interleaved, resample_audio, WHISPER_SAMPLE_RATE},
+    wav::decode_wav,
     whisper::{WhisperParams, WhisperText},
 };
 
@@ -71,61 +71,37 @@ pub struct TranscriptComparison {
 
 pub fn load_wav_fixture(path: impl AsRef<Path>) -> Result<AudioFixture, anyhow::Error> {
     let path = path.as_ref();
-    let mut reader = hound::WavReader::open(path)
+    let reader = hound::WavReader::open(path)
         .with_context(|| format!("failed to open WAV fixture {}", path.display()))?;
-    let spec = reader.spec();
-
-    let interleaved = match spec.sample_format {
-        hound::SampleFormat::Float => reader
-            .samples::<f32>()
-            .collect::<Result<Vec<_>, _>>()
-            .context("failed to read float WAV samples")?,
-        hound::SampleFormat::Int if spec.bits_per_sample <= 16 => reader
-            .samples::<i16>()
-            .map(|sample| sample.map(|s| s as f32 / i16::MAX as f32))
-            .collect::<Result<Vec<_>, _>>()
-            .context("failed to read 16-bit WAV samples")?,
-        hound::SampleFormat::Int if spec.bits_per_sample <= 32 => {
-            let scale = ((1_i64 << (spec.bits_per_sample - 1)) - 1) as f32;
-            reader
-                .samples::<i32>()
-                .map(|sample| sample.map(|s| s as f32 / scale))
-                .collect::<Result<Vec<_>, _>>()
-                .context("failed to read integer WAV samples")?
-        }
-        _ => {
-            return Err(anyhow!(
-                "unsupported WAV sample format: {:?} {} bits",
-                spec.sample_format,
-                spec.bits_per_sample
-            ))
-        }
-    };
-
-    let mono = mono_from_interleaved(&interleaved, spec.channels);
-    let samples = if spec.sample_rate == WHISPER_SAMPLE_RATE {
-        mono
-    } else {
-        resample_audio(&mono, spec.sample_rate, WHISPER_SAMPLE_RATE, 1)
-    };
-
-    let duration_ms = if spec.sample_rate == 0 || spec.channels == 0 {
-        0
-    } else {
-        ((interleaved.len() as f64 / spec.channels as f64) / spec.sample_rate as f64 * 1000.0)
-            .round() as u64
-    };
+    decode_wav_fixture(reader, path.display().to_string())
+}
+
+/// Decodes an in-memory WAV file, e.g. a fixture embedded with `include_bytes!`.
+pub fn load_wav_fixture_from_bytes(
+    bytes: &[u8],
+    label: impl Into<String>,
+) -> Result<AudioFixture, anyhow::Error> {
+    let label = label.into();
+    let reader = hound::WavReader::new(bytes)
+        .with_context(|| format!("failed to open WAV fixture {}", label))?;
+    decode_wav_fixture(reader, label)
+}
 
+fn decode_wav_fixture<R: Read>(
+    reader: hound::WavReader<R>,
+    label: String,
+) -> Result<AudioFixture, anyhow::Error> {
+    let decoded = decode_wav(reader)?;
     Ok(AudioFixture {
         metadata: AudioMetadata {
-            path: path.display().to_string(),
-            source_sample_rate: spec.sample_rate,
-            source_channels: spec.channels,
-            source_bits_per_sample: spec.bits_per_sample,
-            sample_count: samples.len(),
-            duration_ms,
+            path: label,
+            source_sample_rate: decoded.source_sample_rate,
+            source_channels: decoded.source_channels,
+            source_bits_per_sample: decoded.source_bits_per_sample,
+            sample_count: decoded.samples.len(),
+            duration_ms: decoded.duration_ms,
         },
-        samples,
+        samples: decoded.samples,
     })
 }
 
@@ -277,4 +253,16 @@ mod tests {
 
         assert!(!comparison.passed);
     }
+
+    #[test]
+    fn decodes_embedded_wav_fixture() {
+        let bytes = include_bytes!("../../../fixtures/transcription/basic_en_short.wav");
+
+        let fixture = load_wav_fixture_from_bytes(bytes, "basic_en_short.wav").unwrap();
+
+        assert_eq!(fixture.metadata.path, "basic_en_short.wav");
+        assert_eq!(fixture.metadata.source_sample_rate, 22_050);
+        assert_eq!(fixture.metadata.duration_ms, 3164);
+        assert!(!fixture.samples.is_empty());
+    }
 }
@@ -0,0 +1,113 @@
+use std::io::Read;
+
+use anyhow::{anyhow, Context};
+
+use crate::audio::{mono_from_interleaved, resample_audio, WHISPER_SAMPLE_RATE};
+
+/// Audio decoded from a WAV file and converted to Whisper's 16kHz mono.
+#[derive(Debug, Clone)]
+pub struct DecodedWav {
+    pub samples: Vec<f32>,
+    pub source_sample_rate: u32,
+    pub source_channels: u16,
+    pub source_bits_per_sample: u16,
+    pub duration_ms: u64,
+}
+
+/// Decodes a WAV file held in memory, such as an upload. Only uncompressed
+/// integer or float PCM in a WAV container is supported; MP3, FLAC, Ogg and
+/// other formats have to be converted first.
+pub fn decode_wav_bytes(bytes: &[u8]) -> Result<DecodedWav, anyhow::Error> {
+    let reader = hound::WavReader::new(bytes).context("not a WAV file")?;
+    decode_wav(reader)
+}
+
+pub fn decode_wav<R: Read>(mut reader: hound::WavReader<R>) -> Result<DecodedWav, anyhow::Error> {
+    let spec = reader.spec();
+
+    let interleaved = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to read float WAV samples")?,
+        hound::SampleFormat::Int if spec.bits_per_sample <= 16 => reader
+            .samples::<i16>()
+            .map(|sample| sample.map(|s| s as f32 / i16::MAX as f32))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to read 16-bit WAV samples")?,
+        hound::SampleFormat::Int if spec.bits_per_sample <= 32 => {
+            let scale = ((1_i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()
+                .context("failed to read integer WAV samples")?
+        }
+        _ => {
+            return Err(anyhow!(
+                "unsupported WAV sample format: {:?} {} bits",
+                spec.sample_format,
+                spec.bits_per_sample
+            ))
+        }
+    };
+
+    let mono = mono_from_interleaved(&interleaved, spec.channels);
+    let samples = if spec.sample_rate == WHISPER_SAMPLE_RATE {
+        mono
+    } else {
+        resample_audio(&mono, spec.sample_rate, WHISPER_SAMPLE_RATE, 1)
+    };
+
+    let duration_ms = if spec.sample_rate == 0 || spec.channels == 0 {
+        0
+    } else {
+        ((interleaved.len() as f64 / spec.channels as f64) / spec.sample_rate as f64 * 1000.0)
+            .round() as u64
+    };
+
+    Ok(DecodedWav {
+        samples,
+        source_sample_rate: spec.sample_rate,
+        source_channels: spec.channels,
+        source_bits_per_sample: spec.bits_per_sample,
+        duration_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn decodes_stereo_pcm_to_mono() {
+        let mut bytes = Cursor::new(Vec::new());
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: WHISPER_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for _ in 0..WHISPER_SAMPLE_RATE / 2 {
+            writer.write_sample(i16::MAX).unwrap();
+            writer.write_sample(0_i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let decoded = decode_wav_bytes(bytes.get_ref()).unwrap();
+
+        assert_eq!(decoded.duration_ms, 500);
+        assert_eq!(decoded.source_channels, 2);
+        assert_eq!(decoded.samples.len(), WHISPER_SAMPLE_RATE as usize / 2);
+        assert_eq!(decoded.samples[0], 0.5);
+    }
+
+    #[test]
+    fn refuses_files_that_arent_wav() {
+        let err = decode_wav_bytes(b"ID3\x04\x00\x00\x00\x00\x00\x00").unwrap_err();
+
+        assert!(format!("{:#}", err).contains("not a WAV file"));
+    }
+}
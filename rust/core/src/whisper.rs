@@ -1,11 +1,20 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 #[cfg(feature = "whisper-runtime")]
 use std::time::SystemTime;
 
+#[cfg(feature = "whisper-runtime")]
+use crate::audio::WHISPER_SAMPLE_RATE;
 #[cfg(feature = "whisper-runtime")]
 use tracing::debug;
 #[cfg(feature = "whisper-runtime")]
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
+/// Item text inserted where tinydiarize detects a change of speaker.
+pub const SPEAKER_CHANGE_MARKER: &str = "<Speaker change>";
+
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize, Clone, specta::Type)]
 #[serde(default)]
 pub struct WhisperText {
@@ -67,11 +76,61 @@ impl Default for WhisperToggles {
     }
 }
 
+/// Shared handle used to abort in-flight inference from another thread.
+///
+/// Cancelling bumps a generation counter, so only inference that started
+/// before the call is aborted; later calls run normally.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    generation: Arc<AtomicU64>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn checkpoint(&self) -> CancellationCheckpoint {
+        CancellationCheckpoint {
+            generation: self.generation.clone(),
+            started_at: self.generation.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CancellationCheckpoint {
+    generation: Arc<AtomicU64>,
+    started_at: u64,
+}
+
+impl CancellationCheckpoint {
+    pub fn is_cancelled(&self) -> bool {
+        self.generation.load(Ordering::SeqCst) != self.started_at
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InferenceCancelled;
+
+impl std::fmt::Display for InferenceCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "inference was cancelled")
+    }
+}
+
+impl std::error::Error for InferenceCancelled {}
+
+pub fn is_cancelled_error(err: &anyhow::Error) -> bool {
+    err.is::<InferenceCancelled>()
+}
+
 #[cfg(feature = "whisper-runtime")]
 pub struct WhisperManager {
     ctx: WhisperContext,
     last_prompt: String,
     segment_index: u64,
+    cancellation: CancellationToken,
 }
 
 #[cfg(feature = "whisper-runtime")]
@@ -93,13 +152,67 @@ impl WhisperManager {
             ctx,
             last_prompt: "".to_owned(),
             segment_index: 0,
+            cancellation: CancellationToken::default(),
         })
     }
 
+    pub fn with_cancellation_token(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Runs one pass over a second of silence so the first real inference
+    /// doesn't pay for backend kernel compilation and buffer allocation.
+    pub fn warm_up(&mut self) -> Result<(), anyhow::Error> {
+        let mut full_params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        full_params.set_print_special(false);
+        full_params.set_print_progress(false);
+        full_params.set_single_segment(true);
+        full_params.set_language(Some("en"));
+
+        let checkpoint = self.cancellation.checkpoint();
+        set_abort_checkpoint(&mut full_params, &checkpoint);
+
+        let silence = vec![0.0_f32; WHISPER_SAMPLE_RATE as usize];
+        let start = SystemTime::now();
+
+        let mut state = self.ctx.create_state()?;
+        let result = state.full(full_params, &silence[..]);
+        if checkpoint.is_cancelled() {
+            return Err(InferenceCancelled.into());
+        }
+        result?;
+
+        debug!(
+            "Warm-up took {}ms",
+            start.elapsed().unwrap_or_default().as_millis()
+        );
+        Ok(())
+    }
+
     pub fn process_samples(
         &mut self,
         samples: Vec<f32>,
         params: WhisperParams,
+    ) -> Result<Vec<WhisperText>, anyhow::Error> {
+        let cancellation = self.cancellation.clone();
+        self.process_samples_with_prompt(samples, params, None, &cancellation)
+    }
+
+    /// Like `process_samples`, with text that primes the decoder, such as
+    /// names and terms the audio is likely to contain. Aborted through
+    /// `cancellation` rather than the manager's own token, so work that
+    /// isn't live captioning can outlive a stop.
+    pub fn process_samples_with_prompt(
+        &mut self,
+        samples: Vec<f32>,
+        params: WhisperParams,
+        initial_prompt: Option<&str>,
+        cancellation: &CancellationToken,
     ) -> Result<Vec<WhisperText>, anyhow::Error> {
         let mut full_params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
 
@@ -118,11 +231,22 @@ impl WhisperManager {
         // if !self.last_prompt.is_empty() {
         //     full_params.set_initial_prompt(&self.last_prompt.clone());
         // }
+        if let Some(prompt) = initial_prompt.filter(|prompt| !prompt.is_empty()) {
+            full_params.set_initial_prompt(prompt);
+        }
+
+        let checkpoint = cancellation.checkpoint();
+        set_abort_checkpoint(&mut full_params, &checkpoint);
 
         let start = SystemTime::now();
 
         let mut state = self.ctx.create_state().expect("failed to create state");
-        state.full(full_params, &samples[..])?;
+        let result = state.full(full_params, &samples[..]);
+        if checkpoint.is_cancelled() {
+            debug!("inference cancelled");
+            return Err(InferenceCancelled.into());
+        }
+        result?;
 
         let end = SystemTime::now();
 
@@ -159,7 +283,7 @@ impl WhisperManager {
                     index: 0,
                     start_time: 0,
                     end_time: 0,
-                    text: "<Speaker change>".to_owned(),
+                    text: SPEAKER_CHANGE_MARKER.to_owned(),
                 });
             }
         }
@@ -167,3 +291,56 @@ impl WhisperManager {
         Ok(segments)
     }
 }
+
+// whisper.cpp polls the abort callback between graph computations, so a
+// cancel lands within one encoder/decoder step instead of after the full run.
+// The caller must keep `checkpoint` alive until `WhisperState::full` returns,
+// which is the only place whisper.cpp dereferences the user data pointer.
+#[cfg(feature = "whisper-runtime")]
+fn set_abort_checkpoint(full_params: &mut FullParams, checkpoint: &CancellationCheckpoint) {
+    unsafe {
+        full_params.set_abort_callback(Some(abort_if_cancelled));
+        full_params.set_abort_callback_user_data(
+            checkpoint as *const CancellationCheckpoint as *mut std::ffi::c_void,
+        );
+    }
+}
+
+#[cfg(feature = "whisper-runtime")]
+unsafe extern "C" fn abort_if_cancelled(user_data: *mut std::ffi::c_void) -> bool {
+    let checkpoint = &*(user_data as *const CancellationCheckpoint);
+    checkpoint.is_cancelled()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_is_cancelled_after_cancel() {
+        let token = CancellationToken::default();
+        let checkpoint = token.checkpoint();
+
+        assert!(!checkpoint.is_cancelled());
+        token.cancel();
+        assert!(checkpoint.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_before_checkpoint_does_not_affect_later_inference() {
+        let token = CancellationToken::default();
+        token.cancel();
+
+        let checkpoint = token.checkpoint();
+
+        assert!(!checkpoint.is_cancelled());
+    }
+
+    #[test]
+    fn cancelled_error_is_detected_through_anyhow() {
+        let err: anyhow::Error = InferenceCancelled.into();
+
+        assert!(is_cancelled_error(&err));
+        assert!(!is_cancelled_error(&anyhow::anyhow!("other failure")));
+    }
+}
@@ -0,0 +1,307 @@
+use scrybe_core::{
+    audio::WHISPER_SAMPLE_RATE,
+    subtitles::{self, CueOptions, TranscriptSegment},
+    whisper::{WhisperParams, WhisperText, SPEAKER_CHANGE_MARKER},
+};
+use serde_json::json;
+use std::ops::Range;
+
+/// Audio handed to the model at a time. Matches whisper's own window, and
+/// bounds how long a live step can wait behind an upload.
+pub const CHUNK_SECS: u32 = 30;
+
+/// The `response_format`s of OpenAI's transcription API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl ResponseFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(ResponseFormat::Json),
+            "text" => Ok(ResponseFormat::Text),
+            "srt" => Ok(ResponseFormat::Srt),
+            "vtt" => Ok(ResponseFormat::Vtt),
+            "verbose_json" => Ok(ResponseFormat::VerboseJson),
+            _ => Err(format!(
+                "unsupported response_format {:?}; expected json, text, srt, vtt or verbose_json",
+                name
+            )),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json | ResponseFormat::VerboseJson => "application/json",
+            ResponseFormat::Text | ResponseFormat::Srt => "text/plain; charset=utf-8",
+            ResponseFormat::Vtt => "text/vtt; charset=utf-8",
+        }
+    }
+}
+
+/// The live captioning params adjusted for whole recordings: several
+/// segments per chunk, no translation, and the requested language.
+pub fn batch_params(live: &WhisperParams, language: Option<&str>) -> WhisperParams {
+    let mut params = live.clone();
+    params.language = language
+        .filter(|language| !language.is_empty())
+        .unwrap_or("auto")
+        .to_owned();
+    params.toggles.single_segment = false;
+    params.toggles.translate = false;
+    params.toggles.tdrz_enable = false;
+    params.toggles.print_special = false;
+    params.toggles.print_progress = false;
+    params
+}
+
+/// Splits 16kHz samples into the pieces transcribed one at a time.
+pub fn chunk_ranges(sample_count: usize) -> Vec<Range<usize>> {
+    let chunk_len = (WHISPER_SAMPLE_RATE * CHUNK_SECS) as usize;
+    (0..sample_count)
+        .step_by(chunk_len)
+        .map(|start| start..(start + chunk_len).min(sample_count))
+        .collect()
+}
+
+fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64
+}
+
+/// A transcribed upload, one transcript segment per chunk.
+#[derive(Debug, Clone, Default)]
+pub struct BatchTranscript {
+    /// As requested; whisper's detected language isn't reported back.
+    pub language: String,
+    pub duration_ms: u64,
+    pub chunks: Vec<TranscriptSegment>,
+}
+
+impl BatchTranscript {
+    pub fn push_chunk(&mut self, range: Range<usize>, items: Vec<WhisperText>) {
+        self.chunks.push(TranscriptSegment {
+            start_ms: samples_to_ms(range.start),
+            end_ms: samples_to_ms(range.end),
+            items,
+        });
+    }
+
+    /// The prompt for the next chunk: the caller's, followed by what the
+    /// last chunk said, so names and sentences carry across the cut. whisper
+    /// keeps the end of a prompt that's too long.
+    pub fn next_prompt(&self, prompt: Option<&str>) -> Option<String> {
+        let previous = self.chunks.last().map(|chunk| {
+            chunk
+                .items
+                .iter()
+                .map(|item| item.text.trim())
+                .filter(|text| !text.is_empty() && *text != SPEAKER_CHANGE_MARKER)
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        let parts: Vec<&str> = [prompt.map(str::trim), previous.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    pub fn text(&self) -> String {
+        self.spoken_items()
+            .map(|(_, _, text)| text)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn render(&self, format: ResponseFormat) -> String {
+        let cues = || subtitles::build_cues(&self.chunks, &CueOptions::default());
+        match format {
+            ResponseFormat::Json => json!({ "text": self.text() }).to_string(),
+            ResponseFormat::Text => format!("{}\n", self.text()),
+            ResponseFormat::Srt => subtitles::write_srt(&cues()),
+            ResponseFormat::Vtt => subtitles::write_webvtt(&cues()),
+            ResponseFormat::VerboseJson => self.verbose_json().to_string(),
+        }
+    }
+
+    /// OpenAI's verbose shape, with zeros for the decoder statistics whisper
+    /// doesn't expose here, so its client libraries can parse it.
+    fn verbose_json(&self) -> serde_json::Value {
+        let segments: Vec<_> = self
+            .spoken_items()
+            .enumerate()
+            .map(|(id, (start_ms, end_ms, text))| {
+                json!({
+                    "id": id,
+                    "seek": 0,
+                    "start": start_ms as f64 / 1000.0,
+                    "end": end_ms as f64 / 1000.0,
+                    "text": text,
+                    "tokens": [],
+                    "temperature": 0.0,
+                    "avg_logprob": 0.0,
+                    "compression_ratio": 0.0,
+                    "no_speech_prob": 0.0,
+                })
+            })
+            .collect();
+        json!({
+            "task": "transcribe",
+            "language": self.language,
+            "duration": self.duration_ms as f64 / 1000.0,
+            "text": self.text(),
+            "segments": segments,
+        })
+    }
+
+    /// Each item's start, end and text on the upload's timeline, leaving out
+    /// speaker change markers and blanks.
+    fn spoken_items(&self) -> impl Iterator<Item = (u64, u64, &str)> {
+        self.chunks.iter().flat_map(|chunk| {
+            let place = move |centiseconds: i64| {
+                (chunk.start_ms + centiseconds.max(0) as u64 * 10).min(chunk.end_ms)
+            };
+            chunk
+                .items
+                .iter()
+                .map(move |item| {
+                    (
+                        place(item.start_time),
+                        place(item.end_time),
+                        item.text.trim(),
+                    )
+                })
+                .filter(|(_, _, text)| !text.is_empty() && *text != SPEAKER_CHANGE_MARKER)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(start_time: i64, end_time: i64, text: &str) -> WhisperText {
+        WhisperText {
+            start_time,
+            end_time,
+            text: text.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn transcript() -> BatchTranscript {
+        let chunk_len = (WHISPER_SAMPLE_RATE * CHUNK_SECS) as usize;
+        let mut transcript = BatchTranscript {
+            language: "en".to_owned(),
+            duration_ms: 32_000,
+            chunks: Vec::new(),
+        };
+        transcript.push_chunk(
+            0..chunk_len,
+            vec![
+                item(0, 150, " Hello there."),
+                item(0, 0, SPEAKER_CHANGE_MARKER),
+            ],
+        );
+        transcript.push_chunk(
+            chunk_len..chunk_len + 2 * WHISPER_SAMPLE_RATE as usize,
+            vec![item(0, 120, " General Kenobi.")],
+        );
+        transcript
+    }
+
+    #[test]
+    fn chunks_cover_every_sample() {
+        let chunk_len = (WHISPER_SAMPLE_RATE * CHUNK_SECS) as usize;
+
+        assert_eq!(chunk_ranges(0), Vec::<Range<usize>>::new());
+        assert_eq!(
+            chunk_ranges(chunk_len + 1),
+            [0..chunk_len, chunk_len..chunk_len + 1]
+        );
+    }
+
+    #[test]
+    fn prompts_each_chunk_with_the_text_before_the_cut() {
+        let mut transcript = BatchTranscript::default();
+        assert_eq!(transcript.next_prompt(None), None);
+        assert_eq!(transcript.next_prompt(Some(" ")), None);
+        assert_eq!(
+            transcript.next_prompt(Some("Obi-Wan")).as_deref(),
+            Some("Obi-Wan")
+        );
+
+        let chunk_len = (WHISPER_SAMPLE_RATE * CHUNK_SECS) as usize;
+        transcript.push_chunk(
+            0..chunk_len,
+            vec![
+                item(0, 150, " Hello there."),
+                item(0, 0, SPEAKER_CHANGE_MARKER),
+                item(2900, 3000, " General"),
+            ],
+        );
+        assert_eq!(
+            transcript.next_prompt(None).as_deref(),
+            Some("Hello there. General")
+        );
+        assert_eq!(
+            transcript.next_prompt(Some("Obi-Wan")).as_deref(),
+            Some("Obi-Wan Hello there. General")
+        );
+
+        // Only the chunk just before the cut is carried.
+        transcript.push_chunk(chunk_len..2 * chunk_len, vec![item(0, 120, " Kenobi.")]);
+        assert_eq!(transcript.next_prompt(None).as_deref(), Some("Kenobi."));
+    }
+
+    #[test]
+    fn renders_every_response_format() {
+        let transcript = transcript();
+
+        assert_eq!(
+            transcript.render(ResponseFormat::Json),
+            r#"{"text":"Hello there. General Kenobi."}"#
+        );
+        assert_eq!(
+            transcript.render(ResponseFormat::Text),
+            "Hello there. General Kenobi.\n"
+        );
+        assert!(transcript
+            .render(ResponseFormat::Srt)
+            .contains("00:00:30,000 --> 00:00:31,200\nGeneral Kenobi."));
+        assert!(transcript
+            .render(ResponseFormat::Vtt)
+            .starts_with("WEBVTT\n\n00:00:00.000 --> "));
+
+        let verbose: serde_json::Value =
+            serde_json::from_str(&transcript.render(ResponseFormat::VerboseJson)).unwrap();
+        assert_eq!(verbose["duration"], 32.0);
+        assert_eq!(verbose["segments"][1]["start"], 30.0);
+        assert_eq!(verbose["segments"][1]["end"], 31.2);
+        assert_eq!(verbose["segments"][1]["text"], "General Kenobi.");
+    }
+
+    #[test]
+    fn adjusts_live_params_for_recordings() {
+        let mut live = WhisperParams::default();
+        live.language = "de".to_owned();
+        live.toggles.translate = true;
+
+        let params = batch_params(&live, None);
+        assert_eq!(params.language, "auto");
+        assert!(!params.toggles.single_segment && !params.toggles.translate);
+        assert_eq!(batch_params(&live, Some("fr")).language, "fr");
+    }
+
+    #[test]
+    fn rejects_unknown_response_formats() {
+        assert_eq!(ResponseFormat::parse("srt"), Ok(ResponseFormat::Srt));
+        assert!(ResponseFormat::parse("diarized_json").is_err());
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{model_selection::HardwareProfile, types::ModelPreset};
+use scrybe_core::{
+    audio::WHISPER_SAMPLE_RATE,
+    metrics::nearest_rank_percentile,
+    validation::{load_wav_fixture_from_bytes, AudioFixture},
+};
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info};
+
+pub const CALIBRATION_FILE: &str = "model_calibration.json";
+pub const CALIBRATION_RUNS: usize = 5;
+
+const FIXTURE_NAME: &str = "basic_en_short.wav";
+const FIXTURE: &[u8] = include_bytes!("../../fixtures/transcription/basic_en_short.wav");
+
+/// Identifies the machine a calibration was measured on; results are reused
+/// only while this stays the same.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct CalibrationFingerprint {
+    pub app_version: String,
+    pub accelerated_backend: bool,
+    pub logical_cpus: Option<u32>,
+    pub total_memory_gib: Option<u64>,
+}
+
+impl CalibrationFingerprint {
+    pub(crate) fn new(profile: HardwareProfile) -> Self {
+        Self {
+            app_version: env!("CARGO_PKG_VERSION").to_owned(),
+            accelerated_backend: profile.accelerated_backend,
+            logical_cpus: profile.logical_cpus.map(|cpus| cpus as u32),
+            total_memory_gib: profile.total_memory_gib,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct PresetBenchmark {
+    pub preset_id: String,
+    /// Time to load and warm up the model.
+    pub load_ms: f64,
+    pub inference_p95_ms: f64,
+    /// Mean inference time divided by the fixture duration.
+    pub real_time_factor: f64,
+    pub error: Option<String>,
+}
+
+impl PresetBenchmark {
+    pub fn failed(preset_id: &str, error: impl Into<String>) -> Self {
+        Self {
+            preset_id: preset_id.to_owned(),
+            load_ms: 0.0,
+            inference_p95_ms: 0.0,
+            real_time_factor: 0.0,
+            error: Some(error.into()),
+        }
+    }
+
+    fn fits(&self, audio_step_size_ms: u64) -> bool {
+        self.error.is_none() && self.inference_p95_ms <= audio_step_size_ms as f64
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct CalibrationReport {
+    pub fingerprint: CalibrationFingerprint,
+    pub audio_step_size_ms: u64,
+    pub fixture_duration_ms: u64,
+    pub benchmarks: Vec<PresetBenchmark>,
+    pub selected_preset_id: Option<String>,
+    pub calibrated_at_ms: u64,
+}
+
+impl CalibrationReport {
+    pub fn applies_to(
+        &self,
+        fingerprint: &CalibrationFingerprint,
+        audio_step_size_ms: u64,
+    ) -> bool {
+        self.fingerprint == *fingerprint && self.audio_step_size_ms == audio_step_size_ms
+    }
+}
+
+pub fn load_report(dir: &Path) -> Option<CalibrationReport> {
+    let contents = fs::read_to_string(dir.join(CALIBRATION_FILE)).ok()?;
+    serde_json::from_str(&contents)
+        .inspect_err(|err| debug!("ignoring unreadable calibration: {}", err))
+        .ok()
+}
+
+pub fn save_report(dir: &Path, report: &CalibrationReport) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(dir)?;
+    fs::write(
+        dir.join(CALIBRATION_FILE),
+        serde_json::to_string_pretty(report)?,
+    )?;
+    Ok(())
+}
+
+pub fn fixture() -> Result<AudioFixture, anyhow::Error> {
+    load_wav_fixture_from_bytes(FIXTURE, FIXTURE_NAME)
+}
+
+/// Measures one preset: how long `load` takes, then `CALIBRATION_RUNS`
+/// passes of `infer` over the fixture.
+pub fn benchmark_preset<M>(
+    preset_id: &str,
+    fixture: &AudioFixture,
+    load: impl FnOnce() -> Result<M, anyhow::Error>,
+    mut infer: impl FnMut(&mut M, Vec<f32>) -> Result<(), anyhow::Error>,
+) -> PresetBenchmark {
+    let load_started = Instant::now();
+    let mut model = match load() {
+        Ok(model) => model,
+        Err(err) => return PresetBenchmark::failed(preset_id, format!("load failed: {err}")),
+    };
+    let load_ms = load_started.elapsed().as_secs_f64() * 1000.0;
+
+    let mut durations = Vec::with_capacity(CALIBRATION_RUNS);
+    for _ in 0..CALIBRATION_RUNS {
+        let started = Instant::now();
+        if let Err(err) = infer(&mut model, fixture.samples.clone()) {
+            return PresetBenchmark::failed(preset_id, format!("inference failed: {err}"));
+        }
+        durations.push(started.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    let audio_ms = fixture.samples.len() as f64 * 1000.0 / WHISPER_SAMPLE_RATE as f64;
+    let mean_ms = durations.iter().sum::<f64>() / durations.len() as f64;
+    let benchmark = PresetBenchmark {
+        preset_id: preset_id.to_owned(),
+        load_ms,
+        inference_p95_ms: nearest_rank_percentile(&durations, 0.95),
+        real_time_factor: if audio_ms > 0.0 {
+            mean_ms / audio_ms
+        } else {
+            0.0
+        },
+        error: None,
+    };
+    info!("calibration benchmark: {:?}", benchmark);
+    benchmark
+}
+
+/// Calibration candidates: auto-selectable presets, cheapest first.
+pub fn candidates(presets: &[ModelPreset]) -> Vec<&ModelPreset> {
+    let mut candidates: Vec<_> = presets
+        .iter()
+        .filter(|preset| preset.auto_selectable)
+        .collect();
+    candidates.sort_by_key(|preset| preset.resource_rank);
+    candidates
+}
+
+/// Benchmarks candidates from cheapest to most expensive, skipping the rest
+/// once one no longer fits in the step size since larger models won't either.
+pub fn run_calibration(
+    candidates: &[&ModelPreset],
+    audio_step_size_ms: u64,
+    mut benchmark: impl FnMut(&ModelPreset) -> PresetBenchmark,
+) -> Vec<PresetBenchmark> {
+    let mut benchmarks = Vec::with_capacity(candidates.len());
+    let mut too_slow_rank = None;
+    for preset in candidates {
+        if too_slow_rank.is_some_and(|rank| preset.resource_rank > rank) {
+            benchmarks.push(PresetBenchmark::failed(
+                &preset.id,
+                "skipped: a smaller model already exceeded the step size",
+            ));
+            continue;
+        }
+
+        let result = benchmark(preset);
+        if result.error.is_none() && !result.fits(audio_step_size_ms) {
+            too_slow_rank.get_or_insert(preset.resource_rank);
+        }
+        benchmarks.push(result);
+    }
+    benchmarks
+}
+
+/// The largest preset whose p95 inference time fits inside the step size.
+pub fn select_calibrated_preset<'a>(
+    benchmarks: &[PresetBenchmark],
+    presets: &'a [ModelPreset],
+    audio_step_size_ms: u64,
+) -> Option<&'a ModelPreset> {
+    benchmarks
+        .iter()
+        .filter(|benchmark| benchmark.fits(audio_step_size_ms))
+        .filter_map(|benchmark| {
+            presets
+                .iter()
+                .find(|preset| preset.id == benchmark.preset_id)
+        })
+        .max_by_key(|preset| preset.resource_rank)
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Ensures only one calibration runs at a time.
+#[derive(Clone, Default)]
+pub struct CalibrationRunner {
+    running: Arc<AtomicBool>,
+}
+
+impl CalibrationRunner {
+    pub fn try_start(&self) -> Option<CalibrationRunGuard> {
+        self.running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| CalibrationRunGuard {
+                running: self.running.clone(),
+            })
+    }
+}
+
+pub struct CalibrationRunGuard {
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for CalibrationRunGuard {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        model_presets, BASE_MODEL_PRESET_ID, LARGE_V3_TURBO_Q5_MODEL_PRESET_ID,
+        TINY_MODEL_PRESET_ID,
+    };
+
+    fn benchmark(preset_id: &str, inference_p95_ms: f64) -> PresetBenchmark {
+        PresetBenchmark {
+            preset_id: preset_id.to_owned(),
+            load_ms: 10.0,
+            inference_p95_ms,
+            real_time_factor: 0.1,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn embedded_fixture_decodes() {
+        let fixture = fixture().unwrap();
+
+        assert!(!fixture.samples.is_empty());
+        assert!(fixture.metadata.duration_ms > 0);
+    }
+
+    #[test]
+    fn selects_largest_preset_whose_p95_fits_the_step() {
+        let presets = model_presets();
+        let benchmarks = vec![
+            benchmark(TINY_MODEL_PRESET_ID, 80.0),
+            benchmark(BASE_MODEL_PRESET_ID, 450.0),
+            benchmark(LARGE_V3_TURBO_Q5_MODEL_PRESET_ID, 900.0),
+        ];
+
+        let selected = select_calibrated_preset(&benchmarks, &presets, 500);
+        assert_eq!(
+            selected.map(|preset| preset.id.as_str()),
+            Some(BASE_MODEL_PRESET_ID)
+        );
+
+        let selected = select_calibrated_preset(&benchmarks, &presets, 1000);
+        assert_eq!(
+            selected.map(|preset| preset.id.as_str()),
+            Some(LARGE_V3_TURBO_Q5_MODEL_PRESET_ID)
+        );
+
+        assert!(select_calibrated_preset(&benchmarks, &presets, 50).is_none());
+    }
+
+    #[test]
+    fn failed_benchmarks_are_never_selected() {
+        let presets = model_presets();
+        let benchmarks = vec![
+            benchmark(TINY_MODEL_PRESET_ID, 80.0),
+            PresetBenchmark::failed(BASE_MODEL_PRESET_ID, "load failed"),
+        ];
+
+        let selected = select_calibrated_preset(&benchmarks, &presets, 500);
+
+        assert_eq!(
+            selected.map(|preset| preset.id.as_str()),
+            Some(TINY_MODEL_PRESET_ID)
+        );
+    }
+
+    #[test]
+    fn calibration_skips_larger_presets_after_one_is_too_slow() {
+        let presets = model_presets();
+        let candidates = candidates(&presets);
+        let mut measured = Vec::new();
+
+        let benchmarks = run_calibration(&candidates, 500, |preset| {
+            measured.push(preset.id.clone());
+            benchmark(
+                &preset.id,
+                if preset.resource_rank == 1 {
+                    100.0
+                } else {
+                    700.0
+                },
+            )
+        });
+
+        assert_eq!(measured, vec![TINY_MODEL_PRESET_ID, BASE_MODEL_PRESET_ID]);
+        assert_eq!(benchmarks.len(), 3);
+        assert!(benchmarks[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("skipped"));
+    }
+
+    #[test]
+    fn benchmark_measures_runs_and_reports_failures() {
+        let fixture = fixture().unwrap();
+        let mut runs = 0;
+
+        let result = benchmark_preset(
+            "fake",
+            &fixture,
+            || Ok(()),
+            |_, samples| {
+                assert_eq!(samples.len(), fixture.samples.len());
+                runs += 1;
+                Ok(())
+            },
+        );
+
+        assert_eq!(runs, CALIBRATION_RUNS);
+        assert!(result.error.is_none());
+        assert!(result.real_time_factor >= 0.0);
+
+        let failed = benchmark_preset(
+            "fake",
+            &fixture,
+            || Err::<(), _>(anyhow::anyhow!("boom")),
+            |_, _| Ok(()),
+        );
+        assert_eq!(failed.error.as_deref(), Some("load failed: boom"));
+    }
+
+    #[test]
+    fn stored_report_applies_only_to_same_hardware_and_step() {
+        let dir = tempfile::tempdir().unwrap();
+        let fingerprint = CalibrationFingerprint::new(HardwareProfile {
+            total_memory_gib: Some(16),
+            logical_cpus: Some(8),
+            accelerated_backend: true,
+        });
+        let report = CalibrationReport {
+            fingerprint: fingerprint.clone(),
+            audio_step_size_ms: 500,
+            fixture_duration_ms: 3164,
+            benchmarks: vec![benchmark(TINY_MODEL_PRESET_ID, 80.0)],
+            selected_preset_id: Some(TINY_MODEL_PRESET_ID.to_owned()),
+            calibrated_at_ms: now_ms(),
+        };
+
+        save_report(dir.path(), &report).unwrap();
+        let loaded = load_report(dir.path()).unwrap();
+
+        assert!(loaded.applies_to(&fingerprint, 500));
+        assert!(!loaded.applies_to(&fingerprint, 250));
+        let other = CalibrationFingerprint {
+            accelerated_backend: false,
+            ..fingerprint
+        };
+        assert!(!loaded.applies_to(&other, 500));
+    }
+
+    #[test]
+    fn only_one_calibration_runs_at_a_time() {
+        let runner = CalibrationRunner::default();
+
+        let guard = runner.try_start();
+        assert!(guard.is_some());
+        assert!(runner.try_start().is_none());
+
+        drop(guard);
+        assert!(runner.try_start().is_some());
+    }
+}
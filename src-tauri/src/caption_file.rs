@@ -0,0 +1,256 @@
+use anyhow::Context;
+use scrybe_core::whisper::{WhisperSegment, SPEAKER_CHANGE_MARKER};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+/// Writes the live caption to a text file for OBS, vMix and other tools
+/// that can show a file but not host a browser source.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(default)]
+pub struct CaptionFileSettings {
+    pub enabled: bool,
+    pub path: String,
+    /// Only the most recent lines are kept.
+    pub max_lines: u32,
+    pub max_line_chars: u32,
+    /// Empties the file after this much silence; 0 leaves the last caption up.
+    pub clear_after_silence_secs: u32,
+}
+
+impl Default for CaptionFileSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: String::new(),
+            max_lines: 2,
+            max_line_chars: 42,
+            clear_after_silence_secs: 0,
+        }
+    }
+}
+
+/// Keeps the caption file in step with one transcription run. Write errors
+/// are logged when they first occur and otherwise don't interrupt captions.
+#[derive(Debug, Default)]
+pub struct CaptionFileSink {
+    written: Option<(PathBuf, String)>,
+    /// When the caption on screen was last written or extended by speech.
+    last_caption_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl CaptionFileSink {
+    /// Writes an emitted segment. Empty segments, such as the one emitted when
+    /// a segment rolls over, leave the previous caption in place.
+    pub fn segment_emitted(
+        &mut self,
+        settings: &CaptionFileSettings,
+        segment: &WhisperSegment,
+        now: Instant,
+    ) {
+        if !settings.enabled {
+            return;
+        }
+        let text = caption_text(segment, settings);
+        if text.is_empty() {
+            return;
+        }
+        self.last_caption_at = Some(now);
+        self.write(settings, text);
+    }
+
+    /// Clears the file once no speech has been heard for the configured
+    /// silence timeout. Call once per transcription step.
+    pub fn tick(&mut self, settings: &CaptionFileSettings, heard_speech: bool, now: Instant) {
+        if !settings.enabled || settings.clear_after_silence_secs == 0 {
+            return;
+        }
+        if heard_speech && self.last_caption_at.is_some() {
+            self.last_caption_at = Some(now);
+        }
+        let timeout = Duration::from_secs(settings.clear_after_silence_secs as u64);
+        if self
+            .last_caption_at
+            .is_some_and(|at| now.saturating_duration_since(at) >= timeout)
+        {
+            self.last_caption_at = None;
+            self.write(settings, String::new());
+        }
+    }
+
+    /// Empties the file when transcription stops.
+    pub fn clear(&mut self, settings: &CaptionFileSettings) {
+        if settings.enabled {
+            self.last_caption_at = None;
+            self.write(settings, String::new());
+        }
+    }
+
+    fn write(&mut self, settings: &CaptionFileSettings, text: String) {
+        if settings.path.trim().is_empty() {
+            return;
+        }
+        let path = PathBuf::from(&settings.path);
+        if self
+            .written
+            .as_ref()
+            .is_some_and(|(written_path, written)| *written_path == path && *written == text)
+        {
+            return;
+        }
+
+        match write_atomically(&path, &text) {
+            Ok(()) => {
+                self.written = Some((path, text));
+                self.last_error = None;
+            }
+            Err(err) => {
+                let message = format!("{:#}", err);
+                if self.last_error.as_ref() != Some(&message) {
+                    error!("unable to write caption file: {}", message);
+                    self.last_error = Some(message);
+                }
+            }
+        }
+    }
+}
+
+/// Writes next to the target and renames over it, so readers polling the
+/// file never see it half written.
+fn write_atomically(path: &Path, text: &str) -> Result<(), anyhow::Error> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    fs::write(&temp_path, text)
+        .with_context(|| format!("failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("failed to replace {}", path.display()))
+}
+
+/// Word-wraps the segment's text and keeps the last `max_lines` lines, the
+/// way a live caption scrolls. Words longer than a line are broken.
+fn caption_text(segment: &WhisperSegment, settings: &CaptionFileSettings) -> String {
+    let max_chars = settings.max_line_chars.max(1) as usize;
+    let mut lines: Vec<String> = Vec::new();
+    let words = segment
+        .items
+        .iter()
+        .filter(|item| item.text.trim() != SPEAKER_CHANGE_MARKER)
+        .flat_map(|item| item.text.split_whitespace());
+    for word in words {
+        let mut word: Vec<char> = word.chars().collect();
+        while !word.is_empty() {
+            let line_chars = lines.last().map(|line| line.chars().count());
+            match line_chars {
+                Some(len) if len + 1 + word.len() <= max_chars => {
+                    let line = lines.last_mut().expect("line exists");
+                    line.push(' ');
+                    line.extend(word.drain(..));
+                }
+                _ => {
+                    let take = word.len().min(max_chars);
+                    lines.push(word.drain(..take).collect());
+                }
+            }
+        }
+    }
+
+    let keep = settings.max_lines.max(1) as usize;
+    lines[lines.len().saturating_sub(keep)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrybe_core::whisper::WhisperText;
+
+    fn segment(texts: &[&str]) -> WhisperSegment {
+        WhisperSegment {
+            id: "segment".to_owned(),
+            index: 0,
+            items: texts
+                .iter()
+                .map(|text| WhisperText {
+                    text: text.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn settings(path: &Path) -> CaptionFileSettings {
+        CaptionFileSettings {
+            enabled: true,
+            path: path.to_string_lossy().into_owned(),
+            max_lines: 2,
+            max_line_chars: 12,
+            clear_after_silence_secs: 3,
+        }
+    }
+
+    #[test]
+    fn keeps_the_last_lines_within_the_line_length() {
+        let text = caption_text(
+            &segment(&[
+                " one two three",
+                SPEAKER_CHANGE_MARKER,
+                " four extraordinarily",
+            ]),
+            &CaptionFileSettings {
+                max_lines: 2,
+                max_line_chars: 10,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(text, "extraordin\narily");
+    }
+
+    #[test]
+    fn rewrites_the_file_and_clears_after_silence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("captions.txt");
+        let settings = settings(&path);
+        let mut sink = CaptionFileSink::default();
+        let start = Instant::now();
+
+        sink.segment_emitted(&settings, &segment(&[" hello there friend"]), start);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello there\nfriend");
+
+        sink.segment_emitted(&settings, &segment(&[]), start + Duration::from_secs(1));
+        sink.tick(&settings, true, start + Duration::from_secs(2));
+        sink.tick(&settings, false, start + Duration::from_secs(4));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello there\nfriend");
+
+        sink.tick(&settings, false, start + Duration::from_secs(5));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        let leftovers = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[test]
+    fn does_nothing_when_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("captions.txt");
+        let mut sink = CaptionFileSink::default();
+
+        sink.segment_emitted(
+            &CaptionFileSettings {
+                enabled: false,
+                ..settings(&path)
+            },
+            &segment(&["hello"]),
+            Instant::now(),
+        );
+
+        assert!(!path.exists());
+    }
+}
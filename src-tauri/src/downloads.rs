@@ -0,0 +1,757 @@
+use anyhow::{anyhow, Context};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug, info};
+
+const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+const PART_EXTENSION: &str = "part";
+const CHUNK_SIZE: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct DownloadProgress {
+    pub preset_id: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+    pub url: String,
+    pub destination: PathBuf,
+    pub expected_size: Option<u64>,
+    pub expected_sha256: Option<String>,
+    /// Refuses to download when neither `expected_sha256` nor the server
+    /// gives a checksum to verify the file against.
+    pub require_checksum: bool,
+}
+
+/// A verified download and the checksum it was verified against, if any.
+#[derive(Debug, Clone)]
+pub struct DownloadedFile {
+    pub path: PathBuf,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadCancelled;
+
+impl std::fmt::Display for DownloadCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "download was cancelled")
+    }
+}
+
+impl std::error::Error for DownloadCancelled {}
+
+pub fn is_cancelled_error(err: &anyhow::Error) -> bool {
+    err.is::<DownloadCancelled>()
+}
+
+/// Tracks in-flight downloads by key so they can be cancelled from another
+/// command and so the same file is never fetched twice at once.
+#[derive(Clone, Default)]
+pub struct DownloadManager {
+    active: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl DownloadManager {
+    pub fn begin(&self, key: &str) -> Result<DownloadGuard, anyhow::Error> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(key) {
+            return Err(anyhow!("{} is already downloading", key));
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        active.insert(key.to_owned(), cancel.clone());
+        Ok(DownloadGuard {
+            key: key.to_owned(),
+            cancel,
+            active: self.active.clone(),
+        })
+    }
+
+    pub fn cancel(&self, key: &str) -> bool {
+        match self.active.lock().unwrap().get(key) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for cancel in self.active.lock().unwrap().values() {
+            cancel.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+pub struct DownloadGuard {
+    key: String,
+    cancel: Arc<AtomicBool>,
+    active: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl DownloadGuard {
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+}
+
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(&self.key);
+        }
+    }
+}
+
+pub fn hf_resolve_url(repo: &str, filename: &str) -> String {
+    let endpoint = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_owned());
+    format!(
+        "{}/{}/resolve/main/{}",
+        endpoint.trim_end_matches('/'),
+        repo,
+        filename
+    )
+}
+
+pub fn part_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".");
+    file_name.push(PART_EXTENSION);
+    destination.with_file_name(file_name)
+}
+
+/// Downloads `request.url` into `request.destination`.
+///
+/// Bytes are streamed into a `.part` file next to the destination, which is
+/// resumed with a range request on the next attempt and only renamed into
+/// place once its size and SHA-256 match. When the request doesn't pin a size
+/// or checksum, the values the Hugging Face Hub reports for the LFS object
+/// (`X-Linked-Size` / `X-Linked-ETag`) are used instead.
+pub fn download_file(
+    request: &DownloadRequest,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<DownloadedFile, anyhow::Error> {
+    let agent = ureq::AgentBuilder::new()
+        .redirects(0)
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .user_agent(concat!("scrybe/", env!("CARGO_PKG_VERSION")))
+        .build();
+
+    let metadata = fetch_metadata(&agent, &request.url)?;
+    debug!("download metadata for {}: {:?}", request.url, metadata);
+
+    let expected_size = request.expected_size.or(metadata.size);
+    let expected_sha256 = request
+        .expected_sha256
+        .clone()
+        .or(metadata.sha256)
+        .map(|sha256| sha256.to_ascii_lowercase());
+    if request.require_checksum && expected_sha256.is_none() {
+        return Err(anyhow!(
+            "no checksum to verify {} against",
+            request.destination.display()
+        ));
+    }
+    let downloaded = DownloadedFile {
+        path: request.destination.clone(),
+        sha256: expected_sha256.clone(),
+    };
+
+    if let Some(parent) = request.destination.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    if request.destination.exists()
+        && verify_file(
+            &request.destination,
+            expected_size,
+            expected_sha256.as_deref(),
+        )
+        .is_ok()
+    {
+        info!("{} already downloaded", request.destination.display());
+        return Ok(downloaded);
+    }
+
+    let part = part_path(&request.destination);
+    let mut offset = fs::metadata(&part).map(|meta| meta.len()).unwrap_or(0);
+    if expected_size.is_some_and(|size| offset > size) {
+        debug!("discarding oversized partial download {}", part.display());
+        offset = 0;
+    }
+
+    if expected_size != Some(offset) || offset == 0 {
+        offset = fetch_into_part(
+            &agent,
+            &metadata.download_url,
+            &part,
+            offset,
+            expected_size,
+            cancel,
+            &mut on_progress,
+        )?;
+    }
+    on_progress(offset, expected_size.or(Some(offset)));
+
+    if let Err(err) = verify_file(&part, expected_size, expected_sha256.as_deref()) {
+        let _ = fs::remove_file(&part);
+        return Err(err);
+    }
+
+    fs::rename(&part, &request.destination).with_context(|| {
+        format!(
+            "failed to move {} to {}",
+            part.display(),
+            request.destination.display()
+        )
+    })?;
+    info!("downloaded {}", request.destination.display());
+    Ok(downloaded)
+}
+
+#[derive(Debug)]
+struct RemoteMetadata {
+    download_url: String,
+    size: Option<u64>,
+    sha256: Option<String>,
+}
+
+fn fetch_metadata(agent: &ureq::Agent, url: &str) -> Result<RemoteMetadata, anyhow::Error> {
+    let response = agent
+        .head(url)
+        .call()
+        .with_context(|| format!("failed to query {}", url))?;
+
+    // A redirect's own length and etag describe the redirect, not the file.
+    let is_redirect = (300..400).contains(&response.status());
+    let size = response
+        .header("x-linked-size")
+        .or_else(|| (!is_redirect).then(|| response.header("content-length"))?)
+        .and_then(|value| value.trim().parse().ok());
+    let sha256 = response
+        .header("x-linked-etag")
+        .or_else(|| (!is_redirect).then(|| response.header("etag"))?)
+        .map(|value| value.trim().trim_start_matches("W/").trim_matches('"'))
+        .filter(|value| is_sha256_hex(value))
+        .map(str::to_owned);
+
+    let download_url = if is_redirect {
+        let location = response
+            .header("location")
+            .ok_or_else(|| anyhow!("redirect from {} has no location", url))?;
+        resolve_location(url, location)
+    } else {
+        url.to_owned()
+    };
+
+    Ok(RemoteMetadata {
+        download_url,
+        size,
+        sha256,
+    })
+}
+
+fn fetch_into_part(
+    agent: &ureq::Agent,
+    url: &str,
+    part: &Path,
+    mut offset: u64,
+    expected_size: Option<u64>,
+    cancel: &AtomicBool,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+) -> Result<u64, anyhow::Error> {
+    let mut request = agent.get(url);
+    if offset > 0 {
+        debug!("resuming {} from byte {}", url, offset);
+        request = request.set("Range", &format!("bytes={}-", offset));
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, _)) if offset > 0 => {
+            // The server has nothing past `offset`; let verification decide
+            // whether the partial file is actually complete.
+            return Ok(offset);
+        }
+        Err(err) => return Err(anyhow!(err).context(format!("failed to download {}", url))),
+    };
+
+    let status = response.status();
+    let mut file = match status {
+        206 if offset > 0 => OpenOptions::new()
+            .append(true)
+            .open(part)
+            .with_context(|| format!("failed to open {}", part.display()))?,
+        200 => {
+            if offset > 0 {
+                debug!("server ignored range request, restarting {}", url);
+            }
+            offset = 0;
+            File::create(part).with_context(|| format!("failed to create {}", part.display()))?
+        }
+        _ => return Err(anyhow!("unexpected status {} from {}", status, url)),
+    };
+
+    let total = expected_size.or_else(|| {
+        response
+            .header("content-length")
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|length| length + offset)
+    });
+
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0_u8; CHUNK_SIZE];
+    let mut last_progress = Instant::now();
+    on_progress(offset, total);
+
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            file.flush()?;
+            return Err(DownloadCancelled.into());
+        }
+
+        let read = reader
+            .read(&mut buffer)
+            .with_context(|| format!("failed to read from {}", url))?;
+        if read == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..read])
+            .with_context(|| format!("failed to write {}", part.display()))?;
+        offset += read as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            on_progress(offset, total);
+            last_progress = Instant::now();
+        }
+    }
+
+    file.flush()?;
+    Ok(offset)
+}
+
+fn verify_file(
+    path: &Path,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let size = fs::metadata(path)
+        .with_context(|| format!("failed to stat {}", path.display()))?
+        .len();
+    if let Some(expected_size) = expected_size {
+        if size != expected_size {
+            return Err(anyhow!(
+                "size mismatch for {}: expected {} bytes, got {}",
+                path.display(),
+                expected_size,
+                size
+            ));
+        }
+    }
+
+    if let Some(expected_sha256) = expected_sha256 {
+        let actual = sha256_file(path)?;
+        if !actual.eq_ignore_ascii_case(expected_sha256) {
+            return Err(anyhow!(
+                "checksum mismatch for {}: expected {}, got {}",
+                path.display(),
+                expected_sha256,
+                actual
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, anyhow::Error> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn resolve_location(base: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return location.to_owned();
+    }
+
+    let origin_end = base
+        .find("://")
+        .and_then(|scheme_end| {
+            base[scheme_end + 3..]
+                .find('/')
+                .map(|path_start| scheme_end + 3 + path_start)
+        })
+        .unwrap_or(base.len());
+
+    if location.starts_with('/') {
+        format!("{}{}", &base[..origin_end], location)
+    } else {
+        let dir_end = base
+            .rfind('/')
+            .filter(|end| *end >= origin_end)
+            .unwrap_or(origin_end);
+        format!("{}/{}", &base[..dir_end], location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        net::{TcpListener, TcpStream},
+        sync::atomic::AtomicUsize,
+        thread,
+    };
+
+    #[derive(Clone)]
+    struct StandIn {
+        body: Arc<Vec<u8>>,
+        honor_range: bool,
+        redirect_with_linked_headers: bool,
+        ranges: Arc<Mutex<Vec<String>>>,
+        gets: Arc<AtomicUsize>,
+    }
+
+    impl StandIn {
+        fn new(body: Vec<u8>) -> Self {
+            Self {
+                body: Arc::new(body),
+                honor_range: true,
+                redirect_with_linked_headers: false,
+                ranges: Arc::new(Mutex::new(Vec::new())),
+                gets: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn serve(self) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!(
+                "http://{}/repo/resolve/main/model.bin",
+                listener.local_addr().unwrap()
+            );
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let stand_in = self.clone();
+                    thread::spawn(move || stand_in.handle(stream));
+                }
+            });
+            url
+        }
+
+        fn handle(&self, mut stream: TcpStream) {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_owned();
+                let path = parts.next().unwrap_or_default().to_owned();
+
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("range") {
+                            range = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+
+                let response = self.respond(&method, &path, range);
+                if stream.write_all(&response).is_err() {
+                    return;
+                }
+            }
+        }
+
+        fn respond(&self, method: &str, path: &str, range: Option<String>) -> Vec<u8> {
+            let sha256 = format!("{:x}", Sha256::digest(self.body.as_slice()));
+
+            if self.redirect_with_linked_headers && path.starts_with("/repo/") {
+                return format!(
+                    "HTTP/1.1 302 Found\r\nlocation: /cdn/model.bin\r\nx-linked-size: {}\r\nx-linked-etag: \"{}\"\r\ncontent-length: 0\r\n\r\n",
+                    self.body.len(),
+                    sha256
+                )
+                .into_bytes();
+            }
+
+            if method == "HEAD" {
+                return format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n",
+                    self.body.len()
+                )
+                .into_bytes();
+            }
+
+            self.gets.fetch_add(1, Ordering::SeqCst);
+            let start = match (&range, self.honor_range) {
+                (Some(range), true) => {
+                    self.ranges.lock().unwrap().push(range.clone());
+                    range
+                        .trim_start_matches("bytes=")
+                        .trim_end_matches('-')
+                        .parse::<usize>()
+                        .unwrap()
+                }
+                _ => 0,
+            };
+
+            if start >= self.body.len() && start > 0 {
+                return b"HTTP/1.1 416 Range Not Satisfiable\r\ncontent-length: 0\r\n\r\n".to_vec();
+            }
+
+            let body = &self.body[start..];
+            let head = if start > 0 {
+                format!(
+                    "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\ncontent-range: bytes {}-{}/{}\r\n\r\n",
+                    body.len(),
+                    start,
+                    self.body.len() - 1,
+                    self.body.len()
+                )
+            } else {
+                format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n", body.len())
+            };
+            let mut response = head.into_bytes();
+            response.extend_from_slice(body);
+            response
+        }
+    }
+
+    fn body(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn request(url: String, dir: &Path, body: &[u8]) -> DownloadRequest {
+        DownloadRequest {
+            url,
+            destination: dir.join("model.bin"),
+            expected_size: Some(body.len() as u64),
+            expected_sha256: Some(format!("{:x}", Sha256::digest(body))),
+            require_checksum: true,
+        }
+    }
+
+    #[test]
+    fn downloads_and_verifies_a_fresh_file() {
+        let body = body(200_000);
+        let url = StandIn::new(body.clone()).serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = request(url, dir.path(), &body);
+        let mut last_progress = (0, None);
+
+        let path = download_file(&request, &AtomicBool::new(false), |bytes, total| {
+            last_progress = (bytes, total)
+        })
+        .unwrap()
+        .path;
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert!(!part_path(&path).exists());
+        assert_eq!(last_progress, (body.len() as u64, Some(body.len() as u64)));
+    }
+
+    #[test]
+    fn resumes_partial_download_with_range_request() {
+        let body = body(200_000);
+        let stand_in = StandIn::new(body.clone());
+        let ranges = stand_in.ranges.clone();
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = request(url, dir.path(), &body);
+        fs::write(part_path(&request.destination), &body[..75_000]).unwrap();
+
+        let path = download_file(&request, &AtomicBool::new(false), |_, _| {})
+            .unwrap()
+            .path;
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert_eq!(*ranges.lock().unwrap(), vec!["bytes=75000-".to_owned()]);
+    }
+
+    #[test]
+    fn restarts_when_server_ignores_range() {
+        let body = body(100_000);
+        let mut stand_in = StandIn::new(body.clone());
+        stand_in.honor_range = false;
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = request(url, dir.path(), &body);
+        fs::write(part_path(&request.destination), vec![0xff; 40_000]).unwrap();
+
+        let path = download_file(&request, &AtomicBool::new(false), |_, _| {})
+            .unwrap()
+            .path;
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch_and_discards_part() {
+        let body = body(50_000);
+        let url = StandIn::new(body.clone()).serve();
+        let dir = tempfile::tempdir().unwrap();
+        let mut request = request(url, dir.path(), &body);
+        request.expected_sha256 = Some("0".repeat(64));
+
+        let err = download_file(&request, &AtomicBool::new(false), |_, _| {}).unwrap_err();
+
+        assert!(err.to_string().contains("checksum mismatch"));
+        assert!(!request.destination.exists());
+        assert!(!part_path(&request.destination).exists());
+    }
+
+    #[test]
+    fn cancellation_keeps_part_for_resume() {
+        let body = body(100_000);
+        let stand_in = StandIn::new(body.clone());
+        let gets = stand_in.gets.clone();
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = request(url, dir.path(), &body);
+
+        let err = download_file(&request, &AtomicBool::new(true), |_, _| {}).unwrap_err();
+
+        assert!(is_cancelled_error(&err));
+        assert!(part_path(&request.destination).exists());
+        assert!(!request.destination.exists());
+        assert_eq!(gets.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn skips_download_when_destination_is_already_valid() {
+        let body = body(10_000);
+        let stand_in = StandIn::new(body.clone());
+        let gets = stand_in.gets.clone();
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = request(url, dir.path(), &body);
+        fs::write(&request.destination, &body).unwrap();
+
+        download_file(&request, &AtomicBool::new(false), |_, _| {}).unwrap();
+
+        assert_eq!(gets.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn follows_hub_redirect_and_uses_linked_checksum() {
+        let body = body(30_000);
+        let mut stand_in = StandIn::new(body.clone());
+        stand_in.redirect_with_linked_headers = true;
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let request = DownloadRequest {
+            url,
+            destination: dir.path().join("model.bin"),
+            expected_size: None,
+            expected_sha256: None,
+            require_checksum: true,
+        };
+
+        let downloaded = download_file(&request, &AtomicBool::new(false), |_, _| {}).unwrap();
+
+        assert_eq!(fs::read(&downloaded.path).unwrap(), body);
+        assert_eq!(
+            downloaded.sha256,
+            Some(format!("{:x}", Sha256::digest(&body)))
+        );
+    }
+
+    #[test]
+    fn refuses_to_download_without_a_checksum_when_one_is_required() {
+        let body = body(10_000);
+        let stand_in = StandIn::new(body.clone());
+        let gets = stand_in.gets.clone();
+        let url = stand_in.serve();
+        let dir = tempfile::tempdir().unwrap();
+        let mut request = request(url, dir.path(), &body);
+        request.expected_sha256 = None;
+
+        let err = download_file(&request, &AtomicBool::new(false), |_, _| {}).unwrap_err();
+
+        assert!(err.to_string().contains("no checksum"));
+        assert_eq!(gets.load(Ordering::SeqCst), 0);
+        assert!(!request.destination.exists());
+    }
+
+    #[test]
+    fn manager_rejects_duplicate_downloads_and_cancels_by_key() {
+        let manager = DownloadManager::default();
+
+        let guard = manager.begin("tiny").unwrap();
+        assert!(manager.begin("tiny").is_err());
+        assert!(manager.cancel("tiny"));
+        assert!(guard.cancel_flag().load(Ordering::SeqCst));
+
+        drop(guard);
+        assert!(!manager.cancel("tiny"));
+        assert!(manager.begin("tiny").is_ok());
+    }
+
+    #[test]
+    fn resolves_relative_redirects() {
+        assert_eq!(
+            resolve_location("https://hf.co/a/resolve/main/x.bin", "/cdn/x.bin"),
+            "https://hf.co/cdn/x.bin"
+        );
+        assert_eq!(
+            resolve_location(
+                "https://hf.co/a/resolve/main/x.bin",
+                "https://cdn.example/x"
+            ),
+            "https://cdn.example/x"
+        );
+        assert_eq!(
+            resolve_location("https://hf.co/a/b/x.bin", "y.bin"),
+            "https://hf.co/a/b/y.bin"
+        );
+    }
+}
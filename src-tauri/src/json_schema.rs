@@ -0,0 +1,322 @@
+use serde_json::{json, Map, Value};
+use specta::{
+    datatype::{
+        DataType, EnumRepr, EnumType, EnumVariant, EnumVariants, Field, LiteralType, NamedFields,
+        PrimitiveType, StructFields, StructType,
+    },
+    TypeCollection,
+};
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Describes every type in `types` as a JSON Schema (draft 2020-12) under
+/// `$defs`, with `roots` as the alternatives a document may match. Types are
+/// described as serde serializes them, following specta's view of the serde
+/// attributes.
+pub fn export(title: &str, types: &TypeCollection, roots: &[&str]) -> Value {
+    let mut defs = Map::new();
+    for (_, named) in types {
+        let mut schema = datatype(&named.inner);
+        describe(&mut schema, named.docs());
+        defs.insert(named.name().to_string(), schema);
+    }
+
+    json!({
+        "$schema": DIALECT,
+        "title": title,
+        "oneOf": roots.iter().map(|root| reference(root)).collect::<Vec<_>>(),
+        "$defs": defs,
+    })
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
+
+fn describe(schema: &mut Value, docs: &str) {
+    let docs = docs.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    let docs = docs.trim();
+    if docs.is_empty() {
+        return;
+    }
+    if let Value::Object(object) = schema {
+        object.insert("description".to_owned(), Value::String(docs.to_owned()));
+    }
+}
+
+fn datatype(ty: &DataType) -> Value {
+    match ty {
+        DataType::Any | DataType::Unknown | DataType::Generic(_) => json!({}),
+        DataType::Primitive(primitive) => primitive_type(primitive),
+        DataType::Literal(literal) => literal_type(literal),
+        DataType::List(list) => {
+            let mut schema = json!({ "type": "array", "items": datatype(list.ty()) });
+            if let Some(length) = list.length() {
+                schema["minItems"] = json!(length);
+                schema["maxItems"] = json!(length);
+            }
+            if list.unique() {
+                schema["uniqueItems"] = json!(true);
+            }
+            schema
+        }
+        DataType::Map(map) => json!({
+            "type": "object",
+            "additionalProperties": datatype(map.value_ty()),
+        }),
+        DataType::Nullable(inner) => json!({ "anyOf": [datatype(inner), { "type": "null" }] }),
+        DataType::Struct(structure) => struct_type(structure),
+        DataType::Enum(enumeration) => enum_type(enumeration),
+        DataType::Tuple(tuple) => tuple_type(tuple.elements()),
+        DataType::Reference(reference_type) => reference(reference_type.name()),
+    }
+}
+
+fn primitive_type(primitive: &PrimitiveType) -> Value {
+    match primitive {
+        PrimitiveType::i8
+        | PrimitiveType::i16
+        | PrimitiveType::i32
+        | PrimitiveType::i64
+        | PrimitiveType::i128
+        | PrimitiveType::isize => json!({ "type": "integer" }),
+        PrimitiveType::u8
+        | PrimitiveType::u16
+        | PrimitiveType::u32
+        | PrimitiveType::u64
+        | PrimitiveType::u128
+        | PrimitiveType::usize => json!({ "type": "integer", "minimum": 0 }),
+        PrimitiveType::f32 | PrimitiveType::f64 => json!({ "type": "number" }),
+        PrimitiveType::bool => json!({ "type": "boolean" }),
+        PrimitiveType::char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        PrimitiveType::String => json!({ "type": "string" }),
+    }
+}
+
+fn literal_type(literal: &LiteralType) -> Value {
+    let value = match literal {
+        LiteralType::i8(value) => json!(value),
+        LiteralType::i16(value) => json!(value),
+        LiteralType::i32(value) => json!(value),
+        LiteralType::u8(value) => json!(value),
+        LiteralType::u16(value) => json!(value),
+        LiteralType::u32(value) => json!(value),
+        LiteralType::f32(value) => json!(value),
+        LiteralType::f64(value) => json!(value),
+        LiteralType::bool(value) => json!(value),
+        LiteralType::String(value) => json!(value),
+        LiteralType::char(value) => json!(value.to_string()),
+        LiteralType::None => Value::Null,
+        _ => return json!({}),
+    };
+    json!({ "const": value })
+}
+
+fn tuple_type(elements: &[DataType]) -> Value {
+    match elements {
+        [] => json!({ "type": "null" }),
+        [element] => datatype(element),
+        elements => json!({
+            "type": "array",
+            "prefixItems": elements.iter().map(datatype).collect::<Vec<_>>(),
+            "items": false,
+        }),
+    }
+}
+
+fn struct_type(structure: &StructType) -> Value {
+    match structure.fields() {
+        StructFields::Unit => json!({ "type": "null" }),
+        StructFields::Unnamed(unnamed) => unnamed_fields(unnamed.fields()),
+        StructFields::Named(named) => named_fields(named, None),
+    }
+}
+
+fn unnamed_fields(fields: &[Field]) -> Value {
+    let elements: Vec<DataType> = fields
+        .iter()
+        .filter_map(|field| field.ty().cloned())
+        .collect();
+    tuple_type(&elements)
+}
+
+/// An object schema for named fields, with the enum tag as a constant
+/// property when the fields belong to an internally tagged variant.
+/// Flattened fields are merged in with `allOf`.
+fn named_fields(fields: &NamedFields, tag: Option<(&str, &str)>) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    let mut flattened = Vec::new();
+
+    let tag = tag.or_else(|| fields.tag().as_deref().map(|tag| (tag, "")));
+    if let Some((tag, value)) = tag.filter(|(_, value)| !value.is_empty()) {
+        properties.insert(tag.to_owned(), json!({ "const": value }));
+        required.push(tag.to_owned());
+    }
+    for (name, field) in fields.fields() {
+        let Some(ty) = field.ty() else {
+            continue;
+        };
+        let mut schema = datatype(ty);
+        if field.flatten() {
+            flattened.push(schema);
+            continue;
+        }
+        describe(&mut schema, field.docs());
+        if !field.optional() {
+            required.push(name.to_string());
+        }
+        properties.insert(name.to_string(), schema);
+    }
+
+    let object = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    });
+    if flattened.is_empty() {
+        object
+    } else {
+        flattened.insert(0, object);
+        json!({ "allOf": flattened })
+    }
+}
+
+fn enum_type(enumeration: &EnumType) -> Value {
+    let variants: Vec<Value> = enumeration
+        .variants()
+        .iter()
+        .filter(|(_, variant)| !variant.skip())
+        .map(|(name, variant)| {
+            let mut schema = enum_variant(enumeration.repr(), name, variant);
+            describe(&mut schema, variant.docs());
+            schema
+        })
+        .collect();
+
+    match enumeration.repr() {
+        EnumRepr::Untagged => json!({ "anyOf": variants }),
+        _ => json!({ "oneOf": variants }),
+    }
+}
+
+fn enum_variant(repr: &EnumRepr, name: &str, variant: &EnumVariant) -> Value {
+    let content = || match variant.inner() {
+        EnumVariants::Unit => json!({ "type": "null" }),
+        EnumVariants::Named(named) => named_fields(named, None),
+        EnumVariants::Unnamed(unnamed) => unnamed_fields(unnamed.fields()),
+    };
+
+    match repr {
+        EnumRepr::Untagged => content(),
+        EnumRepr::External => match variant.inner() {
+            EnumVariants::Unit => json!({ "const": name }),
+            _ => json!({
+                "type": "object",
+                "properties": { name: content() },
+                "required": [name],
+                "additionalProperties": false,
+            }),
+        },
+        EnumRepr::Internal { tag } => {
+            let tag_only = json!({
+                "type": "object",
+                "properties": { tag.as_ref(): { "const": name } },
+                "required": [tag],
+            });
+            match variant.inner() {
+                EnumVariants::Unit => tag_only,
+                EnumVariants::Named(named) => named_fields(named, Some((tag, name))),
+                EnumVariants::Unnamed(_) => json!({ "allOf": [tag_only, content()] }),
+            }
+        }
+        EnumRepr::Adjacent { tag, content: key } => {
+            let mut properties = Map::new();
+            properties.insert(tag.to_string(), json!({ "const": name }));
+            let mut required = vec![tag.to_string()];
+            if !matches!(variant.inner(), EnumVariants::Unit) {
+                properties.insert(key.to_string(), content());
+                required.push(key.to_string());
+            }
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A greeting.
+    #[derive(serde::Serialize, specta::Type)]
+    #[allow(dead_code)]
+    struct Greeting {
+        /// Who to greet.
+        name: String,
+        #[serde(default)]
+        times: Option<u32>,
+        #[serde(flatten)]
+        kind: GreetingKind,
+    }
+
+    #[derive(serde::Serialize, specta::Type)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    #[allow(dead_code)]
+    enum GreetingKind {
+        Wave,
+        Say { words: Vec<String> },
+    }
+
+    #[derive(serde::Serialize, specta::Type)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Mood {
+        Happy,
+        Level(f64),
+    }
+
+    fn schema() -> Value {
+        let mut types = TypeCollection::default();
+        types.register::<Greeting>().register::<Mood>();
+        export("Test", &types, &["Greeting"])
+    }
+
+    #[test]
+    fn describes_structs_with_flattened_internally_tagged_enums() {
+        let schema = schema();
+
+        assert_eq!(schema["$schema"], DIALECT);
+        assert_eq!(schema["oneOf"], json!([{ "$ref": "#/$defs/Greeting" }]));
+        let greeting = &schema["$defs"]["Greeting"];
+        assert_eq!(greeting["description"], "A greeting.");
+        let object = &greeting["allOf"][0];
+        assert_eq!(object["required"], json!(["name"]));
+        assert_eq!(object["properties"]["name"]["description"], "Who to greet.");
+        assert_eq!(
+            object["properties"]["times"],
+            json!({ "anyOf": [{ "type": "integer", "minimum": 0 }, { "type": "null" }] })
+        );
+
+        // Flattened types are inlined rather than referenced.
+        let kind = &greeting["allOf"][1]["oneOf"];
+        assert_eq!(kind[0]["properties"]["type"], json!({ "const": "wave" }));
+        assert_eq!(kind[1]["required"], json!(["type", "words"]));
+        assert_eq!(
+            kind[1]["properties"]["words"],
+            json!({ "type": "array", "items": { "type": "string" } })
+        );
+    }
+
+    #[test]
+    fn describes_externally_tagged_enums() {
+        let mood = &schema()["$defs"]["Mood"]["oneOf"];
+
+        assert_eq!(mood[0], json!({ "const": "happy" }));
+        assert_eq!(mood[1]["required"], json!(["level"]));
+        assert_eq!(mood[1]["properties"]["level"], json!({ "type": "number" }));
+    }
+}
@@ -1,21 +1,41 @@
-use hf_hub::api::sync::Api;
-use rust_embed::RustEmbed;
+use calibration::{CalibrationReport, CalibrationRunner, PresetBenchmark};
+use caption_file::CaptionFileSink;
+use downloads::{DownloadManager, DownloadProgress, DownloadRequest};
+use model_adaptation::{
+    ModelAdaptation, ModelAdaptationMode, ModelAdaptationPolicy, ModelAdaptationState,
+};
+use model_catalog::{ModelCatalog, ModelCatalogReport};
+use model_library::LocalModel;
+use model_priority::ModelPriority;
+use process_usage::SharedProcessSampler;
 use scrybe_core::{
     audio::{self, AudioManager},
+    capture::CaptureBuffer,
     devices::AudioDevice,
-    metrics::{rms_level, AudioMetricsState, InferenceTimingStats},
+    metrics::{rms_level, AudioMetricsState, CaptionLatencyTracker, InferenceTimingStats},
+    scheduler::{samples_to_ms, StepOutcome, StepScheduler},
     segments::{
         GateTelemetryState, SegmentAccumulator, SegmentEmissionDecision, SegmentEmissionGate,
     },
-    whisper::WhisperManager,
+    subtitles::{self, CueOptions, SubtitleFormat},
+    whisper::{
+        is_cancelled_error, CancellationToken, WhisperManager, WhisperParams, WhisperSegment,
+    },
 };
 use serde::{Deserialize, Serialize};
+use sessions::{
+    CaptureAnchor, Session, SessionInfo, SessionSegment, SessionSummary, SessionWriter,
+};
 #[cfg(debug_assertions)]
 use specta_typescript::Typescript;
 use std::{
     collections::HashMap,
     env,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 #[cfg(target_os = "macos")]
@@ -23,15 +43,37 @@ use tauri::TitleBarStyle;
 use tauri::{AppHandle, Emitter, Listener, Manager, State, WebviewUrl, WebviewWindowBuilder};
 use tauri_specta::collect_commands;
 use tauri_svelte_synced_store::{StateSyncer, StateSyncerConfig};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
-use warp::Filter;
 use ws::WebsocketManager;
+use ws_protocol::{ServerMessage, Topic};
 
+mod batch_transcription;
+mod calibration;
+mod caption_file;
+mod downloads;
 mod internal;
+mod json_schema;
+mod model_adaptation;
+mod model_catalog;
+mod model_library;
+mod model_priority;
 mod model_selection;
+mod openmetrics;
+mod process_usage;
+mod remote_control;
+mod rest_api;
+mod segment_replay;
+mod server;
+mod server_config;
+mod sessions;
+mod transcription_api;
 mod types;
 mod ws;
+mod ws_fanout;
+mod ws_protocol;
+mod wyoming;
+mod wyoming_server;
 
 const DEFAULT_AUDIO_STEP_SIZE: u64 = 500; //ms
 type SharedWhisperManager = Arc<Mutex<Option<WhisperManager>>>;
@@ -101,12 +143,68 @@ impl RuntimeDependencyState {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, specta::Type)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum ModelLoadState {
+    Idle,
+    Downloading { bytes: u64, total: Option<u64> },
+    Loading,
+    WarmingUp,
+    Ready,
+    Failed { reason: String },
+}
+
+impl Default for ModelLoadState {
+    fn default() -> Self {
+        Self::Idle
+    }
+}
+
+impl ModelLoadState {
+    /// Whether a model is being downloaded, loaded or warmed up.
+    fn is_in_progress(&self) -> bool {
+        matches!(
+            self,
+            Self::Downloading { .. } | Self::Loading | Self::WarmingUp
+        )
+    }
+
+    /// The serialized `state` tag.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Downloading { .. } => "downloading",
+            Self::Loading => "loading",
+            Self::WarmingUp => "warming_up",
+            Self::Ready => "ready",
+            Self::Failed { .. } => "failed",
+        }
+    }
+}
+
 struct WhisperSetupResult {
-    model_path: String,
+    whisper_manager: Option<WhisperManager>,
     runtime_dependency: RuntimeDependencyState,
 }
 
+/// Tracks which background model load is the current one. Starting a new
+/// load bumps the generation, and stale loads drop their result instead of
+/// installing it.
+#[derive(Clone, Debug, Default)]
+struct ModelLoader {
+    generation: Arc<AtomicU64>,
+}
+
+impl ModelLoader {
+    fn begin(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, specta::Type)]
 #[serde(default)]
 struct OverlayTestState {
@@ -132,7 +230,15 @@ struct InternalState {
     version: String,
     name: String,
     runtime_dependency: RuntimeDependencyState,
+    model_load: ModelLoadState,
+    model_adaptation: ModelAdaptationState,
     overlay_test: OverlayTestState,
+    /// Where the local server ended up listening, or `None` when it couldn't
+    /// start.
+    server_url: Option<String>,
+    /// Where the Wyoming server listens, or `None` when it is off or
+    /// couldn't start.
+    wyoming_uri: Option<String>,
 }
 
 impl Default for InternalState {
@@ -144,7 +250,11 @@ impl Default for InternalState {
             version: "".to_owned(),
             name: "".to_owned(),
             runtime_dependency: RuntimeDependencyState::default(),
+            model_load: ModelLoadState::default(),
+            model_adaptation: ModelAdaptationState::default(),
             overlay_test: OverlayTestState::default(),
+            server_url: None,
+            wyoming_uri: None,
         }
     }
 }
@@ -168,6 +278,23 @@ impl InternalState {
     fn reset_runtime_only_state(&mut self) {
         self.stop_transcription();
         self.overlay_test.clear();
+        self.model_load = ModelLoadState::Idle;
+        self.model_adaptation = ModelAdaptationState::default();
+        self.server_url = None;
+        self.wyoming_uri = None;
+    }
+
+    fn model_not_ready_reason(&self) -> Option<String> {
+        match &self.model_load {
+            ModelLoadState::Ready => None,
+            ModelLoadState::Failed { reason } => Some(reason.clone()),
+            ModelLoadState::Downloading { .. } => {
+                Some("The Whisper model is still downloading.".to_owned())
+            }
+            ModelLoadState::Idle | ModelLoadState::Loading | ModelLoadState::WarmingUp => {
+                Some("The Whisper model is still loading.".to_owned())
+            }
+        }
     }
 
     fn is_transcription_run_active(&self, run_id: &str) -> bool {
@@ -226,10 +353,6 @@ fn accelerated_backend_available(has_nvidia_gpu: bool) -> bool {
     }
 }
 
-#[derive(RustEmbed)]
-#[folder = "../build"]
-struct Static;
-
 #[cfg(debug_assertions)]
 fn repo_root() -> std::path::PathBuf {
     std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -263,6 +386,7 @@ pub fn specta_builder() -> tauri_specta::Builder<tauri::Wry> {
         .typ::<OverlayTestState>()
         .typ::<RuntimeDependencyState>()
         .typ::<RuntimeDependencyStatus>()
+        .typ::<ModelLoadState>()
         .typ::<types::AppState>()
         .typ::<types::AdvancedSettings>()
         .typ::<types::HomeRightRailSettings>()
@@ -271,9 +395,16 @@ pub fn specta_builder() -> tauri_specta::Builder<tauri::Wry> {
         .typ::<types::OverlayBox>()
         .typ::<types::OverlayStyle>()
         .typ::<types::OverlayPadding>()
-        .typ::<types::WebsocketRequest>()
-        .typ::<types::WebsocketResponse>()
+        .typ::<ws_protocol::ClientEnvelope>()
+        .typ::<ws_protocol::ServerEnvelope>()
         .typ::<types::ModelPreset>()
+        .typ::<DownloadProgress>()
+        .typ::<LocalModel>()
+        .typ::<ModelCatalogReport>()
+        .typ::<CalibrationReport>()
+        .typ::<PresetBenchmark>()
+        .typ::<ModelAdaptationState>()
+        .typ::<ModelAdaptationMode>()
         .typ::<scrybe_core::whisper::WhisperParams>()
         .typ::<scrybe_core::whisper::WhisperToggles>()
         .typ::<scrybe_core::whisper::WhisperSegment>()
@@ -288,7 +419,20 @@ pub fn specta_builder() -> tauri_specta::Builder<tauri::Wry> {
             get_transcribe_running,
             get_audio_devices,
             list_model_presets,
+            reload_model_catalog,
             download_model_preset,
+            cancel_model_download,
+            run_model_calibration,
+            get_model_calibration,
+            list_local_models,
+            verify_local_model,
+            delete_local_model,
+            reveal_local_model,
+            import_local_model,
+            list_sessions,
+            load_session,
+            export_session,
+            delete_session,
             emit_state,
             update_state,
         ])
@@ -316,12 +460,7 @@ fn emit_state(
 // TODO: move this into a macro as well
 #[tauri::command]
 #[specta::specta]
-fn update_state(
-    app: AppHandle,
-    ws_manager: State<'_, WebsocketManager>,
-    state: tauri_svelte_synced_store::StateUpdate,
-    state_syncer: tauri::State<'_, tauri_svelte_synced_store::StateSyncer>,
-) -> bool {
+fn update_state(app: AppHandle, state: tauri_svelte_synced_store::StateUpdate) -> bool {
     tracing::info!("update_state: {:?}", state);
 
     match state.name.as_str() {
@@ -334,68 +473,96 @@ fn update_state(
                     return false;
                 }
             };
-            state_syncer.update("internal_state", new_internal_state.clone(), true);
-
-            let response = WebsocketManager::to_ws_response(
-                "internal_state_update".to_owned(),
-                new_internal_state.clone(),
-            );
-
-            match serde_json::to_string(&response) {
-                Ok(msg) => <WebsocketManager as Clone>::clone(&ws_manager).broadcast(msg),
-                Err(err) => error!("error creating websocket response: {}", err),
-            };
+            apply_internal_state(&app, new_internal_state);
         }
         "app_state" => {
-            let mut new_app_state: types::AppState =
-                match serde_json::from_str(state.value.as_str()) {
-                    Ok(res) => res,
-                    Err(_) => {
-                        error!("failed to parse app state");
-                        return false;
-                    }
-                };
-
-            let app_state_snapshot = state_syncer.snapshot::<types::AppState>("app_state");
-            if new_app_state.model_path != app_state_snapshot.model_path {
-                info!("Model path changed, re-setting up whisper manager");
-                let setup_result = setup_whisper_manager(&app, new_app_state.model_path.clone());
-                new_app_state.model_path = setup_result.model_path;
-
-                let mut internal_state = state_syncer.snapshot::<InternalState>("internal_state");
-                internal_state.runtime_dependency = setup_result.runtime_dependency;
-                state_syncer.update("internal_state", internal_state.clone(), true);
-
-                let response = WebsocketManager::to_ws_response(
-                    "internal_state_update".to_owned(),
-                    internal_state.clone(),
-                );
-
-                match serde_json::to_string(&response) {
-                    Ok(msg) => <WebsocketManager as Clone>::clone(&ws_manager).broadcast(msg),
-                    Err(err) => error!("error creating websocket response: {}", err),
-                };
-            }
-            state_syncer.update("app_state", new_app_state.clone(), true);
-
-            let response = WebsocketManager::to_ws_response(
-                "app_state_update".to_owned(),
-                new_app_state.clone(),
-            );
-
-            match serde_json::to_string(&response) {
-                Ok(msg) => <WebsocketManager as Clone>::clone(&ws_manager).broadcast(msg),
-                Err(err) => error!("error creating websocket response: {}", err),
+            let new_app_state: types::AppState = match serde_json::from_str(state.value.as_str()) {
+                Ok(res) => res,
+                Err(_) => {
+                    error!("failed to parse app state");
+                    return false;
+                }
             };
+            apply_app_state(&app, new_app_state);
         }
         _ => {
-            tracing::warn!("unknown type");
+            warn!("unknown type");
             return false;
         }
     }
     return true;
 }
 
+/// Replaces the internal state with one edited outside the backend, by the
+/// frontend or a remote client.
+fn apply_internal_state(app: &AppHandle, mut new_internal_state: InternalState) {
+    update_internal_state(app, |state| {
+        // Model loading, adaptation and the server addresses are owned by the
+        // backend; a frontend snapshot taken mid-load must not roll them back.
+        new_internal_state.model_load = state.model_load.clone();
+        new_internal_state.model_adaptation = state.model_adaptation.clone();
+        new_internal_state.server_url = state.server_url.clone();
+        new_internal_state.wyoming_uri = state.wyoming_uri.clone();
+        *state = new_internal_state;
+    });
+}
+
+/// Replaces the app state with one edited outside the backend, reloading
+/// the model when its path changed and restarting the local or Wyoming
+/// server when its settings did.
+fn apply_app_state(app: &AppHandle, mut new_app_state: types::AppState) {
+    let model_load = app
+        .state::<StateSyncer>()
+        .snapshot::<InternalState>("internal_state")
+        .model_load;
+    let server_settings = new_app_state.server.clone();
+    let wyoming_settings = new_app_state.wyoming.clone();
+    let mut model_path = String::new();
+    let mut model_path_changed = false;
+    let mut server_settings_changed = false;
+    let mut wyoming_settings_changed = false;
+    update_app_state(app, |state| {
+        keep_backend_model_path(&mut new_app_state, state, &model_load);
+        model_path = new_app_state.model_path.clone();
+        model_path_changed = new_app_state.model_path != state.model_path;
+        server_settings_changed = new_app_state.server != state.server;
+        wyoming_settings_changed = new_app_state.wyoming != state.wyoming;
+        *state = new_app_state;
+    });
+
+    if server_settings_changed {
+        info!("Server settings changed, restarting the local server");
+        server::restart(app, server_settings);
+    }
+
+    if wyoming_settings_changed {
+        info!("Wyoming settings changed, restarting the wyoming server");
+        wyoming_server::restart(app, wyoming_settings);
+    }
+
+    if model_path_changed {
+        info!("Model path changed, reloading whisper manager in the background");
+        app.state::<CancellationToken>().cancel();
+        update_internal_state(app, |state| {
+            state.model_adaptation.recommendation = None;
+        });
+        spawn_model_load(app, model_path);
+    }
+}
+
+/// The backend picks and downloads a model when none is set. Until that
+/// model has loaded, a frontend snapshot taken before the pick still has no
+/// path and must not roll the backend's choice back.
+fn keep_backend_model_path(
+    new_app_state: &mut types::AppState,
+    state: &types::AppState,
+    model_load: &ModelLoadState,
+) {
+    if model_load.is_in_progress() && new_app_state.model_path.is_empty() {
+        new_app_state.model_path = state.model_path.clone();
+    }
+}
+
 #[tauri::command]
 #[specta::specta]
 fn get_audio_devices(
@@ -409,24 +576,315 @@ fn get_audio_devices(
 
 #[tauri::command]
 #[specta::specta]
-fn list_model_presets() -> Vec<types::ModelPreset> {
-    types::model_presets()
+fn list_model_presets(catalog: tauri::State<'_, ModelCatalog>) -> Vec<types::ModelPreset> {
+    catalog.presets()
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn download_model_preset(preset_id: String) -> Result<String, String> {
-    let preset = types::model_presets()
-        .into_iter()
-        .find(|p| p.id == preset_id)
+fn reload_model_catalog(
+    app: AppHandle,
+    catalog: tauri::State<'_, ModelCatalog>,
+) -> Result<ModelCatalogReport, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(catalog.reload(&config_dir))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn download_model_preset(
+    app: AppHandle,
+    catalog: tauri::State<'_, ModelCatalog>,
+    preset_id: String,
+) -> Result<String, String> {
+    let preset = catalog
+        .find(&preset_id)
         .ok_or_else(|| format!("unknown preset: {}", preset_id))?;
 
-    tauri::async_runtime::spawn_blocking(move || download_preset_file(&preset))
+    tauri::async_runtime::spawn_blocking(move || {
+        download_preset_file(&app, &preset, |progress| {
+            if let Err(err) = app.emit("model_download_progress", progress) {
+                error!("failed to emit model download progress: {}", err);
+            }
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn cancel_model_download(
+    download_manager: tauri::State<'_, DownloadManager>,
+    preset_id: String,
+) -> bool {
+    download_manager.cancel(&preset_id)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn run_model_calibration(
+    app: AppHandle,
+    runner: tauri::State<'_, CalibrationRunner>,
+    download_missing: bool,
+) -> Result<CalibrationReport, String> {
+    let guard = runner
+        .try_start()
+        .ok_or_else(|| "calibration is already running".to_owned())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _guard = guard;
+        calibrate_models(&app, download_missing)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_model_calibration(app: AppHandle) -> Option<CalibrationReport> {
+    let data_dir = app.path().app_data_dir().ok()?;
+    calibration::load_report(&data_dir)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_local_models(app: AppHandle) -> Result<Vec<LocalModel>, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<_, anyhow::Error> {
+        let roots = model_library_roots(&app)?;
+        Ok(model_library::list_models(
+            &roots,
+            &model_catalog_presets(&app),
+            &current_model_path(&app),
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn verify_local_model(app: AppHandle, path: String) -> Result<LocalModel, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let roots = model_library_roots(&app)?;
+        model_library::verify_model(
+            &roots,
+            &model_catalog_presets(&app),
+            &current_model_path(&app),
+            Path::new(&path),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn delete_local_model(app: AppHandle, path: String) -> Result<(), String> {
+    let roots = model_library_roots(&app).map_err(|e| e.to_string())?;
+    model_library::delete_model(&roots, &current_model_path(&app), Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn reveal_local_model(app: AppHandle, path: String) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let roots = model_library_roots(&app).map_err(|e| e.to_string())?;
+    let resolved =
+        model_library::resolve_in_library(&roots, Path::new(&path)).map_err(|e| e.to_string())?;
+    app.opener()
+        .reveal_item_in_dir(resolved)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn import_local_model(app: AppHandle, path: String) -> Result<LocalModel, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let roots = model_library_roots(&app)?;
+        let imported = model_library::import_model(&roots.models_dir, Path::new(&path))?;
+        model_library::list_models(
+            &roots,
+            &model_catalog_presets(&app),
+            &current_model_path(&app),
+        )
+        .into_iter()
+        .find(|model| Path::new(&model.path) == imported)
+        .ok_or_else(|| anyhow::anyhow!("imported model missing from library"))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_sessions(app: AppHandle) -> Result<Vec<SessionSummary>, String> {
+    let sessions_dir = sessions_dir(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || sessions::list_sessions(&sessions_dir))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn load_session(app: AppHandle, id: String) -> Result<Session, String> {
+    let sessions_dir = sessions_dir(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || sessions::load_session(&sessions_dir, &id))
         .await
         .map_err(|e| e.to_string())?
         .map_err(|e| e.to_string())
 }
 
+/// Renders a recorded session as subtitles for the frontend to save.
+#[tauri::command]
+#[specta::specta]
+async fn export_session(
+    app: AppHandle,
+    id: String,
+    format: SubtitleFormat,
+    options: Option<CueOptions>,
+) -> Result<String, String> {
+    let sessions_dir = sessions_dir(&app).map_err(|e| e.to_string())?;
+    let style = app
+        .state::<StateSyncer>()
+        .snapshot::<types::AppState>("app_state")
+        .overlay_config
+        .subtitle_style();
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, anyhow::Error> {
+        let session = sessions::load_session(&sessions_dir, &id)?;
+        Ok(subtitles::export(
+            format,
+            &session.transcript(),
+            &options.unwrap_or_default(),
+            &style,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn delete_session(app: AppHandle, id: String) -> Result<(), String> {
+    let sessions_dir = sessions_dir(&app).map_err(|e| e.to_string())?;
+    sessions::delete_session(&sessions_dir, &id, active_session_id(&app).as_deref())
+        .map_err(|e| e.to_string())
+}
+
+fn sessions_dir(app: &AppHandle) -> Result<PathBuf, anyhow::Error> {
+    Ok(app.path().app_data_dir()?.join(sessions::SESSIONS_DIR))
+}
+
+fn active_session_id(app: &AppHandle) -> Option<String> {
+    app.state::<StateSyncer>()
+        .snapshot::<InternalState>("internal_state")
+        .active_transcription_run_id
+}
+
+/// Starts recording a session for `run_id` and prunes old sessions. History
+/// is best effort: failures are logged and transcription carries on.
+fn start_session(
+    app: &AppHandle,
+    run_id: &str,
+    app_state: &types::AppState,
+) -> Option<SessionWriter> {
+    let settings = &app_state.session_history;
+    if !settings.enabled {
+        return None;
+    }
+    let sessions_dir = match sessions_dir(app) {
+        Ok(sessions_dir) => sessions_dir,
+        Err(err) => {
+            error!("unable to locate session history: {}", err);
+            return None;
+        }
+    };
+    sessions::apply_retention(&sessions_dir, settings, calibration::now_ms(), Some(run_id));
+
+    let info = SessionInfo {
+        id: run_id.to_owned(),
+        started_at_ms: calibration::now_ms(),
+        device: app_state.current_device.clone(),
+        model_path: app_state.model_path.clone(),
+        model_preset_id: model_adaptation::preset_for_model_path(
+            &model_catalog_presets(app),
+            &app_state.model_path,
+        )
+        .map(|preset| preset.id.clone()),
+        whisper_params: app_state.whisper_params.clone(),
+        audio_segment_size: app_state.audio_segment_size,
+    };
+    match SessionWriter::create(&sessions_dir, &info) {
+        Ok(writer) => Some(writer),
+        Err(err) => {
+            error!("unable to record session: {:#}", err);
+            None
+        }
+    }
+}
+
+/// Appends a finalized segment to the session, dropping the writer if the
+/// file can no longer be written.
+fn record_session_segment(
+    session: &mut Option<SessionWriter>,
+    segment: &WhisperSegment,
+    started_at: SystemTime,
+    capture_anchors: &[CaptureAnchor],
+) {
+    let Some(writer) = session.as_mut() else {
+        return;
+    };
+    if segment.items.is_empty() {
+        return;
+    }
+    let started_at_ms = started_at
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+    let record = SessionSegment::from_segment(
+        segment,
+        started_at_ms,
+        calibration::now_ms(),
+        capture_anchors,
+    );
+    if let Err(err) = writer.append_segment(&record) {
+        error!("stopping session history: {:#}", err);
+        *session = None;
+    }
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, anyhow::Error> {
+    Ok(app.path().app_data_dir()?.join(model_library::MODELS_DIR))
+}
+
+fn model_library_roots(app: &AppHandle) -> Result<model_library::LibraryRoots, anyhow::Error> {
+    let state_syncer = app.state::<StateSyncer>();
+    let app_state = state_syncer.snapshot::<types::AppState>("app_state");
+    Ok(model_library::LibraryRoots {
+        hf_cache: hf_hub::Cache::default().path().clone(),
+        models_dir: models_dir(app)?,
+        user_folders: app_state.model_folders.iter().map(PathBuf::from).collect(),
+    })
+}
+
+fn model_catalog_presets(app: &AppHandle) -> Vec<types::ModelPreset> {
+    app.state::<ModelCatalog>().presets()
+}
+
+fn current_model_path(app: &AppHandle) -> String {
+    app.state::<StateSyncer>()
+        .snapshot::<types::AppState>("app_state")
+        .model_path
+}
+
 #[tauri::command]
 #[specta::specta]
 fn get_transcribe_running(
@@ -446,7 +904,7 @@ fn get_transcribe_running(
 
 #[tauri::command]
 #[specta::specta]
-fn stop_transcribe(state_syncer: tauri::State<'_, tauri_svelte_synced_store::StateSyncer>) {
+fn stop_transcribe(app: AppHandle) {
     debug!(
         "{:?} stop_transcribe",
         SystemTime::now()
@@ -454,10 +912,12 @@ fn stop_transcribe(state_syncer: tauri::State<'_, tauri_svelte_synced_store::Sta
             .expect("Time went backwards")
             .as_millis()
     );
-    let internal_state_ref = state_syncer.get::<InternalState>("internal_state");
-    let mut state = internal_state_ref.lock().unwrap();
+    end_transcription(&app);
+}
 
-    state.stop_transcription();
+fn end_transcription(app: &AppHandle) {
+    update_internal_state(app, |state| state.stop_transcription());
+    app.state::<CancellationToken>().cancel();
 }
 
 fn transcription_run_is_active(state_syncer: &StateSyncer, run_id: &str) -> bool {
@@ -474,10 +934,7 @@ fn clear_transcription_run_if_current(state_syncer: &StateSyncer, run_id: &str)
 
 #[tauri::command]
 #[specta::specta]
-fn start_transcribe<'a>(
-    app: AppHandle,
-    state_syncer: tauri::State<'_, tauri_svelte_synced_store::StateSyncer>,
-) -> Result<(), String> {
+fn start_transcribe(app: AppHandle) -> Result<(), String> {
     debug!(
         "{:?} start_transcribe",
         SystemTime::now()
@@ -485,6 +942,19 @@ fn start_transcribe<'a>(
             .expect("Time went backwards")
             .as_millis()
     );
+    begin_transcription(&app)
+}
+
+/// Starts a transcription run on its own thread, or explains why it can't.
+fn begin_transcription(app: &AppHandle) -> Result<(), String> {
+    let state_syncer = app.state::<StateSyncer>();
+    if let Some(reason) = state_syncer
+        .snapshot::<InternalState>("internal_state")
+        .model_not_ready_reason()
+    {
+        info!("refusing to start transcription: {}", reason);
+        return Err(reason);
+    }
 
     {
         let wm_state_ref = app.state::<SharedWhisperManager>();
@@ -502,13 +972,13 @@ fn start_transcribe<'a>(
     }
 
     let run_id = Uuid::new_v4().to_string();
-    {
-        let internal_state_ref = state_syncer.get::<InternalState>("internal_state");
-        let mut state = internal_state_ref.lock().unwrap();
-        if !state.claim_transcription_run(run_id.clone()) {
-            info!("transcribe already running");
-            return Err("Transcription is already running.".to_owned());
-        }
+    let mut claimed = false;
+    update_internal_state(app, |state| {
+        claimed = state.claim_transcription_run(run_id.clone());
+    });
+    if !claimed {
+        info!("transcribe already running");
+        return Err("Transcription is already running.".to_owned());
     }
     state_syncer.update("gate_telemetry", GateTelemetryState::default(), true);
     state_syncer.update("audio_metrics", AudioMetricsState::default(), true);
@@ -518,8 +988,9 @@ fn start_transcribe<'a>(
 
     std::thread::spawn(move || {
         let wm_state_ref = app_handle_ref.state::<SharedWhisperManager>();
+        let model_priority = app_handle_ref.state::<ModelPriority>();
 
-        let writer: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
+        let writer: Arc<Mutex<CaptureBuffer>> = Arc::new(Mutex::new(CaptureBuffer::default()));
         let mut audio_manager = match AudioManager::new_with_device(
             writer.clone(),
             state_syncer_ref
@@ -542,6 +1013,12 @@ fn start_transcribe<'a>(
             return;
         }
 
+        let mut session = start_session(
+            &app_handle_ref,
+            &run_id,
+            &state_syncer_ref.snapshot::<types::AppState>("app_state"),
+        );
+
         let mut segment_accumulator = SegmentAccumulator::new(
             Uuid::new_v4().to_string(),
             Duration::from_secs(
@@ -553,8 +1030,17 @@ fn start_transcribe<'a>(
         let mut segment_emission_gate = SegmentEmissionGate::new();
         let mut segment_start_time = SystemTime::now();
         let mut samples: Vec<f32> = Vec::new();
+        let mut capture_anchors: Vec<CaptureAnchor> = Vec::new();
         let mut audio_metrics = AudioMetricsState::default();
         let mut inference_timing_stats = InferenceTimingStats::default();
+        let mut adaptation_policy = ModelAdaptationPolicy::default();
+        let mut caption_latency = CaptionLatencyTracker::default();
+        let mut caption_file = CaptionFileSink::default();
+        let mut step_scheduler = StepScheduler::new(Duration::from_millis(
+            state_syncer_ref
+                .snapshot::<InternalState>("internal_state")
+                .audio_step_size,
+        ));
         loop {
             let internal_state_ref = state_syncer_ref.snapshot::<InternalState>("internal_state");
             let app_state_ref = state_syncer_ref.snapshot::<types::AppState>("app_state");
@@ -574,10 +1060,11 @@ fn start_transcribe<'a>(
                 break;
             }
 
-            debug!("waiting for {}ms", internal_state_ref.audio_step_size);
-            std::thread::sleep(std::time::Duration::from_millis(
-                internal_state_ref.audio_step_size,
-            ));
+            step_scheduler.set_base_step(Duration::from_millis(internal_state_ref.audio_step_size));
+            let step_delay = step_scheduler.next_delay();
+            debug!("waiting for {:?}", step_delay);
+            std::thread::sleep(step_delay);
+            let step_started = Instant::now();
 
             if !transcription_run_is_active(&state_syncer_ref, &run_id) {
                 info!("stopping transcription");
@@ -585,28 +1072,40 @@ fn start_transcribe<'a>(
             }
 
             debug!("copying buffer");
-            audio_metrics.input_rms = if let Ok(mut guard) = writer.lock() {
-                let input_rms = rms_level(&guard);
-                debug!(
-                    "guard len pre-trim {}, avg threshold {}, rms {}",
-                    guard.len(),
-                    audio::avg_threshold(&guard),
-                    input_rms
-                );
-                // TODO: this should be a setting to adjust
-                audio::trim_silence(&mut guard, 0.01);
-                debug!("guard len post-trim {}", guard.len());
-                samples.append(&mut guard);
-                input_rms
-            } else {
-                0.0
-            };
+            let mut captured = writer
+                .lock()
+                .map(|mut guard| guard.take())
+                .unwrap_or_default();
+            audio_metrics.input_rms = rms_level(captured.samples());
+            debug!(
+                "captured len pre-trim {}, avg threshold {}, rms {}",
+                captured.len(),
+                audio::avg_threshold(captured.samples()),
+                audio_metrics.input_rms
+            );
+            // TODO: this should be a setting to adjust
+            captured.trim_silence(0.01);
+            debug!("captured len post-trim {}", captured.len());
+            let new_samples = captured.len();
+            if let Some(captured_at) = captured.first_captured_at() {
+                caption_latency.speech_captured(captured_at);
+                // Trimmed silence leaves gaps whisper's timestamps don't
+                // see; session exports put them back from these.
+                capture_anchors.push(CaptureAnchor {
+                    audio_ms: samples_to_ms(samples.len()) as u64,
+                    captured_at_ms: calibration::now_ms()
+                        .saturating_sub(captured_at.elapsed().as_millis() as u64),
+                });
+            }
+            samples.append(&mut captured.into_samples());
             audio_metrics.segment_sample_len = samples.len() as u64;
 
-            // 8037 normal samples in 500ms
-            if samples.len() > 4000 {
+            let mut step_inference_ms = None;
+            if step_scheduler.should_infer(samples.len(), new_samples) {
                 debug!("got enough samples, getting whisper manager");
 
+                // Uploads to the transcription API wait while this is held.
+                let live_step = model_priority.live_step();
                 let mut whisper_manager_ref = wm_state_ref.lock().unwrap();
                 let Some(whisper_manager) = whisper_manager_ref.as_mut() else {
                     error!("whisper manager is unavailable");
@@ -615,57 +1114,93 @@ fn start_transcribe<'a>(
                 };
 
                 let inference_started = Instant::now();
-                let segments =
-                    match whisper_manager.process_samples(samples.clone(), whisper_params) {
-                        Ok(segments) => segments,
-                        Err(err) => {
-                            inference_timing_stats.record(
-                                inference_started.elapsed().as_secs_f64() * 1000.0,
-                                &mut audio_metrics,
-                            );
-                            error!("ERROR {}", err);
-                            state_syncer_ref.update("audio_metrics", audio_metrics.clone(), true);
-                            continue;
-                        }
-                    };
-                inference_timing_stats.record(
-                    inference_started.elapsed().as_secs_f64() * 1000.0,
-                    &mut audio_metrics,
-                );
+                let result = whisper_manager.process_samples(samples.clone(), whisper_params);
+                let inference_ms = inference_started.elapsed().as_secs_f64() * 1000.0;
+                drop(whisper_manager_ref);
+                drop(live_step);
+
+                // Failed and cancelled steps still reach the bookkeeping at
+                // the end of the loop, so the scheduler paces around them. A
+                // cancelled run was cut short, so its time isn't recorded.
+                let segments = match result {
+                    Ok(segments) => {
+                        inference_timing_stats.record(inference_ms, &mut audio_metrics);
+                        step_inference_ms = Some(inference_ms);
+                        Some(segments)
+                    }
+                    Err(err) if is_cancelled_error(&err) => {
+                        info!("inference cancelled");
+                        audio_metrics.inference_cancelled_count += 1;
+                        None
+                    }
+                    Err(err) => {
+                        inference_timing_stats.record(inference_ms, &mut audio_metrics);
+                        step_inference_ms = Some(inference_ms);
+                        error!("ERROR {}", err);
+                        None
+                    }
+                };
 
-                let current_segment = segment_accumulator.replace_items(segments);
+                if let Some(segments) = segments {
+                    if app_state_ref.model_adaptation != ModelAdaptationMode::Off {
+                        let backlog_samples = writer.lock().map(|guard| guard.len()).unwrap_or(0);
+                        adapt_model(
+                            &app_handle_ref,
+                            &mut adaptation_policy,
+                            &app_state_ref,
+                            inference_ms,
+                            samples_to_ms(backlog_samples),
+                            internal_state_ref.audio_step_size,
+                        );
+                    }
 
-                if !transcription_run_is_active(&state_syncer_ref, &run_id) {
-                    info!("stopping transcription");
-                    break;
-                }
+                    let current_segment = segment_accumulator.replace_items(segments);
 
-                let evaluation = segment_emission_gate.evaluate(current_segment);
-                let gate_emitted = match &evaluation.decision {
-                    SegmentEmissionDecision::Emit(_) => true,
-                    SegmentEmissionDecision::Suppress(_) => false,
-                };
-                audio_metrics.gate_total_evaluations += 1;
-                if gate_emitted {
-                    audio_metrics.gate_total_emits += 1;
-                }
-                audio_metrics.gate_emit_rate = audio_metrics.gate_total_emits as f64
-                    / audio_metrics.gate_total_evaluations as f64;
-                {
-                    let telemetry_ref =
-                        state_syncer_ref.get::<GateTelemetryState>("gate_telemetry");
-                    let mut telemetry = telemetry_ref.lock().unwrap();
-                    telemetry.push(evaluation.telemetry);
-                }
+                    if !transcription_run_is_active(&state_syncer_ref, &run_id) {
+                        info!("stopping transcription");
+                        break;
+                    }
 
-                match evaluation.decision {
-                    SegmentEmissionDecision::Emit(segment) => {
-                        app_handle_ref
-                            .emit("segment_update", segment)
-                            .expect("failed to emit event");
+                    let evaluation = segment_emission_gate.evaluate(current_segment);
+                    let gate_emitted = match &evaluation.decision {
+                        SegmentEmissionDecision::Emit(_) => true,
+                        SegmentEmissionDecision::Suppress(_) => false,
+                    };
+                    audio_metrics.gate_total_evaluations += 1;
+                    if gate_emitted {
+                        audio_metrics.gate_total_emits += 1;
+                    }
+                    audio_metrics.gate_emit_rate = audio_metrics.gate_total_emits as f64
+                        / audio_metrics.gate_total_evaluations as f64;
+                    if let Some(ws_manager) = app_handle_ref.try_state::<WebsocketManager>() {
+                        if ws_manager.has_subscribers(Topic::GateTelemetry) {
+                            ws_manager.broadcast(ServerMessage::GateEvaluation {
+                                entry: Box::new(evaluation.telemetry.clone()),
+                            });
+                        }
                     }
-                    SegmentEmissionDecision::Suppress(reason) => {
-                        debug!("suppressing segment update: {:?}", reason);
+                    {
+                        let telemetry_ref =
+                            state_syncer_ref.get::<GateTelemetryState>("gate_telemetry");
+                        let mut telemetry = telemetry_ref.lock().unwrap();
+                        telemetry.push(evaluation.telemetry);
+                    }
+                    caption_latency.gate_evaluated(Instant::now(), &evaluation.decision);
+
+                    match evaluation.decision {
+                        SegmentEmissionDecision::Emit(segment) => {
+                            caption_file.segment_emitted(
+                                &app_state_ref.caption_file,
+                                &segment,
+                                Instant::now(),
+                            );
+                            app_handle_ref
+                                .emit("segment_update", segment)
+                                .expect("failed to emit event");
+                        }
+                        SegmentEmissionDecision::Suppress(reason) => {
+                            debug!("suppressing segment update: {:?}", reason);
+                        }
                     }
                 }
             }
@@ -676,28 +1211,82 @@ fn start_transcribe<'a>(
 
             segment_accumulator
                 .set_segment_size(Duration::from_secs(app_state_ref.audio_segment_size));
+            let finished_segment = segment_accumulator.current().clone();
             if let Some(next_segment) = segment_accumulator.rollover_if_elapsed(
                 segment_start_time.elapsed().unwrap(),
                 Uuid::new_v4().to_string(),
             ) {
+                record_session_segment(
+                    &mut session,
+                    &finished_segment,
+                    segment_start_time,
+                    &capture_anchors,
+                );
                 debug!("trimming samples, total {}", samples.len(),);
                 samples.clear();
+                capture_anchors.clear();
                 audio_metrics.segment_sample_len = 0;
 
                 segment_start_time = SystemTime::now();
+                caption_latency.segment_rolled_over(Instant::now());
                 if !transcription_run_is_active(&state_syncer_ref, &run_id) {
                     info!("stopping transcription");
                     break;
                 }
 
+                caption_file.segment_emitted(
+                    &app_state_ref.caption_file,
+                    &next_segment,
+                    Instant::now(),
+                );
                 app_handle_ref
                     .emit("segment_update", next_segment.clone())
                     .expect("failed to emit event");
                 segment_emission_gate.reset_with_emitted(&next_segment);
             }
-            state_syncer_ref.update("audio_metrics", audio_metrics.clone(), true);
+
+            caption_file.tick(&app_state_ref.caption_file, new_samples > 0, Instant::now());
+            step_scheduler.finish_step(
+                step_started,
+                StepOutcome {
+                    new_samples,
+                    inference_ms: step_inference_ms,
+                    backlog_samples: writer.lock().map(|guard| guard.len()).unwrap_or(0),
+                },
+            );
+            step_scheduler.update_metrics(&mut audio_metrics);
+            audio_metrics.system = app_handle_ref
+                .state::<SharedProcessSampler>()
+                .lock()
+                .unwrap()
+                .sample();
+            caption_latency.update_metrics(
+                &app_handle_ref
+                    .state::<WebsocketManager>()
+                    .segment_fanout
+                    .lock()
+                    .unwrap(),
+                &mut audio_metrics,
+            );
+            publish_audio_metrics(&app_handle_ref, &audio_metrics);
         }
 
+        record_session_segment(
+            &mut session,
+            segment_accumulator.current(),
+            segment_start_time,
+            &capture_anchors,
+        );
+        if let Some(writer) = session {
+            if let Err(err) = writer.finish(calibration::now_ms()) {
+                error!("unable to finish session: {:#}", err);
+            }
+        }
+        caption_file.clear(
+            &state_syncer_ref
+                .snapshot::<types::AppState>("app_state")
+                .caption_file,
+        );
         clear_transcription_run_if_current(&state_syncer_ref, &run_id);
     });
 
@@ -705,104 +1294,318 @@ fn start_transcribe<'a>(
     Ok(())
 }
 
-fn download_preset_file(preset: &types::ModelPreset) -> Result<String, anyhow::Error> {
+/// Feeds one inference timing to the adaptation policy and applies its
+/// decision: a recommendation in `Suggest` mode, or a model switch in
+/// `Automatic` mode when the target preset is already on disk.
+fn adapt_model(
+    app: &AppHandle,
+    policy: &mut ModelAdaptationPolicy,
+    app_state: &types::AppState,
+    inference_ms: f64,
+    backlog_ms: f64,
+    audio_step_size_ms: u64,
+) {
+    let presets = model_catalog_presets(app);
+    let Some(current) = model_adaptation::preset_for_model_path(&presets, &app_state.model_path)
+    else {
+        return;
+    };
+    let Some(decision) = policy.observe(
+        Instant::now(),
+        current,
+        &presets,
+        inference_ms,
+        backlog_ms,
+        audio_step_size_ms,
+    ) else {
+        return;
+    };
+
+    info!(
+        "model adaptation: {:?} {} -> {} ({})",
+        decision.direction, decision.from_preset_id, decision.to.id, decision.reason
+    );
+    let mut adaptation = ModelAdaptation {
+        direction: decision.direction,
+        from_preset_id: decision.from_preset_id,
+        to_preset_id: decision.to.id.clone(),
+        reason: decision.reason,
+        at_ms: calibration::now_ms(),
+    };
+
+    if app_state.model_adaptation != ModelAdaptationMode::Automatic {
+        update_internal_state(app, |state| {
+            state.model_adaptation.recommendation = Some(adaptation)
+        });
+        return;
+    }
+
+    match local_preset_file(app, &decision.to) {
+        Ok(Some(model_path)) => {
+            update_internal_state(app, |state| {
+                state.model_adaptation.recommendation = None;
+                state.model_adaptation.last_switch = Some(adaptation);
+            });
+            update_app_state(app, |state| state.model_path = model_path.clone());
+            spawn_model_load(app, model_path);
+        }
+        Ok(None) | Err(_) => {
+            adaptation.reason = format!(
+                "{}; download {} to switch automatically",
+                adaptation.reason, decision.to.label
+            );
+            update_internal_state(app, |state| {
+                state.model_adaptation.recommendation = Some(adaptation)
+            });
+        }
+    }
+}
+
+/// Returns the on-disk file for `preset` if it doesn't need downloading.
+fn local_preset_file(
+    app: &AppHandle,
+    preset: &types::ModelPreset,
+) -> Result<Option<String>, anyhow::Error> {
+    if let Some(local_path) = &preset.local_path {
+        if !Path::new(local_path).is_file() {
+            return Err(anyhow::anyhow!("model file {} not found", local_path));
+        }
+        return Ok(Some(local_path.clone()));
+    }
+
+    if let Some(cached) = hf_hub::Cache::default()
+        .model(preset.repo.clone())
+        .get(&preset.filename)
+    {
+        debug!(
+            "model preset {} found in hf cache at {:?}",
+            preset.id, cached
+        );
+        return Ok(Some(cached.to_string_lossy().into_owned()));
+    }
+
+    // Files only land here after passing verification.
+    let destination = preset_download_destination(app, preset)?;
+    Ok(destination
+        .exists()
+        .then(|| destination.to_string_lossy().into_owned()))
+}
+
+fn preset_download_destination(
+    app: &AppHandle,
+    preset: &types::ModelPreset,
+) -> Result<PathBuf, anyhow::Error> {
+    Ok(models_dir(app)?
+        .join(preset.repo.replace('/', "--"))
+        .join(&preset.filename))
+}
+
+fn download_preset_file(
+    app: &AppHandle,
+    preset: &types::ModelPreset,
+    mut on_progress: impl FnMut(DownloadProgress),
+) -> Result<String, anyhow::Error> {
+    if let Some(path) = local_preset_file(app, preset)? {
+        return Ok(path);
+    }
+
     info!(
         "downloading model preset {} ({}/{})",
         preset.id, preset.repo, preset.filename
     );
-    let api = Api::new()?;
-    let repo = api.model(preset.repo.clone());
-    let filename = repo.get(&preset.filename)?;
-    debug!("downloaded to {:?}", filename);
-    Ok(filename.to_string_lossy().into_owned())
+    let guard = app.state::<DownloadManager>().begin(&preset.id)?;
+    let request = DownloadRequest {
+        url: downloads::hf_resolve_url(&preset.repo, &preset.filename),
+        destination: preset_download_destination(app, preset)?,
+        expected_size: preset.size_bytes,
+        expected_sha256: preset.sha256.clone(),
+        require_checksum: true,
+    };
+    let downloaded = downloads::download_file(&request, guard.cancel_flag(), |bytes, total| {
+        on_progress(DownloadProgress {
+            preset_id: preset.id.clone(),
+            downloaded_bytes: bytes,
+            total_bytes: total,
+        })
+    })?;
+    debug!("downloaded to {:?}", downloaded.path);
+    if let Some(sha256) = &downloaded.sha256 {
+        if let Err(err) =
+            model_library::record_download(&models_dir(app)?, &downloaded.path, sha256)
+        {
+            warn!(
+                "failed to record checksum for {:?}: {:#}",
+                downloaded.path, err
+            );
+        }
+    }
+    Ok(downloaded.path.to_string_lossy().into_owned())
 }
 
-fn setup_whisper_manager(app: &AppHandle, mut model_path: String) -> WhisperSetupResult {
-    let has_nvidia_gpu = has_nvidia_gpu();
+fn select_initial_model_preset(app: &AppHandle, has_nvidia_gpu: bool) -> types::ModelPreset {
+    let profile =
+        model_selection::collect_hardware_profile(accelerated_backend_available(has_nvidia_gpu));
+    let presets = model_catalog_presets(app);
 
-    if model_path.is_empty() {
-        let profile = model_selection::collect_hardware_profile(accelerated_backend_available(
-            has_nvidia_gpu,
-        ));
-        let selected_preset_id = model_selection::select_initial_model_preset_id(profile);
+    if let Some(preset) = calibrated_model_preset(app, profile, &presets) {
         info!(
-            "empty model path, selecting initial model preset {} for hardware profile {:?}",
-            selected_preset_id, profile
+            "empty model path, selecting calibrated model preset {}",
+            preset.id
         );
+        return preset;
+    }
 
-        let presets = types::model_presets();
-        let selected_preset = presets
-            .iter()
-            .find(|preset| preset.id == selected_preset_id)
-            .or_else(|| {
-                error!(
-                    "selected model preset {} missing from model_presets(), falling back to {}",
-                    selected_preset_id,
-                    types::DEFAULT_MODEL_PRESET_ID
-                );
-                presets
-                    .iter()
-                    .find(|preset| preset.id == types::DEFAULT_MODEL_PRESET_ID)
+    match model_selection::select_initial_model_preset(profile, &presets) {
+        Some(preset) => {
+            info!(
+                "empty model path, selecting initial model preset {} for hardware profile {:?}",
+                preset.id, profile
+            );
+            preset.clone()
+        }
+        None => {
+            error!(
+                "no auto-selectable model preset for hardware profile {:?}, falling back to {}",
+                profile,
+                types::DEFAULT_MODEL_PRESET_ID
+            );
+            types::model_presets()
+                .into_iter()
+                .find(|preset| preset.id == types::DEFAULT_MODEL_PRESET_ID)
+                .expect("default model preset missing from model_presets()")
+        }
+    }
+}
+
+fn calibrated_model_preset(
+    app: &AppHandle,
+    profile: model_selection::HardwareProfile,
+    presets: &[types::ModelPreset],
+) -> Option<types::ModelPreset> {
+    let report = calibration::load_report(&app.path().app_data_dir().ok()?)?;
+    let audio_step_size_ms = app
+        .state::<StateSyncer>()
+        .snapshot::<InternalState>("internal_state")
+        .audio_step_size;
+    if !report.applies_to(
+        &calibration::CalibrationFingerprint::new(profile),
+        audio_step_size_ms,
+    ) {
+        debug!("stored model calibration is stale, ignoring it");
+        return None;
+    }
+
+    let selected_preset_id = report.selected_preset_id?;
+    presets
+        .iter()
+        .find(|preset| preset.id == selected_preset_id)
+        .cloned()
+}
+
+fn calibrate_models(
+    app: &AppHandle,
+    download_missing: bool,
+) -> Result<CalibrationReport, anyhow::Error> {
+    let internal_state = app
+        .state::<StateSyncer>()
+        .snapshot::<InternalState>("internal_state");
+    if internal_state.transcribe_running {
+        return Err(anyhow::anyhow!("stop transcription before calibrating"));
+    }
+
+    let audio_step_size_ms = internal_state.audio_step_size;
+    let has_nvidia_gpu = has_nvidia_gpu();
+    let profile =
+        model_selection::collect_hardware_profile(accelerated_backend_available(has_nvidia_gpu));
+    let fixture = calibration::fixture()?;
+    let presets = model_catalog_presets(app);
+    let params = WhisperParams {
+        language: "en".to_owned(),
+        ..WhisperParams::default()
+    };
+
+    let candidates = calibration::candidates(&presets);
+    let benchmarks = calibration::run_calibration(&candidates, audio_step_size_ms, |preset| {
+        let model_path = if download_missing {
+            download_preset_file(app, preset, |progress| {
+                let _ = app.emit("model_download_progress", progress);
             })
-            .expect("default model preset missing from model_presets()")
-            .clone();
+        } else {
+            local_preset_file(app, preset)
+                .and_then(|path| path.ok_or_else(|| anyhow::anyhow!("not downloaded")))
+        };
 
-        match download_preset_file(&selected_preset) {
-            Ok(downloaded_model_path) => model_path = downloaded_model_path,
-            Err(err) => {
-                error!("failed to download initial model preset: {}", err);
-                let manager_ref = app.state::<SharedWhisperManager>();
-                *manager_ref.lock().unwrap() = None;
-                return WhisperSetupResult {
-                    model_path,
-                    runtime_dependency: RuntimeDependencyState {
-                        status: RuntimeDependencyStatus::Unavailable,
-                        has_nvidia_gpu,
-                        reason: format!(
-                            "Failed to download the initial Whisper model ({}): {err}",
-                            selected_preset.id
-                        ),
-                        action_url: None,
-                    },
-                };
-            }
+        let benchmark = match model_path {
+            Ok(model_path) => calibration::benchmark_preset(
+                &preset.id,
+                &fixture,
+                || {
+                    let setup = setup_whisper_manager(app, &model_path, has_nvidia_gpu);
+                    let mut manager = setup
+                        .whisper_manager
+                        .ok_or_else(|| anyhow::anyhow!(setup.runtime_dependency.reason))?;
+                    manager.warm_up()?;
+                    Ok(manager)
+                },
+                |manager, samples| manager.process_samples(samples, params.clone()).map(|_| ()),
+            ),
+            Err(err) => PresetBenchmark::failed(&preset.id, err.to_string()),
+        };
+
+        if let Err(err) = app.emit("model_calibration_progress", benchmark.clone()) {
+            error!("failed to emit model calibration progress: {}", err);
         }
-    }
+        benchmark
+    });
+
+    let selected_preset_id =
+        calibration::select_calibrated_preset(&benchmarks, &presets, audio_step_size_ms)
+            .map(|preset| preset.id.clone());
+    let report = CalibrationReport {
+        fingerprint: calibration::CalibrationFingerprint::new(profile),
+        audio_step_size_ms,
+        fixture_duration_ms: fixture.metadata.duration_ms,
+        benchmarks,
+        selected_preset_id,
+        calibrated_at_ms: calibration::now_ms(),
+    };
+    calibration::save_report(&app.path().app_data_dir()?, &report)?;
+    info!("model calibration selected {:?}", report.selected_preset_id);
+    Ok(report)
+}
 
+fn setup_whisper_manager(
+    app: &AppHandle,
+    model_path: &str,
+    has_nvidia_gpu: bool,
+) -> WhisperSetupResult {
     info!("Model path {}", model_path);
 
     debug!("creating whisper context");
 
-    let manager_ref = app.state::<SharedWhisperManager>();
-    match WhisperManager::new(model_path.clone().as_str(), true) {
-        Ok(whisper_manager) => {
-            *manager_ref.lock().unwrap() = Some(whisper_manager);
-            WhisperSetupResult {
-                model_path,
-                runtime_dependency: RuntimeDependencyState::ready_gpu(has_nvidia_gpu),
-            }
-        }
+    let cancellation = app.state::<CancellationToken>().inner().clone();
+    match WhisperManager::new(model_path, true) {
+        Ok(whisper_manager) => WhisperSetupResult {
+            whisper_manager: Some(whisper_manager.with_cancellation_token(cancellation)),
+            runtime_dependency: RuntimeDependencyState::ready_gpu(has_nvidia_gpu),
+        },
         Err(gpu_err) => {
             error!("failed to initialize Whisper with GPU: {}", gpu_err);
-            match WhisperManager::new(model_path.clone().as_str(), false) {
-                Ok(whisper_manager) => {
-                    *manager_ref.lock().unwrap() = Some(whisper_manager);
-                    WhisperSetupResult {
-                        model_path,
-                        runtime_dependency: RuntimeDependencyState::ready_cpu_fallback(
-                            has_nvidia_gpu,
-                            format!("GPU initialization failed: {gpu_err}"),
-                        ),
-                    }
-                }
+            match WhisperManager::new(model_path, false) {
+                Ok(whisper_manager) => WhisperSetupResult {
+                    whisper_manager: Some(whisper_manager.with_cancellation_token(cancellation)),
+                    runtime_dependency: RuntimeDependencyState::ready_cpu_fallback(
+                        has_nvidia_gpu,
+                        format!("GPU initialization failed: {gpu_err}"),
+                    ),
+                },
                 Err(cpu_err) => {
                     error!(
                         "failed to initialize Whisper with CPU fallback: {}",
                         cpu_err
                     );
-                    *manager_ref.lock().unwrap() = None;
                     WhisperSetupResult {
-                        model_path,
+                        whisper_manager: None,
                         runtime_dependency: RuntimeDependencyState::unavailable(
                             has_nvidia_gpu,
                             format!(
@@ -816,13 +1619,181 @@ fn setup_whisper_manager(app: &AppHandle, mut model_path: String) -> WhisperSetu
     }
 }
 
-struct EmbedFile {
-    data: std::borrow::Cow<'static, [u8]>,
+fn spawn_model_load(app: &AppHandle, model_path: String) {
+    // Uploads in flight would otherwise finish on the old model first.
+    app.state::<ModelPriority>().cancel_batch_work();
+    let loader = app.state::<ModelLoader>().inner().clone();
+    let generation = loader.begin();
+    let app = app.clone();
+
+    std::thread::spawn(move || load_model(&app, &loader, generation, model_path));
+}
+
+fn load_model(app: &AppHandle, loader: &ModelLoader, generation: u64, mut model_path: String) {
+    let has_nvidia_gpu = has_nvidia_gpu();
+    let publish = |model_load: ModelLoadState| -> bool {
+        if !loader.is_current(generation) {
+            debug!(
+                "model load {} superseded, dropping {:?}",
+                generation, model_load
+            );
+            return false;
+        }
+        update_internal_state(app, |state| state.model_load = model_load);
+        true
+    };
+
+    if model_path.is_empty() {
+        let preset = select_initial_model_preset(app, has_nvidia_gpu);
+        if !publish(ModelLoadState::Downloading {
+            bytes: 0,
+            total: None,
+        }) {
+            return;
+        }
+
+        let progress = |progress: DownloadProgress| {
+            publish(ModelLoadState::Downloading {
+                bytes: progress.downloaded_bytes,
+                total: progress.total_bytes,
+            });
+        };
+        match download_preset_file(app, &preset, progress) {
+            Ok(downloaded_model_path) => model_path = downloaded_model_path,
+            Err(err) => {
+                error!("failed to download initial model preset: {}", err);
+                let reason = format!(
+                    "Failed to download the initial Whisper model ({}): {err}",
+                    preset.id
+                );
+                fail_model_load(
+                    app,
+                    loader,
+                    generation,
+                    RuntimeDependencyState {
+                        status: RuntimeDependencyStatus::Unavailable,
+                        has_nvidia_gpu,
+                        reason,
+                        action_url: None,
+                    },
+                );
+                return;
+            }
+        }
+
+        if !loader.is_current(generation) {
+            return;
+        }
+        update_app_state(app, |state| state.model_path = model_path.clone());
+    }
+
+    if !publish(ModelLoadState::Loading) {
+        return;
+    }
+
+    let setup_result = setup_whisper_manager(app, &model_path, has_nvidia_gpu);
+    let Some(mut whisper_manager) = setup_result.whisper_manager else {
+        fail_model_load(app, loader, generation, setup_result.runtime_dependency);
+        return;
+    };
+
+    if !publish(ModelLoadState::WarmingUp) {
+        return;
+    }
+
+    let warm_up_started = Instant::now();
+    match whisper_manager.warm_up() {
+        Ok(()) => info!("whisper warm-up took {:?}", warm_up_started.elapsed()),
+        Err(err) if is_cancelled_error(&err) => debug!("whisper warm-up cancelled"),
+        Err(err) => error!("whisper warm-up failed: {}", err),
+    }
+
+    {
+        let manager_ref = app.state::<SharedWhisperManager>();
+        let mut manager = manager_ref.lock().unwrap();
+        if !loader.is_current(generation) {
+            debug!(
+                "model load {} superseded, dropping loaded model",
+                generation
+            );
+            return;
+        }
+        *manager = Some(whisper_manager);
+    }
+
+    update_internal_state(app, |state| {
+        state.runtime_dependency = setup_result.runtime_dependency;
+        state.model_load = ModelLoadState::Ready;
+    });
+}
+
+fn fail_model_load(
+    app: &AppHandle,
+    loader: &ModelLoader,
+    generation: u64,
+    runtime_dependency: RuntimeDependencyState,
+) {
+    {
+        let manager_ref = app.state::<SharedWhisperManager>();
+        let mut manager = manager_ref.lock().unwrap();
+        if !loader.is_current(generation) {
+            return;
+        }
+        *manager = None;
+    }
+
+    update_internal_state(app, |state| {
+        state.model_load = ModelLoadState::Failed {
+            reason: runtime_dependency.reason.clone(),
+        };
+        state.runtime_dependency = runtime_dependency;
+    });
+}
+
+fn update_internal_state(app: &AppHandle, update: impl FnOnce(&mut InternalState)) {
+    let state_syncer = app.state::<StateSyncer>();
+    let internal_state = {
+        let internal_state_ref = state_syncer.get::<InternalState>("internal_state");
+        let mut internal_state = internal_state_ref.lock().unwrap();
+        update(&mut internal_state);
+        internal_state.clone()
+    };
+    state_syncer.update("internal_state", internal_state.clone(), true);
+
+    if let Some(ws_manager) = app.try_state::<WebsocketManager>() {
+        ws_manager.broadcast(ServerMessage::InternalState {
+            state: Box::new(internal_state),
+        });
+    }
 }
 
-impl warp::reply::Reply for EmbedFile {
-    fn into_response(self) -> warp::reply::Response {
-        warp::reply::Response::new(self.data.into())
+fn publish_audio_metrics(app: &AppHandle, audio_metrics: &AudioMetricsState) {
+    app.state::<StateSyncer>()
+        .update("audio_metrics", audio_metrics.clone(), true);
+
+    if let Some(ws_manager) = app.try_state::<WebsocketManager>() {
+        if ws_manager.has_subscribers(Topic::AudioMetrics) {
+            ws_manager.broadcast(ServerMessage::AudioMetrics {
+                metrics: Box::new(audio_metrics.clone()),
+            });
+        }
+    }
+}
+
+fn update_app_state(app: &AppHandle, update: impl FnOnce(&mut types::AppState)) {
+    let state_syncer = app.state::<StateSyncer>();
+    let app_state = {
+        let app_state_ref = state_syncer.get::<types::AppState>("app_state");
+        let mut app_state = app_state_ref.lock().unwrap();
+        update(&mut app_state);
+        app_state.clone()
+    };
+    state_syncer.update("app_state", app_state.clone(), true);
+
+    if let Some(ws_manager) = app.try_state::<WebsocketManager>() {
+        ws_manager.broadcast(ServerMessage::AppState {
+            state: Box::new(app_state),
+        });
     }
 }
 
@@ -864,6 +1835,17 @@ fn cleanup_before_exit(app: &AppHandle) {
         };
     }
 
+    if let Some(cancellation) = app.try_state::<CancellationToken>() {
+        cancellation.cancel();
+    }
+    if let Some(model_priority) = app.try_state::<ModelPriority>() {
+        model_priority.cancel_batch_work();
+    }
+
+    if let Some(download_manager) = app.try_state::<DownloadManager>() {
+        download_manager.cancel_all();
+    }
+
     if let Some(manager_ref) = app.try_state::<SharedWhisperManager>() {
         let whisper_manager = match manager_ref.lock() {
             Ok(mut guard) => guard.take(),
@@ -912,6 +1894,16 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_window_state::Builder::new().build())
         .manage(Arc::new(Mutex::new(None::<WhisperManager>)))
+        .manage(SharedProcessSampler::default())
+        .manage(CancellationToken::default())
+        .manage(ModelLoader::default())
+        .manage(DownloadManager::default())
+        .manage(ModelCatalog::default())
+        .manage(CalibrationRunner::default())
+        .manage(server::LocalServer::default())
+        .manage(ModelPriority::default())
+        .manage(transcription_api::TranscriptionQueue::default())
+        .manage(wyoming_server::WyomingServer::default())
         .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
             info!("{}, {argv:?}, {cwd}", app.package_info().name);
             app.emit("single-instance", argv).unwrap();
@@ -963,96 +1955,48 @@ pub fn run() {
             internal_state.name = app.package_info().name.to_string();
             internal_state.reset_runtime_only_state();
 
-            let setup_result = {
-                let app_state_ref = state_syncer.get::<types::AppState>("app_state");
-                let model_path = app_state_ref.lock().unwrap().model_path.clone();
-
-                info!("setting up whisper manager");
-                let setup_result = setup_whisper_manager(app.handle(), model_path);
-                app_state_ref.lock().unwrap().model_path = setup_result.model_path.clone();
-                setup_result
-            };
-
-            internal_state.runtime_dependency = setup_result.runtime_dependency;
             state_syncer.update("internal_state", internal_state.clone(), true);
 
             app.manage::<StateSyncer>(state_syncer.clone());
 
             info!("setting up websocket manager");
-            let ws_manager = WebsocketManager::new(state_syncer.clone()).unwrap();
+            let ws_manager =
+                WebsocketManager::new(app.handle().clone(), state_syncer.clone()).unwrap();
 
             app.manage(ws_manager.clone());
 
             let ws_manager_ref = ws_manager.clone();
             app.listen("segment_update", move |event| {
                 debug!("got segment update: {:?}", event.payload());
-                let response = types::WebsocketResponse {
-                    kind: "segment_update".to_owned(),
-                    data: event.payload().to_string(),
-                    is_error: false,
-                };
-
-                match serde_json::to_string(&response) {
-                    Ok(msg) => ws_manager_ref.clone().broadcast(msg),
-                    Err(err) => error!("error creating websocket response: {}", err),
+                let fanout_started = Instant::now();
+                match serde_json::from_str::<WhisperSegment>(event.payload()) {
+                    Ok(segment) => ws_manager_ref.publish_segment(segment),
+                    Err(err) => error!("failed to parse segment update: {}", err),
                 };
+                ws_manager_ref
+                    .segment_fanout
+                    .lock()
+                    .unwrap()
+                    .record(fanout_started.elapsed());
             });
 
-            let ws_manager_ref = ws_manager.clone();
+            match app.path().app_config_dir() {
+                Ok(config_dir) => {
+                    app.state::<ModelCatalog>().reload(&config_dir);
+                }
+                Err(err) => error!("failed to resolve app config dir: {}", err),
+            }
 
-            let _server_handle = tauri::async_runtime::spawn(async move {
-                let static_files = warp::path("app")
-                    .and(warp::get())
-                    .and(warp::path::tail())
-                    .and_then(move |tail: warp::path::Tail| {
-                        let tail_str = tail.as_str();
-                        debug!("GET {}", tail_str);
-
-                        // Try paths in order: exact path, path with .html, path/index.html
-                        let paths_to_try = if tail_str.is_empty() {
-                            vec!["index.html".to_string()]
-                        } else {
-                            vec![
-                                tail_str.to_string(),
-                                format!("{}.html", tail_str),
-                                format!("{}/index.html", tail_str),
-                            ]
-                        };
-
-                        async move {
-                            for path in paths_to_try {
-                                if let Some(file) = Static::get(&path) {
-                                    let mime = mime_guess::from_path(&path).first_or_octet_stream();
-                                    return Ok(warp::reply::with_header(
-                                        EmbedFile { data: file.data },
-                                        "content-type",
-                                        mime.as_ref(),
-                                    ));
-                                }
-                            }
-                            Err(warp::reject::not_found())
-                        }
-                    })
-                    .boxed();
-
-                let ws_route = warp::path("ws")
-                    .and(warp::ws())
-                    .map(move |ws: warp::ws::Ws| {
-                        debug!("ws_handler");
-                        let ws_manager_ref = ws_manager_ref.clone();
-                        ws.on_upgrade(move |socket| {
-                            ws_manager_ref.clone().client_connection(socket)
-                        })
-                    });
-
-                let cors = warp::cors()
-                    .allow_any_origin()
-                    .allow_methods(vec!["GET", "POST"])
-                    .allow_headers(vec!["Content-Type"]);
-
-                let routes = static_files.or(ws_route).with(cors);
-                warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
-            });
+            info!("setting up whisper manager in the background");
+            let model_path = state_syncer
+                .snapshot::<types::AppState>("app_state")
+                .model_path;
+            spawn_model_load(app.handle(), model_path);
+
+            info!("starting local server");
+            let snapshot = state_syncer.snapshot::<types::AppState>("app_state");
+            server::restart(app.handle(), snapshot.server);
+            wyoming_server::restart(app.handle(), snapshot.wyoming);
 
             info!("creating main window");
             let win_builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::default())
@@ -1170,6 +2114,33 @@ mod tests {
         assert!(state.is_transcription_run_active("new"));
     }
 
+    #[test]
+    fn stale_snapshot_keeps_the_model_the_backend_is_loading() {
+        let state = types::AppState {
+            model_path: "/models/ggml-base.bin".to_owned(),
+            ..Default::default()
+        };
+        let stale = types::AppState {
+            model_path: String::new(),
+            ..Default::default()
+        };
+
+        let mut new_app_state = stale.clone();
+        keep_backend_model_path(&mut new_app_state, &state, &ModelLoadState::Loading);
+        assert_eq!(new_app_state.model_path, state.model_path);
+
+        let mut picked = types::AppState {
+            model_path: "/models/ggml-small.bin".to_owned(),
+            ..Default::default()
+        };
+        keep_backend_model_path(&mut picked, &state, &ModelLoadState::Loading);
+        assert_eq!(picked.model_path, "/models/ggml-small.bin");
+
+        let mut new_app_state = stale;
+        keep_backend_model_path(&mut new_app_state, &state, &ModelLoadState::Ready);
+        assert_eq!(new_app_state.model_path, "");
+    }
+
     #[test]
     fn reset_runtime_only_state_clears_overlay_test() {
         let mut state = InternalState::default();
@@ -1188,4 +2159,63 @@ mod tests {
         assert_eq!(state.overlay_test.text, "");
         assert_eq!(state.overlay_test.expires_at_ms, None);
     }
+
+    #[test]
+    fn reset_runtime_only_state_clears_persisted_model_load() {
+        let mut state = InternalState::default();
+        state.model_load = ModelLoadState::Ready;
+
+        state.reset_runtime_only_state();
+
+        assert_eq!(state.model_load, ModelLoadState::Idle);
+    }
+
+    #[test]
+    fn transcription_is_refused_until_model_is_ready() {
+        let mut state = InternalState::default();
+        assert!(state.model_not_ready_reason().is_some());
+
+        state.model_load = ModelLoadState::Downloading {
+            bytes: 10,
+            total: Some(100),
+        };
+        assert!(state.model_not_ready_reason().is_some());
+
+        state.model_load = ModelLoadState::Failed {
+            reason: "missing model".to_owned(),
+        };
+        assert_eq!(
+            state.model_not_ready_reason().as_deref(),
+            Some("missing model")
+        );
+
+        state.model_load = ModelLoadState::Ready;
+        assert_eq!(state.model_not_ready_reason(), None);
+    }
+
+    #[test]
+    fn newer_model_load_supersedes_older_one() {
+        let loader = ModelLoader::default();
+
+        let first = loader.begin();
+        assert!(loader.is_current(first));
+
+        let second = loader.begin();
+        assert!(!loader.is_current(first));
+        assert!(loader.is_current(second));
+    }
+
+    #[test]
+    fn model_load_state_serializes_with_state_tag() {
+        let value = serde_json::to_value(ModelLoadState::Downloading {
+            bytes: 5,
+            total: None,
+        })
+        .unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({ "state": "downloading", "bytes": 5, "total": null })
+        );
+    }
 }
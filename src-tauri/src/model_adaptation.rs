@@ -0,0 +1,376 @@
+use crate::types::ModelPreset;
+use scrybe_core::metrics::nearest_rank_percentile;
+use std::{
+    collections::VecDeque,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Inference timings considered by the policy; older ones are dropped.
+const WINDOW: usize = 20;
+/// Observations required on the current model before acting at all.
+const MIN_OBSERVATIONS: usize = 10;
+/// Consecutive over-budget observations that trigger a downgrade.
+const DOWNGRADE_STREAK: u32 = 5;
+/// Consecutive comfortably-under-budget observations that trigger an upgrade.
+const UPGRADE_STREAK: u32 = 30;
+/// p95 must stay below this fraction of the step size to consider upgrading.
+const UPGRADE_HEADROOM: f64 = 0.5;
+/// Captured-but-unprocessed audio, in steps, that counts as falling behind.
+const BACKLOG_STEPS: f64 = 2.0;
+/// Minimum time between two decisions.
+const COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelAdaptationMode {
+    Off,
+    /// Report a recommended preset in `InternalState` but keep the model.
+    #[default]
+    Suggest,
+    /// Switch to the recommended preset when it is already on disk.
+    Automatic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum AdaptationDirection {
+    Downgrade,
+    Upgrade,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct ModelAdaptation {
+    pub direction: AdaptationDirection,
+    pub from_preset_id: String,
+    pub to_preset_id: String,
+    pub reason: String,
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(default)]
+pub struct ModelAdaptationState {
+    /// Suggested switch that hasn't been applied yet.
+    pub recommendation: Option<ModelAdaptation>,
+    /// Most recent switch made automatically.
+    pub last_switch: Option<ModelAdaptation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdaptationDecision {
+    pub direction: AdaptationDirection,
+    pub from_preset_id: String,
+    pub to: ModelPreset,
+    pub reason: String,
+}
+
+/// Watches live inference latency for one transcription run and decides when
+/// the model should move down or up a `resource_rank`.
+///
+/// Hysteresis comes from three places: downgrades need a sustained streak
+/// over budget while upgrades need a much longer streak well under it, every
+/// decision starts a cooldown, and a preset the policy had to downgrade from
+/// is not upgraded to again during the run.
+#[derive(Debug, Default)]
+pub struct ModelAdaptationPolicy {
+    current_preset_id: Option<String>,
+    recent_inference_ms: VecDeque<f64>,
+    over_budget_streak: u32,
+    under_budget_streak: u32,
+    last_decision_at: Option<Instant>,
+    upgrade_ceiling: Option<u8>,
+}
+
+impl ModelAdaptationPolicy {
+    pub fn observe(
+        &mut self,
+        now: Instant,
+        current: &ModelPreset,
+        presets: &[ModelPreset],
+        inference_ms: f64,
+        backlog_ms: f64,
+        step_ms: u64,
+    ) -> Option<AdaptationDecision> {
+        if self.current_preset_id.as_deref() != Some(current.id.as_str()) {
+            self.current_preset_id = Some(current.id.clone());
+            self.recent_inference_ms.clear();
+            self.over_budget_streak = 0;
+            self.under_budget_streak = 0;
+        }
+
+        if self.recent_inference_ms.len() == WINDOW {
+            self.recent_inference_ms.pop_front();
+        }
+        self.recent_inference_ms.push_back(inference_ms);
+
+        let step_ms = step_ms as f64;
+        let samples: Vec<f64> = self.recent_inference_ms.iter().copied().collect();
+        let p95_ms = nearest_rank_percentile(&samples, 0.95);
+        let falling_behind = backlog_ms > step_ms * BACKLOG_STEPS;
+
+        if p95_ms > step_ms || falling_behind {
+            self.over_budget_streak += 1;
+            self.under_budget_streak = 0;
+        } else if p95_ms < step_ms * UPGRADE_HEADROOM && !falling_behind {
+            self.under_budget_streak += 1;
+            self.over_budget_streak = 0;
+        } else {
+            self.over_budget_streak = 0;
+            self.under_budget_streak = 0;
+        }
+
+        if samples.len() < MIN_OBSERVATIONS
+            || self
+                .last_decision_at
+                .is_some_and(|at| now.duration_since(at) < COOLDOWN)
+        {
+            return None;
+        }
+
+        let decision = if self.over_budget_streak >= DOWNGRADE_STREAK {
+            let reason = if p95_ms > step_ms {
+                format!(
+                    "p95 inference {:.0} ms exceeds the {:.0} ms step",
+                    p95_ms, step_ms
+                )
+            } else {
+                format!(
+                    "{:.0} ms of audio is waiting, more than {:.0} steps behind",
+                    backlog_ms, BACKLOG_STEPS
+                )
+            };
+            self.upgrade_ceiling = Some(current.resource_rank.saturating_sub(1));
+            next_preset(current, presets, AdaptationDirection::Downgrade, None)
+                .map(|to| (AdaptationDirection::Downgrade, to, reason))
+        } else if self.under_budget_streak >= UPGRADE_STREAK {
+            let reason = format!(
+                "p95 inference {:.0} ms stays under half of the {:.0} ms step",
+                p95_ms, step_ms
+            );
+            next_preset(
+                current,
+                presets,
+                AdaptationDirection::Upgrade,
+                self.upgrade_ceiling,
+            )
+            .map(|to| (AdaptationDirection::Upgrade, to, reason))
+        } else {
+            None
+        };
+
+        let (direction, to, reason) = decision?;
+        self.last_decision_at = Some(now);
+        self.over_budget_streak = 0;
+        self.under_budget_streak = 0;
+        Some(AdaptationDecision {
+            direction,
+            from_preset_id: current.id.clone(),
+            to: to.clone(),
+            reason,
+        })
+    }
+}
+
+/// The auto-selectable preset one `resource_rank` step away from `current`.
+fn next_preset<'a>(
+    current: &ModelPreset,
+    presets: &'a [ModelPreset],
+    direction: AdaptationDirection,
+    max_rank: Option<u8>,
+) -> Option<&'a ModelPreset> {
+    let candidates = presets
+        .iter()
+        .filter(|preset| preset.auto_selectable && preset.id != current.id);
+    match direction {
+        AdaptationDirection::Downgrade => candidates
+            .filter(|preset| preset.resource_rank < current.resource_rank)
+            .max_by_key(|preset| preset.resource_rank),
+        AdaptationDirection::Upgrade => candidates
+            .filter(|preset| preset.resource_rank > current.resource_rank)
+            .filter(|preset| max_rank.is_none_or(|max_rank| preset.resource_rank <= max_rank))
+            .min_by_key(|preset| preset.resource_rank),
+    }
+}
+
+/// Finds the preset a model file was loaded from.
+pub fn preset_for_model_path<'a>(
+    presets: &'a [ModelPreset],
+    model_path: &str,
+) -> Option<&'a ModelPreset> {
+    let file_name = Path::new(model_path).file_name()?.to_str()?;
+    presets
+        .iter()
+        .find(|preset| preset.local_path.as_deref() == Some(model_path))
+        .or_else(|| {
+            presets
+                .iter()
+                .find(|preset| preset.local_path.is_none() && preset.filename == file_name)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        model_presets, BASE_MODEL_PRESET_ID, LARGE_V3_TURBO_Q5_MODEL_PRESET_ID,
+        SMALL_MODEL_PRESET_ID, TINY_MODEL_PRESET_ID,
+    };
+
+    fn preset(id: &str) -> ModelPreset {
+        model_presets()
+            .into_iter()
+            .find(|preset| preset.id == id)
+            .unwrap()
+    }
+
+    fn feed(
+        policy: &mut ModelAdaptationPolicy,
+        now: Instant,
+        current: &ModelPreset,
+        count: usize,
+        inference_ms: f64,
+        backlog_ms: f64,
+    ) -> Option<AdaptationDecision> {
+        let presets = model_presets();
+        let mut decision = None;
+        for _ in 0..count {
+            decision =
+                decision.or(policy.observe(now, current, &presets, inference_ms, backlog_ms, 500));
+        }
+        decision
+    }
+
+    #[test]
+    fn downgrades_to_next_smaller_auto_selectable_preset_when_p95_exceeds_step() {
+        let mut policy = ModelAdaptationPolicy::default();
+        let current = preset(LARGE_V3_TURBO_Q5_MODEL_PRESET_ID);
+
+        assert!(feed(&mut policy, Instant::now(), &current, 9, 800.0, 0.0).is_none());
+        let decision = feed(&mut policy, Instant::now(), &current, 1, 800.0, 0.0).unwrap();
+
+        assert_eq!(decision.direction, AdaptationDirection::Downgrade);
+        assert_eq!(decision.to.id, BASE_MODEL_PRESET_ID);
+        assert!(decision.reason.contains("p95"));
+    }
+
+    #[test]
+    fn downgrades_when_backlog_grows() {
+        let mut policy = ModelAdaptationPolicy::default();
+        let current = preset(BASE_MODEL_PRESET_ID);
+
+        let decision = feed(&mut policy, Instant::now(), &current, 10, 300.0, 1500.0).unwrap();
+
+        assert_eq!(decision.to.id, TINY_MODEL_PRESET_ID);
+        assert!(decision.reason.contains("waiting"));
+    }
+
+    #[test]
+    fn occasional_slow_inference_does_not_trigger_a_switch() {
+        let mut policy = ModelAdaptationPolicy::default();
+        let current = preset(BASE_MODEL_PRESET_ID);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(feed(&mut policy, now, &current, 19, 300.0, 0.0).is_none());
+            assert!(feed(&mut policy, now, &current, 1, 900.0, 0.0).is_none());
+        }
+    }
+
+    #[test]
+    fn upgrades_only_after_a_long_comfortable_streak_and_cooldown() {
+        let mut policy = ModelAdaptationPolicy::default();
+        let current = preset(TINY_MODEL_PRESET_ID);
+        let now = Instant::now();
+
+        assert!(feed(&mut policy, now, &current, 29, 100.0, 0.0).is_none());
+        let decision = feed(&mut policy, now, &current, 1, 100.0, 0.0).unwrap();
+        assert_eq!(decision.direction, AdaptationDirection::Upgrade);
+        assert_eq!(decision.to.id, BASE_MODEL_PRESET_ID);
+
+        assert!(feed(
+            &mut policy,
+            now + Duration::from_secs(30),
+            &current,
+            40,
+            100.0,
+            0.0
+        )
+        .is_none());
+        assert!(feed(&mut policy, now + COOLDOWN, &current, 30, 100.0, 0.0).is_some());
+    }
+
+    #[test]
+    fn does_not_upgrade_back_to_a_preset_it_downgraded_from() {
+        let mut policy = ModelAdaptationPolicy::default();
+        let now = Instant::now();
+
+        let downgrade = feed(
+            &mut policy,
+            now,
+            &preset(BASE_MODEL_PRESET_ID),
+            10,
+            800.0,
+            0.0,
+        );
+        assert_eq!(downgrade.unwrap().to.id, TINY_MODEL_PRESET_ID);
+
+        let later = now + COOLDOWN * 2;
+        assert!(feed(
+            &mut policy,
+            later,
+            &preset(TINY_MODEL_PRESET_ID),
+            60,
+            50.0,
+            0.0
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn smallest_preset_has_nowhere_to_go() {
+        let mut policy = ModelAdaptationPolicy::default();
+
+        assert!(feed(
+            &mut policy,
+            Instant::now(),
+            &preset(TINY_MODEL_PRESET_ID),
+            20,
+            900.0,
+            0.0
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn manual_presets_downgrade_to_auto_selectable_ones() {
+        let mut policy = ModelAdaptationPolicy::default();
+
+        let decision = feed(
+            &mut policy,
+            Instant::now(),
+            &preset(SMALL_MODEL_PRESET_ID),
+            10,
+            900.0,
+            0.0,
+        );
+
+        assert_eq!(decision.unwrap().to.id, BASE_MODEL_PRESET_ID);
+    }
+
+    #[test]
+    fn finds_preset_for_model_path() {
+        let presets = model_presets();
+
+        let found = preset_for_model_path(&presets, "/cache/snapshots/abc/ggml-base-q8_0.bin");
+
+        assert_eq!(
+            found.map(|preset| preset.id.as_str()),
+            Some(BASE_MODEL_PRESET_ID)
+        );
+        assert!(preset_for_model_path(&presets, "/models/custom.bin").is_none());
+        assert!(preset_for_model_path(&presets, "").is_none());
+    }
+}
@@ -0,0 +1,346 @@
+use crate::types::{model_presets, ModelPreset};
+use anyhow::{anyhow, Context};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use tracing::{info, warn};
+
+pub const CATALOG_FILE_NAMES: [&str; 2] = ["model_catalog.toml", "model_catalog.json"];
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// A preset as written in a user catalog file. Exactly one of `repo` +
+/// `filename` (Hugging Face) or `path` (local file) must be set.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogEntry {
+    id: String,
+    label: Option<String>,
+    #[serde(default)]
+    description: String,
+    repo: Option<String>,
+    filename: Option<String>,
+    path: Option<String>,
+    size_mb: Option<u16>,
+    size_bytes: Option<u64>,
+    sha256: Option<String>,
+    resource_rank: u8,
+    #[serde(default)]
+    auto_selectable: bool,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    #[serde(default)]
+    presets: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct ModelCatalogReport {
+    pub files: Vec<String>,
+    pub custom_preset_count: u32,
+    pub errors: Vec<String>,
+}
+
+/// Built-in presets followed by the valid entries of the user catalog.
+#[derive(Clone)]
+pub struct ModelCatalog {
+    inner: Arc<RwLock<Vec<ModelPreset>>>,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(model_presets())),
+        }
+    }
+}
+
+impl ModelCatalog {
+    pub fn presets(&self) -> Vec<ModelPreset> {
+        self.inner.read().unwrap().clone()
+    }
+
+    pub fn find(&self, id: &str) -> Option<ModelPreset> {
+        self.inner
+            .read()
+            .unwrap()
+            .iter()
+            .find(|preset| preset.id == id)
+            .cloned()
+    }
+
+    pub fn reload(&self, config_dir: &Path) -> ModelCatalogReport {
+        let (presets, report) = load_catalog(config_dir);
+        for error in &report.errors {
+            warn!("model catalog: {}", error);
+        }
+        info!(
+            "loaded {} custom model presets from {:?}",
+            report.custom_preset_count, report.files
+        );
+        *self.inner.write().unwrap() = presets;
+        report
+    }
+}
+
+pub fn load_catalog(config_dir: &Path) -> (Vec<ModelPreset>, ModelCatalogReport) {
+    let mut presets = model_presets();
+    let mut report = ModelCatalogReport::default();
+    let mut ids: HashSet<String> = presets.iter().map(|preset| preset.id.clone()).collect();
+
+    for file_name in CATALOG_FILE_NAMES {
+        let path = config_dir.join(file_name);
+        if !path.exists() {
+            continue;
+        }
+        report.files.push(path.to_string_lossy().into_owned());
+
+        let catalog = match read_catalog_file(&path) {
+            Ok(catalog) => catalog,
+            Err(err) => {
+                report.errors.push(format!("{:#}", err));
+                continue;
+            }
+        };
+
+        for entry in catalog.presets {
+            let id = entry.id.clone();
+            match validate_entry(entry, &ids) {
+                Ok(preset) => {
+                    ids.insert(preset.id.clone());
+                    presets.push(preset);
+                    report.custom_preset_count += 1;
+                }
+                Err(err) => report
+                    .errors
+                    .push(format!("{}: preset {:?}: {}", file_name, id, err)),
+            }
+        }
+    }
+
+    (presets, report)
+}
+
+fn read_catalog_file(path: &Path) -> Result<CatalogFile, anyhow::Error> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let catalog = if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        toml::from_str(&contents).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&contents).map_err(anyhow::Error::from)
+    };
+    catalog.with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn validate_entry(
+    entry: CatalogEntry,
+    existing_ids: &HashSet<String>,
+) -> Result<ModelPreset, anyhow::Error> {
+    let id = entry.id.trim();
+    if id.is_empty() {
+        return Err(anyhow!("id must not be empty"));
+    }
+    if existing_ids.contains(id) {
+        return Err(anyhow!("id is already used by another preset"));
+    }
+    if entry.resource_rank == 0 {
+        return Err(anyhow!("resource_rank must be at least 1"));
+    }
+    if let Some(sha256) = &entry.sha256 {
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("sha256 must be 64 hex characters"));
+        }
+    }
+
+    let (repo, filename, local_path, size_bytes) = match (entry.repo, entry.filename, entry.path) {
+        (Some(repo), Some(filename), None) => {
+            validate_repo(&repo)?;
+            validate_filename(&filename)?;
+            (repo, filename, None, entry.size_bytes)
+        }
+        (None, None, Some(path)) => {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(anyhow!("path must be absolute"));
+            }
+            let metadata = fs::metadata(&path)
+                .with_context(|| format!("model file {} not found", path.display()))?;
+            if !metadata.is_file() {
+                return Err(anyhow!("{} is not a file", path.display()));
+            }
+            let filename = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            (
+                String::new(),
+                filename,
+                Some(path.to_string_lossy().into_owned()),
+                entry.size_bytes.or(Some(metadata.len())),
+            )
+        }
+        _ => {
+            return Err(anyhow!(
+                "set either repo and filename, or path, but not both"
+            ))
+        }
+    };
+
+    let size_mb = entry
+        .size_mb
+        .or_else(|| size_bytes.map(|bytes| (bytes / BYTES_PER_MB).min(u16::MAX as u64) as u16))
+        .unwrap_or(0);
+
+    Ok(ModelPreset {
+        id: id.to_owned(),
+        label: entry.label.unwrap_or_else(|| id.to_owned()),
+        description: entry.description,
+        repo,
+        filename,
+        local_path,
+        size_mb,
+        size_bytes,
+        sha256: entry.sha256.map(|sha256| sha256.to_ascii_lowercase()),
+        resource_rank: entry.resource_rank,
+        auto_selectable: entry.auto_selectable,
+    })
+}
+
+fn validate_repo(repo: &str) -> Result<(), anyhow::Error> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match repo.split_once('/') {
+        Some((owner, name)) if valid_part(owner) && valid_part(name) => Ok(()),
+        _ => Err(anyhow!("repo must look like \"owner/name\"")),
+    }
+}
+
+fn validate_filename(filename: &str) -> Result<(), anyhow::Error> {
+    if filename.is_empty()
+        || filename.contains(['/', '\\'])
+        || filename.starts_with('.')
+        || !filename.ends_with(".bin")
+    {
+        return Err(anyhow!("filename must be a plain *.bin file name"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_catalog_yields_builtin_presets() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let (presets, report) = load_catalog(dir.path());
+
+        assert_eq!(presets.len(), model_presets().len());
+        assert!(report.files.is_empty());
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn merges_toml_catalog_after_builtin_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("ggml-finetune.bin");
+        fs::write(&local, vec![0_u8; 2 * BYTES_PER_MB as usize]).unwrap();
+        fs::write(
+            dir.path().join("model_catalog.toml"),
+            format!(
+                r#"
+[[presets]]
+id = "distil-large-v3"
+label = "Distil Large v3"
+repo = "distil-whisper/distil-large-v3-ggml"
+filename = "ggml-distil-large-v3.bin"
+size_mb = 1520
+resource_rank = 5
+auto_selectable = true
+
+[[presets]]
+id = "base-en"
+repo = "ggerganov/whisper.cpp"
+filename = "ggml-base.en.bin"
+resource_rank = 2
+
+[[presets]]
+id = "my-finetune"
+path = {:?}
+resource_rank = 3
+"#,
+                local.to_string_lossy()
+            ),
+        )
+        .unwrap();
+
+        let (presets, report) = load_catalog(dir.path());
+
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.custom_preset_count, 3);
+        let custom = &presets[model_presets().len()..];
+        assert_eq!(custom[0].id, "distil-large-v3");
+        assert!(custom[0].auto_selectable);
+        assert_eq!(custom[1].label, "base-en");
+        assert!(!custom[1].auto_selectable);
+        assert_eq!(
+            custom[2].local_path.as_deref(),
+            Some(&*local.to_string_lossy())
+        );
+        assert_eq!(custom[2].filename, "ggml-finetune.bin");
+        assert_eq!(custom[2].size_mb, 2);
+    }
+
+    #[test]
+    fn reports_invalid_entries_and_keeps_valid_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("model_catalog.json"),
+            r#"{"presets": [
+                {"id": "base-q8_0", "repo": "a/b", "filename": "x.bin", "resource_rank": 1},
+                {"id": "no-source", "resource_rank": 1},
+                {"id": "both", "repo": "a/b", "filename": "x.bin", "path": "/x.bin", "resource_rank": 1},
+                {"id": "bad-repo", "repo": "../etc", "filename": "x.bin", "resource_rank": 1},
+                {"id": "bad-file", "repo": "a/b", "filename": "../x.bin", "resource_rank": 1},
+                {"id": "relative", "path": "models/x.bin", "resource_rank": 1},
+                {"id": "zero-rank", "repo": "a/b", "filename": "x.bin", "resource_rank": 0},
+                {"id": "bad-sha", "repo": "a/b", "filename": "x.bin", "resource_rank": 1, "sha256": "abc"},
+                {"id": "ok", "repo": "a/b", "filename": "x.bin", "resource_rank": 1},
+                {"id": "ok", "repo": "a/b", "filename": "y.bin", "resource_rank": 1}
+            ]}"#,
+        )
+        .unwrap();
+
+        let (presets, report) = load_catalog(dir.path());
+
+        assert_eq!(report.custom_preset_count, 1);
+        assert_eq!(report.errors.len(), 9, "{:?}", report.errors);
+        assert_eq!(presets.last().unwrap().id, "ok");
+    }
+
+    #[test]
+    fn unparseable_catalog_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("model_catalog.toml"), "[[presets]\nid = ").unwrap();
+
+        let (presets, report) = load_catalog(dir.path());
+
+        assert_eq!(presets.len(), model_presets().len());
+        assert_eq!(report.errors.len(), 1);
+    }
+}
@@ -0,0 +1,792 @@
+use crate::{downloads, types::ModelPreset};
+use anyhow::{anyhow, Context};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use tracing::{debug, info};
+
+pub const MODELS_DIR: &str = "models";
+const IMPORTED_DIR: &str = "imported";
+const CHECKSUMS_FILE: &str = "checksums.json";
+
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGML_QNT_VERSION_FACTOR: i32 = 1000;
+const MULTILINGUAL_VOCAB_SIZE: i32 = 51865;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalModelSource {
+    HfCache,
+    Downloaded,
+    Imported,
+    UserFolder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    /// The file hash matches the reference checksum.
+    Verified,
+    /// The file hash differs from the reference checksum.
+    Mismatch,
+    /// A reference checksum exists but the file hasn't been hashed yet.
+    Unchecked,
+    /// There is no reference checksum to compare against.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct GgmlModelInfo {
+    pub model_type: String,
+    pub quantization: String,
+    pub english_only: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct LocalModel {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub source: LocalModelSource,
+    pub repo: Option<String>,
+    pub preset_id: Option<String>,
+    pub ggml: Option<GgmlModelInfo>,
+    pub checksum: ChecksumStatus,
+    pub in_use: bool,
+}
+
+/// Places scrybe looks for model files.
+#[derive(Debug, Clone)]
+pub struct LibraryRoots {
+    pub hf_cache: PathBuf,
+    pub models_dir: PathBuf,
+    pub user_folders: Vec<PathBuf>,
+}
+
+impl LibraryRoots {
+    fn contains(&self, path: &Path) -> bool {
+        std::iter::once(&self.hf_cache)
+            .chain(std::iter::once(&self.models_dir))
+            .chain(self.user_folders.iter())
+            .filter_map(|root| root.canonicalize().ok())
+            .any(|root| path.starts_with(root))
+    }
+}
+
+struct Candidate {
+    path: PathBuf,
+    source: LocalModelSource,
+    repo: Option<String>,
+    reference_sha256: Option<String>,
+}
+
+pub fn list_models(
+    roots: &LibraryRoots,
+    presets: &[ModelPreset],
+    active_model_path: &str,
+) -> Vec<LocalModel> {
+    let mut candidates = Vec::new();
+    collect_hf_cache(&roots.hf_cache, &mut candidates);
+    collect_models_dir(&roots.models_dir, &mut candidates);
+    for folder in &roots.user_folders {
+        collect_folder(folder, LocalModelSource::UserFolder, None, &mut candidates);
+    }
+    if !active_model_path.is_empty() {
+        candidates.push(Candidate {
+            path: PathBuf::from(active_model_path),
+            source: LocalModelSource::UserFolder,
+            repo: None,
+            reference_sha256: None,
+        });
+    }
+
+    let checksums = ChecksumCache::load(&roots.models_dir);
+    let active = canonical(Path::new(active_model_path));
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|candidate| {
+            canonical(&candidate.path).is_some_and(|resolved| seen.insert(resolved))
+        })
+        .filter_map(|candidate| describe(candidate, presets, &checksums, active.as_deref()))
+        .collect()
+}
+
+/// Hashes `path` and records the result so later listings can report it.
+pub fn verify_model(
+    roots: &LibraryRoots,
+    presets: &[ModelPreset],
+    active_model_path: &str,
+    path: &Path,
+) -> Result<LocalModel, anyhow::Error> {
+    let resolved = canonical(path).ok_or_else(|| anyhow!("{} not found", path.display()))?;
+    let sha256 = downloads::sha256_file(&resolved)?;
+
+    let mut checksums = ChecksumCache::load(&roots.models_dir);
+    checksums.record(&resolved, sha256)?;
+    checksums.save(&roots.models_dir)?;
+
+    list_models(roots, presets, active_model_path)
+        .into_iter()
+        .find(|model| canonical(Path::new(&model.path)).as_deref() == Some(resolved.as_path()))
+        .ok_or_else(|| anyhow!("{} is not in the model library", path.display()))
+}
+
+/// Records that `path` was just downloaded and verified against `sha256`,
+/// which then serves as its reference checksum.
+pub fn record_download(models_dir: &Path, path: &Path, sha256: &str) -> Result<(), anyhow::Error> {
+    let resolved = canonical(path).ok_or_else(|| anyhow!("{} not found", path.display()))?;
+    let mut checksums = ChecksumCache::load(models_dir);
+    checksums.record(&resolved, sha256.to_ascii_lowercase())?;
+    checksums.references.insert(
+        resolved.to_string_lossy().into_owned(),
+        sha256.to_ascii_lowercase(),
+    );
+    checksums.save(models_dir)
+}
+
+/// Resolves `path`, following links, and refuses anything outside the
+/// library folders.
+pub fn resolve_in_library(roots: &LibraryRoots, path: &Path) -> Result<PathBuf, anyhow::Error> {
+    let resolved = canonical(path).ok_or_else(|| anyhow!("{} not found", path.display()))?;
+    if !roots.contains(&resolved) {
+        return Err(anyhow!(
+            "{} is outside the model library folders",
+            path.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+pub fn delete_model(
+    roots: &LibraryRoots,
+    active_model_path: &str,
+    path: &Path,
+) -> Result<(), anyhow::Error> {
+    let resolved = resolve_in_library(roots, path)?;
+    if canonical(Path::new(active_model_path)).as_deref() == Some(resolved.as_path()) {
+        return Err(anyhow!("{} is the model currently in use", path.display()));
+    }
+    // Hub cache snapshots are symlinks into the blob store, whose files are
+    // named by hash rather than `.bin`; those are told apart by their header.
+    let is_symlink = fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false);
+    let is_model = is_model_file(&resolved)
+        || (is_symlink && is_model_file(path) && read_ggml_info(&resolved).is_ok());
+    if !is_model {
+        return Err(anyhow!("{} is not a model file", path.display()));
+    }
+
+    // Remove both the snapshot link and its blob so the space is actually
+    // freed.
+    if is_symlink {
+        fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+    fs::remove_file(&resolved)
+        .with_context(|| format!("failed to remove {}", resolved.display()))?;
+    info!("deleted model {}", resolved.display());
+
+    let mut checksums = ChecksumCache::load(&roots.models_dir);
+    if checksums.forget(&resolved) {
+        checksums.save(&roots.models_dir)?;
+    }
+    Ok(())
+}
+
+/// Copies a `ggml-*.bin` file into the library after checking its header.
+pub fn import_model(models_dir: &Path, source: &Path) -> Result<PathBuf, anyhow::Error> {
+    let file_name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("{} has no file name", source.display()))?;
+    if !is_ggml_file_name(file_name) {
+        return Err(anyhow!("{} is not a ggml-*.bin file", file_name));
+    }
+    read_ggml_info(source)?;
+
+    let destination = models_dir.join(IMPORTED_DIR).join(file_name);
+    if destination.exists() {
+        return Err(anyhow!("{} has already been imported", file_name));
+    }
+    fs::create_dir_all(destination.parent().unwrap())?;
+
+    let part = downloads::part_path(&destination);
+    fs::copy(source, &part).with_context(|| format!("failed to copy {}", source.display()))?;
+    fs::rename(&part, &destination)?;
+    info!("imported {} to {}", source.display(), destination.display());
+    Ok(destination)
+}
+
+/// Reads the whisper.cpp GGML header: magic followed by eleven `i32`
+/// hyperparameters, the last of which is the tensor type.
+pub fn read_ggml_info(path: &Path) -> Result<GgmlModelInfo, anyhow::Error> {
+    let mut header = [0_u8; 48];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .with_context(|| format!("failed to read header of {}", path.display()))?;
+
+    let field = |index: usize| {
+        let bytes = header[index * 4..index * 4 + 4].try_into().unwrap();
+        i32::from_le_bytes(bytes)
+    };
+    if field(0) as u32 != GGML_MAGIC {
+        return Err(anyhow!("{} is not a GGML model", path.display()));
+    }
+
+    let n_vocab = field(1);
+    let n_audio_layer = field(5);
+    let n_text_layer = field(9);
+    let n_mels = field(10);
+    let ftype = field(11) % GGML_QNT_VERSION_FACTOR;
+
+    let model_type = match (n_audio_layer, n_mels, n_text_layer) {
+        (4, _, _) => "tiny",
+        (6, _, _) => "base",
+        (12, _, _) => "small",
+        (24, _, _) => "medium",
+        (32, 128, 4) => "large-v3-turbo",
+        (32, 128, _) => "large-v3",
+        (32, _, _) => "large",
+        _ => "unknown",
+    };
+    let quantization = match ftype {
+        0 => "f32",
+        1 => "f16",
+        2 => "q4_0",
+        3 => "q4_1",
+        7 => "q8_0",
+        8 => "q5_0",
+        9 => "q5_1",
+        10 => "q2_k",
+        11 => "q3_k",
+        12 => "q4_k",
+        13 => "q5_k",
+        14 => "q6_k",
+        _ => "unknown",
+    };
+
+    Ok(GgmlModelInfo {
+        model_type: model_type.to_owned(),
+        quantization: quantization.to_owned(),
+        english_only: n_vocab < MULTILINGUAL_VOCAB_SIZE,
+    })
+}
+
+fn describe(
+    candidate: Candidate,
+    presets: &[ModelPreset],
+    checksums: &ChecksumCache,
+    active: Option<&Path>,
+) -> Option<LocalModel> {
+    let resolved = canonical(&candidate.path)?;
+    let size_bytes = fs::metadata(&resolved).ok()?.len();
+    let file_name = candidate.path.file_name()?.to_string_lossy().into_owned();
+    let ggml = read_ggml_info(&resolved).ok();
+    if ggml.is_none() && candidate.source == LocalModelSource::UserFolder {
+        debug!("skipping non-GGML file {}", resolved.display());
+        return None;
+    }
+
+    let preset = presets.iter().find(|preset| {
+        preset.filename == file_name
+            && candidate
+                .repo
+                .as_ref()
+                .is_none_or(|repo| *repo == preset.repo)
+    });
+    let reference = preset
+        .and_then(|preset| preset.sha256.clone())
+        .or(candidate.reference_sha256)
+        .or_else(|| checksums.reference(&resolved).map(str::to_owned));
+    let checksum = match (reference, checksums.lookup(&resolved)) {
+        (None, _) => ChecksumStatus::Unknown,
+        (Some(_), None) => ChecksumStatus::Unchecked,
+        (Some(reference), Some(actual)) if reference.eq_ignore_ascii_case(actual) => {
+            ChecksumStatus::Verified
+        }
+        (Some(_), Some(_)) => ChecksumStatus::Mismatch,
+    };
+
+    Some(LocalModel {
+        path: candidate.path.to_string_lossy().into_owned(),
+        file_name,
+        size_bytes,
+        source: candidate.source,
+        repo: candidate.repo,
+        preset_id: preset.map(|preset| preset.id.clone()),
+        ggml,
+        checksum,
+        in_use: active == Some(resolved.as_path()),
+    })
+}
+
+// Layout: models--{org}--{name}/snapshots/{revision}/{file} -> ../../blobs/{etag}
+fn collect_hf_cache(hf_cache: &Path, candidates: &mut Vec<Candidate>) {
+    for repo_dir in read_dir_paths(hf_cache) {
+        let Some(repo) = repo_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("models--"))
+            .map(|name| name.replacen("--", "/", 1))
+        else {
+            continue;
+        };
+
+        for snapshot in read_dir_paths(&repo_dir.join("snapshots")) {
+            for path in read_dir_paths(&snapshot) {
+                if !is_model_file(&path) {
+                    continue;
+                }
+                let reference_sha256 = fs::read_link(&path)
+                    .ok()
+                    .and_then(|blob| blob.file_name()?.to_str().map(str::to_owned))
+                    .filter(|etag| etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit()));
+                candidates.push(Candidate {
+                    path,
+                    source: LocalModelSource::HfCache,
+                    repo: Some(repo.clone()),
+                    reference_sha256,
+                });
+            }
+        }
+    }
+}
+
+// Layout: models/{org}--{name}/{file} for downloads, models/imported/{file}.
+fn collect_models_dir(models_dir: &Path, candidates: &mut Vec<Candidate>) {
+    for dir in read_dir_paths(models_dir) {
+        let Some(name) = dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name == IMPORTED_DIR {
+            collect_folder(&dir, LocalModelSource::Imported, None, candidates);
+        } else if dir.is_dir() {
+            let repo = name.replacen("--", "/", 1);
+            collect_folder(&dir, LocalModelSource::Downloaded, Some(repo), candidates);
+        }
+    }
+}
+
+fn collect_folder(
+    folder: &Path,
+    source: LocalModelSource,
+    repo: Option<String>,
+    candidates: &mut Vec<Candidate>,
+) {
+    for path in read_dir_paths(folder) {
+        if is_model_file(&path) {
+            candidates.push(Candidate {
+                path,
+                source,
+                repo: repo.clone(),
+                reference_sha256: None,
+            });
+        }
+    }
+}
+
+fn read_dir_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+fn is_model_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "bin") && path.is_file()
+}
+
+fn is_ggml_file_name(file_name: &str) -> bool {
+    file_name.starts_with("ggml-") && file_name.ends_with(".bin")
+}
+
+fn canonical(path: &Path) -> Option<PathBuf> {
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    path.canonicalize().ok()
+}
+
+/// Hashes computed by [`verify_model`], keyed by resolved path and
+/// invalidated when the file's size or modification time changes, plus the
+/// checksums files were verified against when downloaded.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct ChecksumCache {
+    entries: HashMap<String, ChecksumEntry>,
+    #[serde(default)]
+    references: HashMap<String, String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ChecksumEntry {
+    size_bytes: u64,
+    modified_ms: u128,
+    sha256: String,
+}
+
+impl ChecksumCache {
+    fn load(models_dir: &Path) -> Self {
+        fs::read_to_string(models_dir.join(CHECKSUMS_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, models_dir: &Path) -> Result<(), anyhow::Error> {
+        fs::create_dir_all(models_dir)?;
+        fs::write(
+            models_dir.join(CHECKSUMS_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn lookup(&self, resolved: &Path) -> Option<&str> {
+        let entry = self.entries.get(&*resolved.to_string_lossy())?;
+        let (size_bytes, modified_ms) = file_stamp(resolved)?;
+        (entry.size_bytes == size_bytes && entry.modified_ms == modified_ms)
+            .then_some(entry.sha256.as_str())
+    }
+
+    fn record(&mut self, resolved: &Path, sha256: String) -> Result<(), anyhow::Error> {
+        let (size_bytes, modified_ms) =
+            file_stamp(resolved).ok_or_else(|| anyhow!("failed to stat {}", resolved.display()))?;
+        self.entries.insert(
+            resolved.to_string_lossy().into_owned(),
+            ChecksumEntry {
+                size_bytes,
+                modified_ms,
+                sha256,
+            },
+        );
+        Ok(())
+    }
+
+    fn reference(&self, resolved: &Path) -> Option<&str> {
+        self.references
+            .get(&*resolved.to_string_lossy())
+            .map(String::as_str)
+    }
+
+    fn forget(&mut self, resolved: &Path) -> bool {
+        let key = resolved.to_string_lossy();
+        let had_reference = self.references.remove(&*key).is_some();
+        self.entries.remove(&*key).is_some() || had_reference
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u128)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified_ms = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    Some((metadata.len(), modified_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn ggml_bytes(
+        n_vocab: i32,
+        n_audio_layer: i32,
+        n_text_layer: i32,
+        n_mels: i32,
+        ftype: i32,
+    ) -> Vec<u8> {
+        let fields = [
+            GGML_MAGIC as i32,
+            n_vocab,
+            1500,
+            512,
+            8,
+            n_audio_layer,
+            448,
+            512,
+            8,
+            n_text_layer,
+            n_mels,
+            ftype,
+        ];
+        let mut bytes: Vec<u8> = fields
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect();
+        bytes.extend_from_slice(&[0xab; 64]);
+        bytes
+    }
+
+    fn roots(base: &Path) -> LibraryRoots {
+        LibraryRoots {
+            hf_cache: base.join("hf"),
+            models_dir: base.join("models"),
+            user_folders: vec![base.join("user")],
+        }
+    }
+
+    fn preset(id: &str, filename: &str) -> ModelPreset {
+        ModelPreset {
+            id: id.to_owned(),
+            label: id.to_owned(),
+            description: String::new(),
+            repo: "ggerganov/whisper.cpp".to_owned(),
+            filename: filename.to_owned(),
+            local_path: None,
+            size_mb: 1,
+            size_bytes: None,
+            sha256: None,
+            resource_rank: 1,
+            auto_selectable: true,
+        }
+    }
+
+    #[test]
+    fn reads_model_type_and_quantization_from_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = [
+            (ggml_bytes(51865, 4, 4, 80, 1007), "tiny", "q8_0", false),
+            (ggml_bytes(51864, 6, 6, 80, 1), "base", "f16", true),
+            (
+                ggml_bytes(51866, 32, 4, 128, 1008),
+                "large-v3-turbo",
+                "q5_0",
+                false,
+            ),
+            (ggml_bytes(51866, 32, 32, 128, 9), "large-v3", "q5_1", false),
+        ];
+
+        for (index, (bytes, model_type, quantization, english_only)) in
+            cases.into_iter().enumerate()
+        {
+            let path = dir.path().join(format!("ggml-{}.bin", index));
+            fs::write(&path, bytes).unwrap();
+
+            let info = read_ggml_info(&path).unwrap();
+
+            assert_eq!(info.model_type, model_type);
+            assert_eq!(info.quantization, quantization);
+            assert_eq!(info.english_only, english_only);
+        }
+    }
+
+    #[test]
+    fn rejects_files_without_ggml_magic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ggml-fake.bin");
+        fs::write(&path, [0_u8; 64]).unwrap();
+
+        assert!(read_ggml_info(&path).is_err());
+        assert!(import_model(&dir.path().join("models"), &path).is_err());
+    }
+
+    #[test]
+    fn lists_downloaded_imported_and_user_models_with_preset_match() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let downloaded = roots
+            .models_dir
+            .join("ggerganov--whisper.cpp/ggml-tiny-q8_0.bin");
+        fs::create_dir_all(downloaded.parent().unwrap()).unwrap();
+        fs::write(&downloaded, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        fs::write(downloads::part_path(&downloaded), b"partial").unwrap();
+        let user = roots.user_folders[0].join("custom.bin");
+        fs::create_dir_all(user.parent().unwrap()).unwrap();
+        fs::write(&user, ggml_bytes(51865, 12, 12, 80, 1)).unwrap();
+        fs::write(roots.user_folders[0].join("notes.bin"), b"not a model").unwrap();
+        let external = base.path().join("ggml-medium.bin");
+        fs::write(&external, ggml_bytes(51865, 24, 24, 80, 1)).unwrap();
+        import_model(&roots.models_dir, &external).unwrap();
+
+        let presets = [preset("tiny-q8_0", "ggml-tiny-q8_0.bin")];
+        let models = list_models(&roots, &presets, &user.to_string_lossy());
+
+        let summary: Vec<_> = models
+            .iter()
+            .map(|model| {
+                (
+                    model.file_name.as_str(),
+                    model.source,
+                    model.preset_id.as_deref(),
+                    model.in_use,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "ggml-tiny-q8_0.bin",
+                    LocalModelSource::Downloaded,
+                    Some("tiny-q8_0"),
+                    false
+                ),
+                ("ggml-medium.bin", LocalModelSource::Imported, None, false),
+                ("custom.bin", LocalModelSource::UserFolder, None, true),
+            ]
+        );
+        assert_eq!(models[1].ggml.as_ref().unwrap().model_type, "medium");
+        assert!(models
+            .iter()
+            .all(|model| model.checksum == ChecksumStatus::Unknown));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hf_cache_models_are_verified_against_blob_etag() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let bytes = ggml_bytes(51865, 6, 6, 80, 7);
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        let repo_dir = roots.hf_cache.join("models--ggerganov--whisper.cpp");
+        let blob = repo_dir.join("blobs").join(&sha256);
+        let snapshot = repo_dir.join("snapshots/abc123/ggml-base-q8_0.bin");
+        fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+        fs::write(&blob, &bytes).unwrap();
+        std::os::unix::fs::symlink(&blob, &snapshot).unwrap();
+
+        let presets = [preset("base-q8_0", "ggml-base-q8_0.bin")];
+        let models = list_models(&roots, &presets, "");
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].source, LocalModelSource::HfCache);
+        assert_eq!(models[0].repo.as_deref(), Some("ggerganov/whisper.cpp"));
+        assert_eq!(models[0].preset_id.as_deref(), Some("base-q8_0"));
+        assert_eq!(models[0].checksum, ChecksumStatus::Unchecked);
+
+        let verified = verify_model(&roots, &presets, "", &snapshot).unwrap();
+        assert_eq!(verified.checksum, ChecksumStatus::Verified);
+
+        delete_model(&roots, "", &snapshot).unwrap();
+        assert!(!snapshot.exists());
+        assert!(!blob.exists());
+        assert!(list_models(&roots, &presets, "").is_empty());
+    }
+
+    #[test]
+    fn verify_reports_mismatch_against_preset_checksum() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let path = roots
+            .models_dir
+            .join("ggerganov--whisper.cpp/ggml-tiny-q8_0.bin");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        let mut tiny = preset("tiny-q8_0", "ggml-tiny-q8_0.bin");
+        tiny.sha256 = Some("0".repeat(64));
+
+        let model = verify_model(&roots, &[tiny], "", &path).unwrap();
+
+        assert_eq!(model.checksum, ChecksumStatus::Mismatch);
+    }
+
+    #[test]
+    fn downloads_are_verified_against_the_checksum_they_were_fetched_with() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let path = roots
+            .models_dir
+            .join("ggerganov--whisper.cpp/ggml-tiny-q8_0.bin");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let bytes = ggml_bytes(51865, 4, 4, 80, 7);
+        fs::write(&path, &bytes).unwrap();
+        let presets = [preset("tiny-q8_0", "ggml-tiny-q8_0.bin")];
+
+        record_download(
+            &roots.models_dir,
+            &path,
+            &format!("{:x}", Sha256::digest(&bytes)),
+        )
+        .unwrap();
+        assert_eq!(
+            list_models(&roots, &presets, "")[0].checksum,
+            ChecksumStatus::Verified
+        );
+
+        fs::write(&path, ggml_bytes(51865, 4, 4, 80, 8)).unwrap();
+        let model = verify_model(&roots, &presets, "", &path).unwrap();
+        assert_eq!(model.checksum, ChecksumStatus::Mismatch);
+    }
+
+    #[test]
+    fn resolves_only_paths_inside_the_library() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let outside = base.path().join("elsewhere.bin");
+        fs::write(&outside, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        let inside = roots.user_folders[0].join("ggml-tiny.bin");
+        fs::create_dir_all(inside.parent().unwrap()).unwrap();
+        fs::write(&inside, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        let escaping = roots.user_folders[0].join("..").join("elsewhere.bin");
+
+        assert_eq!(
+            resolve_in_library(&roots, &inside).unwrap(),
+            inside.canonicalize().unwrap()
+        );
+        assert!(resolve_in_library(&roots, &outside).is_err());
+        assert!(resolve_in_library(&roots, &escaping).is_err());
+        assert!(resolve_in_library(&roots, &roots.user_folders[0].join("missing.bin")).is_err());
+    }
+
+    #[test]
+    fn delete_refuses_active_model_and_paths_outside_library() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let outside = base.path().join("elsewhere.bin");
+        fs::write(&outside, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        let inside = roots.user_folders[0].join("ggml-tiny.bin");
+        fs::create_dir_all(inside.parent().unwrap()).unwrap();
+        fs::write(&inside, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+
+        assert!(delete_model(&roots, "", &outside).is_err());
+        assert!(delete_model(&roots, &inside.to_string_lossy(), &inside).is_err());
+        assert!(outside.exists() && inside.exists());
+
+        delete_model(&roots, "", &inside).unwrap();
+        assert!(!inside.exists());
+    }
+
+    #[test]
+    fn delete_refuses_files_that_arent_models() {
+        let base = tempfile::tempdir().unwrap();
+        let roots = roots(base.path());
+        let model = roots
+            .models_dir
+            .join("ggerganov--whisper.cpp/ggml-tiny-q8_0.bin");
+        fs::create_dir_all(model.parent().unwrap()).unwrap();
+        fs::write(&model, ggml_bytes(51865, 4, 4, 80, 7)).unwrap();
+        verify_model(&roots, &[], "", &model).unwrap();
+        let checksums = roots.models_dir.join(CHECKSUMS_FILE);
+        let part = downloads::part_path(&model);
+        fs::write(&part, b"partial").unwrap();
+
+        assert!(delete_model(&roots, "", &checksums).is_err());
+        assert!(delete_model(&roots, "", &part).is_err());
+        assert!(checksums.exists() && part.exists());
+    }
+
+    #[test]
+    fn import_rejects_duplicates_and_non_ggml_names() {
+        let base = tempfile::tempdir().unwrap();
+        let models_dir = base.path().join("models");
+        let source = base.path().join("ggml-small.bin");
+        fs::write(&source, ggml_bytes(51865, 12, 12, 80, 7)).unwrap();
+        let renamed = base.path().join("small.bin");
+        fs::copy(&source, &renamed).unwrap();
+
+        let imported = import_model(&models_dir, &source).unwrap();
+
+        assert_eq!(fs::read(&imported).unwrap(), fs::read(&source).unwrap());
+        assert!(import_model(&models_dir, &source).is_err());
+        assert!(import_model(&models_dir, &renamed).is_err());
+    }
+}
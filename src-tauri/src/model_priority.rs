@@ -0,0 +1,155 @@
+use scrybe_core::whisper::CancellationToken;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Lets live captioning go ahead of batch transcriptions sharing the loaded
+/// model. Batch work runs in short pieces and waits before each one until no
+/// live step wants the model, so a live step waits for at most one piece.
+///
+/// Batch work has its own cancellation token: stopping live captions cancels
+/// the model's token, which mustn't abort an upload.
+#[derive(Debug, Default)]
+pub struct ModelPriority {
+    live_steps: Mutex<usize>,
+    live_idle: Condvar,
+    batch_cancellation: CancellationToken,
+}
+
+/// Held by a live step from before it locks the model until its inference is
+/// done.
+#[must_use]
+pub struct LiveStep<'a> {
+    priority: &'a ModelPriority,
+}
+
+impl ModelPriority {
+    pub fn live_step(&self) -> LiveStep<'_> {
+        *self.live_steps.lock().unwrap() += 1;
+        LiveStep { priority: self }
+    }
+
+    /// Blocks until no live step is waiting for or using the model.
+    fn wait_for_live_steps(&self) {
+        let live_steps = self.live_steps.lock().unwrap();
+        let _idle = self
+            .live_idle
+            .wait_while(live_steps, |live_steps| *live_steps > 0)
+            .unwrap();
+    }
+
+    /// Locks `model` for a piece of batch work once no live step wants it.
+    /// A live step that turns up while this waits for the lock still goes
+    /// first: the lock is given back and the wait starts over.
+    pub fn lock_for_batch<'m, T>(&self, model: &'m Mutex<T>) -> MutexGuard<'m, T> {
+        loop {
+            self.wait_for_live_steps();
+            let model = model.lock().unwrap();
+            if *self.live_steps.lock().unwrap() == 0 {
+                return model;
+            }
+        }
+    }
+
+    /// What batch work passes to inference so only a model reload or
+    /// shutdown aborts it.
+    pub fn batch_cancellation(&self) -> &CancellationToken {
+        &self.batch_cancellation
+    }
+
+    /// Aborts batch work in progress. Call when the model is replaced or the
+    /// app exits.
+    pub fn cancel_batch_work(&self) {
+        self.batch_cancellation.cancel();
+    }
+}
+
+impl Drop for LiveStep<'_> {
+    fn drop(&mut self) {
+        let mut live_steps = self.priority.live_steps.lock().unwrap();
+        *live_steps -= 1;
+        if *live_steps == 0 {
+            self.priority.live_idle.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn batch_work_waits_for_live_steps_to_finish() {
+        let priority = Arc::new(ModelPriority::default());
+        priority.wait_for_live_steps();
+
+        let live_step = priority.live_step();
+        let resumed = Arc::new(AtomicBool::new(false));
+        let batch = {
+            let priority = priority.clone();
+            let resumed = resumed.clone();
+            thread::spawn(move || {
+                priority.wait_for_live_steps();
+                resumed.store(true, Ordering::SeqCst);
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!resumed.load(Ordering::SeqCst));
+        drop(live_step);
+        batch.join().unwrap();
+        assert!(resumed.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn batch_work_gives_way_to_a_live_step_that_arrives_while_it_waits_for_the_model() {
+        let priority = Arc::new(ModelPriority::default());
+        let model = Arc::new(Mutex::new(()));
+        let held = model.lock().unwrap();
+
+        let locked = Arc::new(AtomicBool::new(false));
+        let batch = {
+            let (priority, model, locked) = (priority.clone(), model.clone(), locked.clone());
+            thread::spawn(move || {
+                let _model = priority.lock_for_batch(&model);
+                locked.store(true, Ordering::SeqCst);
+            })
+        };
+
+        // The batch thread is past `wait_for_live_steps`, blocked on the
+        // model, when the live step shows up.
+        thread::sleep(Duration::from_millis(50));
+        let live_step = priority.live_step();
+        drop(held);
+        thread::sleep(Duration::from_millis(50));
+        assert!(!locked.load(Ordering::SeqCst));
+        let live_model = model.lock().unwrap();
+
+        drop(live_model);
+        drop(live_step);
+        batch.join().unwrap();
+        assert!(locked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn stopping_live_captions_leaves_an_upload_running() {
+        let priority = ModelPriority::default();
+        let live_cancellation = CancellationToken::default();
+        let upload = priority.batch_cancellation().checkpoint();
+
+        // What stopping live captions does.
+        live_cancellation.cancel();
+        assert!(!upload.is_cancelled());
+
+        // What reloading the model does.
+        live_cancellation.cancel();
+        priority.cancel_batch_work();
+        assert!(upload.is_cancelled());
+    }
+}
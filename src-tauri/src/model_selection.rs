@@ -1,5 +1,6 @@
 use crate::types::{
-    BASE_MODEL_PRESET_ID, LARGE_V3_TURBO_Q5_MODEL_PRESET_ID, TINY_MODEL_PRESET_ID,
+    model_presets, ModelPreset, BASE_MODEL_PRESET_ID, LARGE_V3_TURBO_Q5_MODEL_PRESET_ID,
+    TINY_MODEL_PRESET_ID,
 };
 use sysinfo::{MemoryRefreshKind, RefreshKind, System};
 
@@ -48,6 +49,27 @@ pub(crate) fn select_initial_model_preset_id(profile: HardwareProfile) -> &'stat
     BASE_MODEL_PRESET_ID
 }
 
+/// Picks the auto-selectable preset with the highest `resource_rank` that
+/// doesn't exceed the rank of the built-in preset chosen for this hardware.
+/// Later presets win ties, so a catalog entry can stand in for a built-in of
+/// the same rank.
+pub(crate) fn select_initial_model_preset(
+    profile: HardwareProfile,
+    presets: &[ModelPreset],
+) -> Option<&ModelPreset> {
+    let tier_id = select_initial_model_preset_id(profile);
+    let max_rank = model_presets()
+        .iter()
+        .find(|preset| preset.id == tier_id)
+        .map(|preset| preset.resource_rank)?;
+
+    let auto_selectable = || presets.iter().filter(|preset| preset.auto_selectable);
+    auto_selectable()
+        .filter(|preset| preset.resource_rank <= max_rank)
+        .max_by_key(|preset| preset.resource_rank)
+        .or_else(|| auto_selectable().min_by_key(|preset| preset.resource_rank))
+}
+
 fn bytes_to_gib(bytes: u64) -> Option<u64> {
     let gib = bytes / BYTES_PER_GIB;
     (gib > 0).then_some(gib)
@@ -57,8 +79,7 @@ fn bytes_to_gib(bytes: u64) -> Option<u64> {
 mod tests {
     use super::*;
     use crate::types::{
-        model_presets, LARGE_V3_TURBO_Q8_MODEL_PRESET_ID, MEDIUM_MODEL_PRESET_ID,
-        SMALL_MODEL_PRESET_ID,
+        LARGE_V3_TURBO_Q8_MODEL_PRESET_ID, MEDIUM_MODEL_PRESET_ID, SMALL_MODEL_PRESET_ID,
     };
 
     fn profile(
@@ -169,4 +190,73 @@ mod tests {
             assert!(!preset.auto_selectable);
         }
     }
+
+    fn custom_preset(id: &str, resource_rank: u8, auto_selectable: bool) -> ModelPreset {
+        ModelPreset {
+            id: id.to_owned(),
+            label: id.to_owned(),
+            description: String::new(),
+            repo: "example/whisper".to_owned(),
+            filename: format!("ggml-{}.bin", id),
+            local_path: None,
+            size_mb: 100,
+            size_bytes: None,
+            sha256: None,
+            resource_rank,
+            auto_selectable,
+        }
+    }
+
+    #[test]
+    fn builtin_catalog_selection_matches_hardware_tier() {
+        let presets = model_presets();
+
+        for (profile, expected) in [
+            (profile(Some(7), Some(8), true), TINY_MODEL_PRESET_ID),
+            (profile(Some(16), Some(8), true), BASE_MODEL_PRESET_ID),
+            (
+                profile(Some(24), Some(8), true),
+                LARGE_V3_TURBO_Q5_MODEL_PRESET_ID,
+            ),
+        ] {
+            assert_eq!(
+                select_initial_model_preset(profile, &presets).map(|preset| preset.id.as_str()),
+                Some(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn custom_auto_selectable_preset_replaces_builtin_of_equal_rank() {
+        let mut presets = model_presets();
+        presets.push(custom_preset("distil-large-v3", 5, true));
+        presets.push(custom_preset("huge-finetune", 9, true));
+        presets.push(custom_preset("base-finetune", 2, false));
+
+        let high = select_initial_model_preset(profile(Some(24), Some(8), true), &presets);
+        let modest = select_initial_model_preset(profile(Some(16), Some(8), true), &presets);
+
+        assert_eq!(
+            high.map(|preset| preset.id.as_str()),
+            Some("distil-large-v3")
+        );
+        assert_eq!(
+            modest.map(|preset| preset.id.as_str()),
+            Some(BASE_MODEL_PRESET_ID)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_cheapest_auto_selectable_preset() {
+        let presets = vec![
+            custom_preset("big", 6, true),
+            custom_preset("bigger", 7, true),
+            custom_preset("manual", 1, false),
+        ];
+
+        let selected = select_initial_model_preset(profile(Some(7), Some(8), true), &presets);
+
+        assert_eq!(selected.map(|preset| preset.id.as_str()), Some("big"));
+        assert!(select_initial_model_preset(profile(Some(7), Some(8), true), &[]).is_none());
+    }
 }
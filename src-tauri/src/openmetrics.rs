@@ -0,0 +1,383 @@
+use scrybe_core::metrics::{AudioMetricsState, SystemMetricsState, INFERENCE_HISTOGRAM_BOUNDS_MS};
+use std::fmt::Write;
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Values of `ModelLoadState`'s `state` tag, exported as a state set.
+pub const MODEL_LOAD_STATES: [&str; 6] = [
+    "idle",
+    "downloading",
+    "loading",
+    "warming_up",
+    "ready",
+    "failed",
+];
+
+pub struct MetricsSnapshot<'a> {
+    pub audio: &'a AudioMetricsState,
+    pub transcribe_running: bool,
+    pub model_load_state: &'a str,
+    pub ws_clients: usize,
+    pub ws_dropped_messages: u64,
+    pub ws_lagging_disconnects: u64,
+    pub system: &'a SystemMetricsState,
+}
+
+/// Renders the snapshot in the OpenMetrics text format. Durations are
+/// converted to seconds, the unit Prometheus expects.
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let audio = snapshot.audio;
+    let mut exposition = Exposition::default();
+
+    exposition.gauge(
+        "scrybe_transcription_running",
+        "Whether live transcription is running.",
+        None,
+        &[(&[], snapshot.transcribe_running as u8 as f64)],
+    );
+    exposition.family(
+        "scrybe_model_load_state",
+        "stateset",
+        "Progress of loading the Whisper model.",
+        None,
+    );
+    for state in MODEL_LOAD_STATES {
+        exposition.sample(
+            "scrybe_model_load_state",
+            &[("scrybe_model_load_state", state)],
+            (state == snapshot.model_load_state) as u8 as f64,
+        );
+    }
+    exposition.gauge(
+        "scrybe_websocket_clients",
+        "Connected WebSocket clients.",
+        None,
+        &[(&[], snapshot.ws_clients as f64)],
+    );
+    exposition.counter(
+        "scrybe_websocket_dropped_messages",
+        "Messages dropped because a WebSocket client's queue was full.",
+        snapshot.ws_dropped_messages,
+    );
+    exposition.counter(
+        "scrybe_websocket_lagging_disconnects",
+        "WebSocket clients disconnected for falling too far behind.",
+        snapshot.ws_lagging_disconnects,
+    );
+
+    exposition.family(
+        "scrybe_inference_duration_seconds",
+        "histogram",
+        "Duration of Whisper inference runs.",
+        Some("seconds"),
+    );
+    for (bound, count) in INFERENCE_HISTOGRAM_BOUNDS_MS
+        .iter()
+        .zip(&audio.inference_histogram)
+    {
+        exposition.sample(
+            "scrybe_inference_duration_seconds_bucket",
+            &[("le", &format_value(bound / 1000.0))],
+            *count as f64,
+        );
+    }
+    exposition.sample(
+        "scrybe_inference_duration_seconds_bucket",
+        &[("le", "+Inf")],
+        audio.inference_sample_count as f64,
+    );
+    exposition.sample(
+        "scrybe_inference_duration_seconds_count",
+        &[],
+        audio.inference_sample_count as f64,
+    );
+    exposition.sample(
+        "scrybe_inference_duration_seconds_sum",
+        &[],
+        audio.inference_sum_ms / 1000.0,
+    );
+    exposition.gauge(
+        "scrybe_inference_last_seconds",
+        "Duration of the most recent inference run.",
+        Some("seconds"),
+        &[(&[], audio.last_inference_ms / 1000.0)],
+    );
+    exposition.gauge(
+        "scrybe_inference_recent_seconds",
+        "Inference duration percentiles over a recent window.",
+        Some("seconds"),
+        &[
+            (
+                &[("window", "1m"), ("percentile", "p50")],
+                audio.inference_p50_1m_ms / 1000.0,
+            ),
+            (
+                &[("window", "1m"), ("percentile", "p95")],
+                audio.inference_p95_1m_ms / 1000.0,
+            ),
+            (
+                &[("window", "5m"), ("percentile", "p50")],
+                audio.inference_p50_5m_ms / 1000.0,
+            ),
+            (
+                &[("window", "5m"), ("percentile", "p95")],
+                audio.inference_p95_5m_ms / 1000.0,
+            ),
+        ],
+    );
+    exposition.counter(
+        "scrybe_inference_cancelled",
+        "Inference runs cancelled before completing.",
+        audio.inference_cancelled_count,
+    );
+
+    exposition.counter(
+        "scrybe_gate_evaluations",
+        "Candidate segments evaluated by the emission gate.",
+        audio.gate_total_evaluations,
+    );
+    exposition.counter(
+        "scrybe_gate_emits",
+        "Candidate segments the emission gate let through.",
+        audio.gate_total_emits,
+    );
+    exposition.counter(
+        "scrybe_gate_holds",
+        "Captions held back as a pending drastic change.",
+        audio.gate_hold_count,
+    );
+    exposition.gauge(
+        "scrybe_gate_hold_p95_seconds",
+        "95th percentile of how long the gate held a caption back.",
+        Some("seconds"),
+        &[(&[], audio.gate_hold_p95_ms / 1000.0)],
+    );
+
+    exposition.gauge(
+        "scrybe_speech_to_emit_seconds",
+        "Time from capturing speech to emitting its caption.",
+        Some("seconds"),
+        &[
+            (
+                &[("percentile", "last")],
+                audio.last_speech_to_emit_ms / 1000.0,
+            ),
+            (
+                &[("percentile", "p50")],
+                audio.speech_to_emit_p50_ms / 1000.0,
+            ),
+            (
+                &[("percentile", "p90")],
+                audio.speech_to_emit_p90_ms / 1000.0,
+            ),
+            (
+                &[("percentile", "p99")],
+                audio.speech_to_emit_p99_ms / 1000.0,
+            ),
+        ],
+    );
+    exposition.gauge(
+        "scrybe_ws_fanout_p95_seconds",
+        "95th percentile of the time to queue a caption for every client.",
+        Some("seconds"),
+        &[(&[], audio.ws_fanout_p95_ms / 1000.0)],
+    );
+
+    exposition.gauge(
+        "scrybe_step_rate_hertz",
+        "Transcription loop passes per second.",
+        Some("hertz"),
+        &[(&[], audio.effective_step_rate_hz)],
+    );
+    exposition.gauge(
+        "scrybe_step_lag_seconds",
+        "Captured audio waiting to be processed.",
+        Some("seconds"),
+        &[(&[], audio.step_lag_ms / 1000.0)],
+    );
+    exposition.gauge(
+        "scrybe_input_rms",
+        "RMS level of the most recent input audio.",
+        None,
+        &[(&[], audio.input_rms)],
+    );
+    exposition.gauge(
+        "scrybe_segment_samples",
+        "Samples in the current caption segment.",
+        None,
+        &[(&[], audio.segment_sample_len as f64)],
+    );
+
+    let system = snapshot.system;
+    exposition.gauge(
+        "scrybe_process_cpu_percent",
+        "CPU usage of the scrybe process, as a share of one core.",
+        None,
+        &[(&[], system.process_cpu_percent)],
+    );
+    exposition.gauge(
+        "scrybe_process_resident_memory_bytes",
+        "Resident memory of the scrybe process.",
+        Some("bytes"),
+        &[(&[], system.process_rss_bytes as f64)],
+    );
+    if let Some(threads) = system.process_threads {
+        exposition.gauge(
+            "scrybe_process_threads",
+            "Threads in the scrybe process.",
+            None,
+            &[(&[], threads as f64)],
+        );
+    }
+    exposition.gauge(
+        "scrybe_system_cpu_percent",
+        "CPU usage of the whole system, averaged across cores.",
+        None,
+        &[(&[], system.system_cpu_percent)],
+    );
+    exposition.gauge(
+        "scrybe_system_memory_available_bytes",
+        "Memory available to new allocations.",
+        Some("bytes"),
+        &[(&[], system.available_memory_bytes as f64)],
+    );
+    exposition.gauge(
+        "scrybe_system_memory_pressure_ratio",
+        "Fraction of system memory in use.",
+        Some("ratio"),
+        &[(&[], system.memory_pressure)],
+    );
+
+    exposition.finish()
+}
+
+#[derive(Default)]
+struct Exposition {
+    out: String,
+}
+
+type Labels<'a> = &'a [(&'a str, &'a str)];
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str, unit: Option<&str>) {
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        if let Some(unit) = unit {
+            let _ = writeln!(self.out, "# UNIT {} {}", name, unit);
+        }
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+    }
+
+    fn sample(&mut self, name: &str, labels: Labels, value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, value))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", format_value(value));
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, unit: Option<&str>, samples: &[(Labels, f64)]) {
+        self.family(name, "gauge", help, unit);
+        for (labels, value) in samples {
+            self.sample(name, labels, *value);
+        }
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.family(name, "counter", help, None);
+        self.sample(&format!("{}_total", name), &[], value as f64);
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("# EOF\n");
+        self.out
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_owned()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_with(audio: &AudioMetricsState, system: &SystemMetricsState) -> String {
+        render(&MetricsSnapshot {
+            audio,
+            transcribe_running: true,
+            model_load_state: "ready",
+            ws_clients: 2,
+            ws_dropped_messages: 5,
+            ws_lagging_disconnects: 1,
+            system,
+        })
+    }
+
+    #[test]
+    fn renders_openmetrics_families() {
+        let audio = AudioMetricsState {
+            inference_sample_count: 3,
+            inference_sum_ms: 1500.0,
+            inference_histogram: vec![0, 1, 1, 1, 2, 2, 3, 3, 3, 3],
+            gate_total_emits: 7,
+            ..Default::default()
+        };
+
+        let text = render_with(
+            &audio,
+            &SystemMetricsState {
+                process_cpu_percent: 12.5,
+                process_rss_bytes: 1024,
+                process_threads: Some(12),
+                ..Default::default()
+            },
+        );
+
+        assert!(text.ends_with("# EOF\n"));
+        assert!(text.contains("scrybe_transcription_running 1\n"));
+        assert!(text.contains("scrybe_model_load_state{scrybe_model_load_state=\"ready\"} 1\n"));
+        assert!(text.contains("scrybe_model_load_state{scrybe_model_load_state=\"idle\"} 0\n"));
+        assert!(text.contains("scrybe_websocket_clients 2\n"));
+        assert!(text.contains("scrybe_websocket_dropped_messages_total 5\n"));
+        assert!(text.contains("scrybe_inference_duration_seconds_bucket{le=\"0.1\"} 1\n"));
+        assert!(text.contains("scrybe_inference_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("scrybe_inference_duration_seconds_sum 1.5\n"));
+        assert!(text.contains("# TYPE scrybe_gate_emits counter\n"));
+        assert!(text.contains("scrybe_gate_emits_total 7\n"));
+        assert!(text.contains("scrybe_process_resident_memory_bytes 1024\n"));
+        assert!(text.contains("scrybe_process_threads 12\n"));
+    }
+
+    #[test]
+    fn every_sample_belongs_to_a_declared_family() {
+        let text = render_with(
+            &AudioMetricsState::default(),
+            &SystemMetricsState::default(),
+        );
+        let families: Vec<&str> = text
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+            .filter_map(|line| line.split(' ').next())
+            .collect();
+
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            assert!(
+                families.iter().any(|family| name.starts_with(family)),
+                "{}",
+                line
+            );
+        }
+        assert!(!text.contains("scrybe_process_threads"));
+    }
+}
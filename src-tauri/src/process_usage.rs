@@ -0,0 +1,156 @@
+use scrybe_core::metrics::SystemMetricsState;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use sysinfo::{
+    CpuRefreshKind, MemoryRefreshKind, Pid, ProcessRefreshKind, ProcessesToUpdate, System,
+};
+
+/// Samples taken closer together than this return the previous sample.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub type SharedProcessSampler = Arc<Mutex<ProcessSampler>>;
+
+/// Samples this process's and the system's resource usage. CPU usage is
+/// measured between two refreshes, so the first sample reports 0%.
+pub struct ProcessSampler {
+    system: System,
+    pid: Option<Pid>,
+    last: Option<(Instant, SystemMetricsState)>,
+}
+
+impl Default for ProcessSampler {
+    fn default() -> Self {
+        Self {
+            system: System::new(),
+            pid: sysinfo::get_current_pid().ok(),
+            last: None,
+        }
+    }
+}
+
+impl ProcessSampler {
+    pub fn sample(&mut self) -> SystemMetricsState {
+        if let Some((sampled_at, metrics)) = &self.last {
+            if sampled_at.elapsed() < SAMPLE_INTERVAL {
+                return metrics.clone();
+            }
+        }
+
+        self.system
+            .refresh_cpu_specifics(CpuRefreshKind::nothing().with_cpu_usage());
+        self.system
+            .refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+
+        let total_memory_bytes = self.system.total_memory();
+        let available_memory_bytes = self.system.available_memory();
+        let mut metrics = SystemMetricsState {
+            system_cpu_percent: self.system.global_cpu_usage() as f64,
+            logical_cpus: self.system.cpus().len() as u64,
+            total_memory_bytes,
+            available_memory_bytes,
+            memory_pressure: memory_pressure(total_memory_bytes, available_memory_bytes),
+            ..Default::default()
+        };
+
+        if let Some(pid) = self.pid {
+            self.system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                true,
+                ProcessRefreshKind::nothing()
+                    .with_cpu()
+                    .with_memory()
+                    .with_tasks(),
+            );
+            if let Some(process) = self.system.process(pid) {
+                metrics.process_cpu_percent = process.cpu_usage() as f64;
+                metrics.process_rss_bytes = process.memory();
+                metrics.process_threads = process
+                    .tasks()
+                    .map(|tasks| tasks.len() as u64)
+                    .or_else(|| thread_count(pid.as_u32()));
+            }
+        }
+
+        self.last = Some((Instant::now(), metrics.clone()));
+        metrics
+    }
+}
+
+fn memory_pressure(total_bytes: u64, available_bytes: u64) -> f64 {
+    if total_bytes == 0 {
+        return 0.0;
+    }
+
+    1.0 - available_bytes.min(total_bytes) as f64 / total_bytes as f64
+}
+
+/// `sysinfo` only lists a process's threads on Linux.
+#[cfg(target_os = "windows")]
+fn thread_count(pid: u32) -> Option<u64> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) }.ok()?;
+    let mut entry = THREADENTRY32 {
+        dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+        ..Default::default()
+    };
+
+    let mut count = 0;
+    let mut found = unsafe { Thread32First(snapshot, &mut entry) }.is_ok();
+    while found {
+        if entry.th32OwnerProcessID == pid {
+            count += 1;
+        }
+        found = unsafe { Thread32Next(snapshot, &mut entry) }.is_ok();
+    }
+
+    let _ = unsafe { CloseHandle(snapshot) };
+    Some(count)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn thread_count(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_current_process_and_system() {
+        let mut sampler = ProcessSampler::default();
+
+        let metrics = sampler.sample();
+
+        assert!(metrics.process_rss_bytes > 0);
+        assert!(metrics.logical_cpus > 0);
+        assert!(metrics.total_memory_bytes >= metrics.available_memory_bytes);
+        assert!((0.0..=1.0).contains(&metrics.memory_pressure));
+        if cfg!(target_os = "linux") {
+            assert!(metrics.process_threads.is_some_and(|threads| threads > 0));
+        }
+    }
+
+    #[test]
+    fn repeated_samples_within_interval_are_cached() {
+        let mut sampler = ProcessSampler::default();
+
+        let first = sampler.sample();
+        let second = sampler.sample();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn memory_pressure_is_used_fraction() {
+        assert_eq!(memory_pressure(0, 0), 0.0);
+        assert_eq!(memory_pressure(8, 2), 0.75);
+        assert_eq!(memory_pressure(8, 16), 0.0);
+    }
+}
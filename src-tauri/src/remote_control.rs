@@ -0,0 +1,95 @@
+use scrybe_core::{audio, devices::AudioDevice, whisper::WhisperParams};
+use tauri::{AppHandle, Manager};
+use tauri_svelte_synced_store::StateSyncer;
+
+use crate::{
+    calibration,
+    types::{AppState, OverlayConfig},
+    InternalState, OverlayTestState,
+};
+
+/// Matches the test button on the overlay page.
+const DEFAULT_TEST_TEXT_DURATION_MS: u64 = 60_000;
+
+// Commands from remote clients edit a snapshot of the state and hand it to
+// the same functions `update_state` uses, so they get the same checks and
+// side effects as edits made in the app.
+
+pub fn start_transcription(app: &AppHandle) -> Result<(), String> {
+    crate::begin_transcription(app)
+}
+
+pub fn stop_transcription(app: &AppHandle) {
+    crate::end_transcription(app);
+}
+
+/// Selects a capture device by id, or the system default for an empty id.
+/// A running transcription keeps its device until it is restarted.
+pub fn set_device(app: &AppHandle, device_id: &str) -> Result<(), String> {
+    let device = if device_id.is_empty() {
+        AudioDevice::default()
+    } else {
+        audio::get_devices()
+            .map_err(|err| format!("unable to list audio devices: {}", err))?
+            .into_iter()
+            .find(|device| device.id == device_id)
+            .ok_or_else(|| format!("no audio device with id {}", device_id))?
+    };
+    edit_app_state(app, |state| state.current_device = device);
+    Ok(())
+}
+
+/// Switches to a model preset whose file is already on disk. Presets that
+/// still need downloading are refused rather than fetched in the background.
+pub fn set_model_preset(app: &AppHandle, preset_id: &str) -> Result<(), String> {
+    let presets = crate::model_catalog_presets(app);
+    let preset = presets
+        .iter()
+        .find(|preset| preset.id == preset_id)
+        .ok_or_else(|| format!("unknown model preset {}", preset_id))?;
+    let model_path = crate::local_preset_file(app, preset)
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("{} needs to be downloaded in the app first", preset.label))?;
+    edit_app_state(app, |state| state.model_path = model_path);
+    Ok(())
+}
+
+pub fn set_whisper_params(app: &AppHandle, params: WhisperParams) {
+    edit_app_state(app, |state| state.whisper_params = params);
+}
+
+pub fn set_overlay_config(app: &AppHandle, config: OverlayConfig) {
+    edit_app_state(app, |state| state.overlay_config = config);
+}
+
+/// Shows `text` on the overlay in place of captions until `duration_ms` has
+/// passed, defaulting to a minute.
+pub fn show_test_text(app: &AppHandle, text: String, duration_ms: Option<u64>) {
+    let expires_at_ms =
+        calibration::now_ms() + duration_ms.unwrap_or(DEFAULT_TEST_TEXT_DURATION_MS);
+    edit_internal_state(app, |state| {
+        state.overlay_test = OverlayTestState {
+            visible: true,
+            text,
+            expires_at_ms: Some(expires_at_ms),
+        }
+    });
+}
+
+pub fn clear_test_text(app: &AppHandle) {
+    edit_internal_state(app, |state| state.overlay_test.clear());
+}
+
+fn edit_app_state(app: &AppHandle, edit: impl FnOnce(&mut AppState)) {
+    let mut app_state = app.state::<StateSyncer>().snapshot::<AppState>("app_state");
+    edit(&mut app_state);
+    crate::apply_app_state(app, app_state);
+}
+
+fn edit_internal_state(app: &AppHandle, edit: impl FnOnce(&mut InternalState)) {
+    let mut internal_state = app
+        .state::<StateSyncer>()
+        .snapshot::<InternalState>("internal_state");
+    edit(&mut internal_state);
+    crate::apply_internal_state(app, internal_state);
+}
@@ -0,0 +1,341 @@
+use futures::{stream::BoxStream, StreamExt};
+use scrybe_core::whisper::WhisperSegment;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, convert::Infallible};
+use warp::{http::StatusCode, reject::Rejection, reply::Reply, Filter};
+
+use crate::{
+    sessions::spoken_text,
+    types::AppState,
+    ws_protocol::{ErrorCode, ServerMessage, Topic},
+    InternalState,
+};
+
+/// What the REST routes read and control. The server implements it over the
+/// running app; tests implement it over fixed state.
+pub trait RestBackend: Clone + Send + Sync + 'static {
+    fn app_state(&self) -> AppState;
+    fn internal_state(&self) -> InternalState;
+    /// The segment on the overlay right now.
+    fn current_segment(&self) -> Option<WhisperSegment>;
+    fn start_transcription(&self) -> Result<(), String>;
+    fn stop_transcription(&self);
+    /// Serialized envelopes for the given topics, as the WebSocket sends them.
+    fn event_stream(&self, topics: HashSet<Topic>) -> BoxStream<'static, String>;
+}
+
+/// What `GET /api/transcript/current` returns: the caption on the overlay
+/// right now.
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrentTranscript {
+    pub text: String,
+    pub segment: Option<WhisperSegment>,
+    pub transcribe_running: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptQuery {
+    /// `text` returns the bare caption for scripts.
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    /// Comma-separated topics, defaulting to the ones a new WebSocket client
+    /// gets.
+    topics: Option<String>,
+}
+
+/// Plain HTTP routes for tools that can't speak WebSocket, mounted under
+/// `/api`. Replies and events are the same JSON messages the WebSocket
+/// sends.
+pub fn routes<B: RestBackend>(
+    backend: B,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let state_backend = backend.clone();
+    let state = warp::path!("state")
+        .and(warp::get())
+        .map(move || warp::reply::json(&state_backend.app_state()));
+
+    let internal_backend = backend.clone();
+    let internal = warp::path!("internal")
+        .and(warp::get())
+        .map(move || warp::reply::json(&internal_backend.internal_state()));
+
+    let start_backend = backend.clone();
+    let start = warp::path!("transcribe" / "start")
+        .and(warp::post())
+        .map(move || command_reply(start_backend.start_transcription()));
+
+    let stop_backend = backend.clone();
+    let stop = warp::path!("transcribe" / "stop")
+        .and(warp::post())
+        .map(move || {
+            stop_backend.stop_transcription();
+            command_reply(Ok(()))
+        });
+
+    let transcript_backend = backend.clone();
+    let transcript = warp::path!("transcript" / "current")
+        .and(warp::get())
+        .and(warp::query::<TranscriptQuery>())
+        .map(move |query: TranscriptQuery| {
+            let transcript = current_transcript(&transcript_backend);
+            if query.format.as_deref() == Some("text") {
+                warp::reply::with_header(
+                    transcript.text,
+                    "content-type",
+                    "text/plain; charset=utf-8",
+                )
+                .into_response()
+            } else {
+                warp::reply::json(&transcript).into_response()
+            }
+        });
+
+    let events = warp::path!("events")
+        .and(warp::get())
+        .and(warp::query::<EventsQuery>())
+        .map(move |query: EventsQuery| {
+            let topics = match query.topics.as_deref().map(Topic::parse_list) {
+                None => HashSet::from(Topic::DEFAULT),
+                Some(Ok(topics)) => topics.into_iter().collect(),
+                Some(Err(reason)) => {
+                    return error_reply(
+                        StatusCode::BAD_REQUEST,
+                        ServerMessage::error(ErrorCode::InvalidMessage, reason),
+                    )
+                }
+            };
+            let events = backend
+                .event_stream(topics)
+                .map(|data| Ok::<_, Infallible>(warp::sse::Event::default().data(data)));
+            warp::sse::reply(warp::sse::keep_alive().stream(events)).into_response()
+        });
+
+    state
+        .or(internal)
+        .or(start)
+        .or(stop)
+        .or(transcript)
+        .or(events)
+}
+
+fn current_transcript(backend: &impl RestBackend) -> CurrentTranscript {
+    let segment = backend.current_segment();
+    CurrentTranscript {
+        text: segment
+            .as_ref()
+            .map(|segment| spoken_text(&segment.items))
+            .unwrap_or_default(),
+        segment,
+        transcribe_running: backend.internal_state().transcribe_running,
+    }
+}
+
+fn command_reply(result: Result<(), String>) -> warp::reply::Response {
+    match result {
+        Ok(()) => warp::reply::json(&ServerMessage::Ack).into_response(),
+        Err(reason) => error_reply(
+            StatusCode::CONFLICT,
+            ServerMessage::error(ErrorCode::CommandFailed, reason),
+        ),
+    }
+}
+
+fn error_reply(status: StatusCode, message: ServerMessage) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&message), status).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use scrybe_core::whisper::{WhisperText, SPEAKER_CHANGE_MARKER};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct FakeBackend {
+        running: Arc<Mutex<bool>>,
+        start_error: Option<String>,
+        segment: Option<WhisperSegment>,
+        /// The topics each event stream was opened with.
+        streams: Arc<Mutex<Vec<HashSet<Topic>>>>,
+    }
+
+    impl RestBackend for FakeBackend {
+        fn app_state(&self) -> AppState {
+            AppState {
+                model_path: "ggml-base.bin".to_owned(),
+                ..Default::default()
+            }
+        }
+
+        fn internal_state(&self) -> InternalState {
+            InternalState {
+                transcribe_running: *self.running.lock().unwrap(),
+                ..Default::default()
+            }
+        }
+
+        fn current_segment(&self) -> Option<WhisperSegment> {
+            self.segment.clone()
+        }
+
+        fn start_transcription(&self) -> Result<(), String> {
+            if let Some(reason) = &self.start_error {
+                return Err(reason.clone());
+            }
+            *self.running.lock().unwrap() = true;
+            Ok(())
+        }
+
+        fn stop_transcription(&self) {
+            *self.running.lock().unwrap() = false;
+        }
+
+        fn event_stream(&self, topics: HashSet<Topic>) -> BoxStream<'static, String> {
+            self.streams.lock().unwrap().push(topics);
+            stream::iter(["{\"type\":\"ack\"}".to_owned()]).boxed()
+        }
+    }
+
+    fn json(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_state_snapshots() {
+        let api = routes(FakeBackend::default());
+
+        let state = warp::test::request().path("/state").reply(&api).await;
+        assert_eq!(state.status(), StatusCode::OK);
+        assert_eq!(json(state.body())["model_path"], "ggml-base.bin");
+
+        let internal = warp::test::request().path("/internal").reply(&api).await;
+        assert_eq!(internal.status(), StatusCode::OK);
+        assert_eq!(json(internal.body())["transcribe_running"], false);
+
+        let wrong_method = warp::test::request()
+            .method("POST")
+            .path("/state")
+            .reply(&api)
+            .await;
+        assert_eq!(wrong_method.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn starts_and_stops_transcription() {
+        let backend = FakeBackend::default();
+        let api = routes(backend.clone());
+
+        let start = warp::test::request()
+            .method("POST")
+            .path("/transcribe/start")
+            .reply(&api)
+            .await;
+        assert_eq!(start.status(), StatusCode::OK);
+        assert_eq!(json(start.body())["type"], "ack");
+        assert!(backend.internal_state().transcribe_running);
+
+        let stop = warp::test::request()
+            .method("POST")
+            .path("/transcribe/stop")
+            .reply(&api)
+            .await;
+        assert_eq!(stop.status(), StatusCode::OK);
+        assert!(!backend.internal_state().transcribe_running);
+    }
+
+    #[tokio::test]
+    async fn reports_a_start_that_failed_as_a_conflict() {
+        let api = routes(FakeBackend {
+            start_error: Some("no model loaded".to_owned()),
+            ..Default::default()
+        });
+
+        let start = warp::test::request()
+            .method("POST")
+            .path("/transcribe/start")
+            .reply(&api)
+            .await;
+
+        assert_eq!(start.status(), StatusCode::CONFLICT);
+        assert_eq!(json(start.body())["code"], "command_failed");
+    }
+
+    #[tokio::test]
+    async fn returns_the_current_caption_as_json_or_text() {
+        let items = ["hello", SPEAKER_CHANGE_MARKER, " there"].map(|text| WhisperText {
+            text: text.to_owned(),
+            ..Default::default()
+        });
+        let api = routes(FakeBackend {
+            segment: Some(WhisperSegment {
+                id: "a".to_owned(),
+                index: 0,
+                items: items.to_vec(),
+            }),
+            ..Default::default()
+        });
+
+        let as_json = warp::test::request()
+            .path("/transcript/current")
+            .reply(&api)
+            .await;
+        assert_eq!(json(as_json.body())["text"], "hello there");
+        assert_eq!(json(as_json.body())["segment"]["id"], "a");
+
+        let as_text = warp::test::request()
+            .path("/transcript/current?format=text")
+            .reply(&api)
+            .await;
+        assert_eq!(as_text.status(), StatusCode::OK);
+        assert_eq!(
+            as_text.headers()["content-type"],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(as_text.body().as_ref(), b"hello there");
+    }
+
+    #[tokio::test]
+    async fn streams_events_for_the_requested_topics() {
+        let backend = FakeBackend::default();
+        let api = routes(backend.clone());
+
+        let events = warp::test::request()
+            .path("/events?topics=segments")
+            .reply(&api)
+            .await;
+        assert_eq!(events.status(), StatusCode::OK);
+        assert_eq!(events.headers()["content-type"], "text/event-stream");
+        assert!(String::from_utf8_lossy(events.body()).contains("data:{\"type\":\"ack\"}"));
+
+        let defaults = warp::test::request().path("/events").reply(&api).await;
+        assert_eq!(defaults.status(), StatusCode::OK);
+
+        let streams = backend.streams.lock().unwrap();
+        assert_eq!(
+            *streams,
+            [
+                HashSet::from([Topic::Segments]),
+                HashSet::from(Topic::DEFAULT)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_event_topics() {
+        let backend = FakeBackend::default();
+        let api = routes(backend.clone());
+
+        let events = warp::test::request()
+            .path("/events?topics=segments,nope")
+            .reply(&api)
+            .await;
+
+        assert_eq!(events.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(events.body())["code"], "invalid_message");
+        assert!(backend.streams.lock().unwrap().is_empty());
+    }
+}
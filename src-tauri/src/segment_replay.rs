@@ -0,0 +1,104 @@
+use scrybe_core::whisper::WhisperSegment;
+use std::collections::VecDeque;
+
+/// Finished segments kept for clients that connect late.
+pub const RECENT_SEGMENT_COUNT: usize = 5;
+
+/// The segment being transcribed and the last few finished ones, so an
+/// overlay that reconnects can show the caption it would have shown had it
+/// stayed connected.
+#[derive(Debug, Default)]
+pub struct SegmentReplay {
+    current: Option<WhisperSegment>,
+    finished: VecDeque<WhisperSegment>,
+}
+
+impl SegmentReplay {
+    /// Records a segment update. An update for a new segment finishes the
+    /// current one; finished segments with no text aren't kept.
+    pub fn record(&mut self, segment: WhisperSegment) {
+        if let Some(current) = self.current.take() {
+            if current.id != segment.id && !current.items.is_empty() {
+                self.finished.push_back(current);
+                if self.finished.len() > RECENT_SEGMENT_COUNT {
+                    self.finished.pop_front();
+                }
+            }
+        }
+        self.current = Some(segment);
+    }
+
+    /// Oldest first, ending with the current segment.
+    pub fn recent(&self) -> Vec<WhisperSegment> {
+        self.finished
+            .iter()
+            .chain(self.current.as_ref())
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none()
+    }
+
+    /// The segment being transcribed, which is empty just after a rollover.
+    pub fn current(&self) -> Option<&WhisperSegment> {
+        self.current.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrybe_core::whisper::WhisperText;
+
+    fn segment(id: &str, text: &str) -> WhisperSegment {
+        WhisperSegment {
+            id: id.to_owned(),
+            index: 0,
+            items: if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![WhisperText {
+                    text: text.to_owned(),
+                    ..Default::default()
+                }]
+            },
+        }
+    }
+
+    fn ids(replay: &SegmentReplay) -> Vec<String> {
+        replay
+            .recent()
+            .into_iter()
+            .map(|segment| segment.id)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_current_segment_and_the_last_finished_ones() {
+        let mut replay = SegmentReplay::default();
+        assert!(replay.is_empty());
+
+        replay.record(segment("a", "hello"));
+        replay.record(segment("a", "hello there"));
+        assert_eq!(replay.recent()[0].items[0].text, "hello there");
+
+        for index in 0..RECENT_SEGMENT_COUNT + 1 {
+            replay.record(segment(&index.to_string(), "text"));
+        }
+        replay.record(segment("rollover", ""));
+
+        assert_eq!(ids(&replay), ["1", "2", "3", "4", "5", "rollover"]);
+    }
+
+    #[test]
+    fn skips_finished_segments_without_text() {
+        let mut replay = SegmentReplay::default();
+
+        replay.record(segment("a", ""));
+        replay.record(segment("b", "hello"));
+
+        assert_eq!(ids(&replay), ["b"]);
+    }
+}
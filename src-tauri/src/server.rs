@@ -0,0 +1,374 @@
+use anyhow::anyhow;
+use futures::{stream::BoxStream, StreamExt};
+use rust_embed::RustEmbed;
+use scrybe_core::{metrics::AudioMetricsState, whisper::WhisperSegment};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    sync::Arc,
+};
+use tauri::{async_runtime::JoinHandle, AppHandle, Manager};
+use tracing::{debug, error, info, warn};
+use warp::{
+    http::{header, HeaderValue, StatusCode},
+    reject::Rejection,
+    reply::Reply,
+    Filter,
+};
+
+use crate::{
+    openmetrics,
+    process_usage::SharedProcessSampler,
+    remote_control, rest_api,
+    server_config::{self, ServerSettings, TOKEN_COOKIE, TOKEN_QUERY_PARAM},
+    transcription_api,
+    types::AppState,
+    ws::WebsocketManager,
+    ws_protocol::{self, Topic},
+    InternalState,
+};
+
+#[derive(RustEmbed)]
+#[folder = "../build"]
+struct Static;
+
+struct EmbedFile {
+    data: std::borrow::Cow<'static, [u8]>,
+}
+
+impl warp::reply::Reply for EmbedFile {
+    fn into_response(self) -> warp::reply::Response {
+        warp::reply::Response::new(self.data.into())
+    }
+}
+
+/// The running HTTP and WebSocket server. Connections it has accepted keep
+/// going when it is replaced; only the listener is swapped.
+#[derive(Default)]
+pub struct LocalServer {
+    task: tokio::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+enum Denied {
+    Token,
+    Origin,
+}
+
+impl warp::reject::Reject for Denied {}
+
+/// The REST API over the running app.
+#[derive(Clone)]
+struct AppRestBackend {
+    app: AppHandle,
+    ws_manager: WebsocketManager,
+}
+
+impl rest_api::RestBackend for AppRestBackend {
+    fn app_state(&self) -> AppState {
+        self.ws_manager.store.snapshot::<AppState>("app_state")
+    }
+
+    fn internal_state(&self) -> InternalState {
+        self.ws_manager
+            .store
+            .snapshot::<InternalState>("internal_state")
+    }
+
+    fn current_segment(&self) -> Option<WhisperSegment> {
+        self.ws_manager.current_segment()
+    }
+
+    fn start_transcription(&self) -> Result<(), String> {
+        remote_control::start_transcription(&self.app)
+    }
+
+    fn stop_transcription(&self) {
+        remote_control::stop_transcription(&self.app);
+    }
+
+    fn event_stream(&self, topics: HashSet<Topic>) -> BoxStream<'static, String> {
+        self.ws_manager.event_stream(topics).boxed()
+    }
+}
+
+/// Stops the running server, if any, and starts one with `settings` in the
+/// background, reporting where it listens in `InternalState::server_url`.
+/// Restarts run in the order they were asked for.
+pub fn restart(app: &AppHandle, settings: ServerSettings) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let local_server = app.state::<LocalServer>();
+        let mut task = local_server.task.lock().await;
+        if let Some(running) = task.take() {
+            running.abort();
+            // Wait for the listener to be dropped so its port is free again.
+            let _ = running.await;
+        }
+
+        let server_url = match bind(&app, &settings) {
+            Ok((addr, server)) => {
+                let host = addr.ip().is_unspecified().then(lan_ip).flatten();
+                let url = server_config::server_url(addr, host);
+                info!("local server listening on {} ({})", addr, url);
+                *task = Some(tauri::async_runtime::spawn(server));
+                Some(url)
+            }
+            Err(err) => {
+                error!("failed to start local server: {:#}", err);
+                None
+            }
+        };
+        crate::update_internal_state(&app, |state| state.server_url = server_url);
+    });
+}
+
+fn bind(
+    app: &AppHandle,
+    settings: &ServerSettings,
+) -> anyhow::Result<(SocketAddr, impl Future<Output = ()> + Send + 'static)> {
+    let ip = settings.bind_ip()?;
+    let routes = routes(app, settings);
+
+    let mut last_error = None;
+    for port in settings.candidate_ports() {
+        match warp::serve(routes.clone()).try_bind_ephemeral((ip, port)) {
+            Ok(bound) => {
+                if port != settings.port {
+                    warn!("port {} is taken, using {} instead", settings.port, port);
+                }
+                return Ok(bound);
+            }
+            Err(err) => {
+                debug!("unable to bind {}:{}: {}", ip, port, err);
+                last_error = Some(err);
+            }
+        }
+    }
+    Err(match last_error {
+        Some(err) => anyhow!("unable to bind {}:{}: {}", ip, settings.port, err),
+        None => anyhow!("no ports to try"),
+    })
+}
+
+/// The address other machines on the network reach this one at. Connecting
+/// a UDP socket picks the outgoing interface without sending anything.
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("192.0.2.1", 9)).ok()?;
+    socket
+        .local_addr()
+        .ok()
+        .map(|addr| addr.ip())
+        .filter(|ip| !ip.is_unspecified())
+}
+
+fn routes(
+    app: &AppHandle,
+    settings: &ServerSettings,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let settings = Arc::new(settings.clone());
+    let ws_manager = app.state::<WebsocketManager>().inner().clone();
+    let process_sampler = app.state::<SharedProcessSampler>().inner().clone();
+
+    let static_files = warp::path("app")
+        .and(warp::get())
+        .and(token_guard(settings.clone()))
+        .and(warp::path::tail())
+        .and_then(move |query_token: Option<String>, tail: warp::path::Tail| {
+            let tail_str = tail.as_str();
+            debug!("GET {}", tail_str);
+
+            // Try paths in order: exact path, path with .html, path/index.html
+            let paths_to_try = if tail_str.is_empty() {
+                vec!["index.html".to_string()]
+            } else {
+                vec![
+                    tail_str.to_string(),
+                    format!("{}.html", tail_str),
+                    format!("{}/index.html", tail_str),
+                ]
+            };
+
+            async move {
+                for path in paths_to_try {
+                    if let Some(file) = Static::get(&path) {
+                        let mime = mime_guess::from_path(&path).first_or_octet_stream();
+                        let mut response = warp::reply::with_header(
+                            EmbedFile { data: file.data },
+                            "content-type",
+                            mime.as_ref(),
+                        )
+                        .into_response();
+                        if let Some(cookie) = query_token.as_deref().and_then(token_cookie) {
+                            response.headers_mut().insert(header::SET_COOKIE, cookie);
+                        }
+                        return Ok(response);
+                    }
+                }
+                Err(warp::reject::not_found())
+            }
+        })
+        .boxed();
+
+    let ws_schema_route = warp::path!("ws" / "schema.json")
+        .and(warp::get())
+        .map(|| warp::reply::json(&ws_protocol::json_schema()));
+
+    let ws_route_manager = ws_manager.clone();
+    let ws_route = warp::path("ws")
+        .and(require_token(settings.clone()))
+        .and(warp::ws())
+        .map(move |ws: warp::ws::Ws| {
+            debug!("ws_handler");
+            let ws_manager = ws_route_manager.clone();
+            ws.on_upgrade(move |socket| ws_manager.client_connection(socket))
+        });
+
+    let api_route = warp::path("api")
+        .and(require_token(settings.clone()))
+        .and(rest_api::routes(AppRestBackend {
+            app: app.clone(),
+            ws_manager: ws_manager.clone(),
+        }));
+
+    // OpenAI clients send their API key as a bearer token, so the server
+    // token doubles as the key.
+    let transcriptions_route = warp::path!("v1" / "audio" / "transcriptions")
+        .and(require_token(settings.clone()))
+        .and(transcription_api::route(app));
+
+    let metrics_route = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .then(move || {
+            let ws_manager = ws_manager.clone();
+            let process_sampler = process_sampler.clone();
+            async move {
+                let ws_clients = ws_manager.clients.lock().unwrap().len();
+                let audio = ws_manager
+                    .store
+                    .snapshot::<AudioMetricsState>("audio_metrics");
+                let internal_state = ws_manager.store.snapshot::<InternalState>("internal_state");
+                let body = openmetrics::render(&openmetrics::MetricsSnapshot {
+                    audio: &audio,
+                    transcribe_running: internal_state.transcribe_running,
+                    model_load_state: internal_state.model_load.name(),
+                    ws_clients,
+                    ws_dropped_messages: ws_manager.fanout_counters.dropped_messages(),
+                    ws_lagging_disconnects: ws_manager.fanout_counters.lagging_disconnects(),
+                    system: &process_sampler.lock().unwrap().sample(),
+                });
+                warp::reply::with_header(body, "content-type", openmetrics::CONTENT_TYPE)
+            }
+        });
+
+    // Origins are checked by `origin_guard` rather than here, so a bad entry
+    // in the allow-list is reported as a refused request instead of a panic.
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_methods(vec!["GET", "POST"])
+        .allow_headers(vec!["Content-Type", "Authorization"]);
+
+    origin_guard(settings)
+        .and(
+            static_files
+                .or(ws_schema_route)
+                .or(ws_route)
+                .or(api_route)
+                .or(transcriptions_route)
+                .or(metrics_route),
+        )
+        .recover(handle_rejection)
+        .with(cors)
+}
+
+/// Refuses requests made by pages on origins that aren't allowed.
+fn origin_guard(
+    settings: Arc<ServerSettings>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::header::optional::<String>("host"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(
+            move |origin: Option<String>,
+                  host: Option<String>,
+                  query: HashMap<String, String>,
+                  authorization: Option<String>| {
+                let presented = server_config::presented_token(
+                    query.get(TOKEN_QUERY_PARAM).map(String::as_str),
+                    authorization.as_deref(),
+                    None,
+                );
+                let allowed =
+                    settings.origin_allowed(origin.as_deref(), host.as_deref(), presented);
+                async move {
+                    if allowed {
+                        Ok(())
+                    } else {
+                        debug!("refused request from origin {:?}", origin);
+                        Err(warp::reject::custom(Denied::Origin))
+                    }
+                }
+            },
+        )
+        .untuple_one()
+}
+
+/// Refuses requests that don't present the token, when one is set. Extracts
+/// the token when it came in the query, so a page opened with it can be
+/// handed the cookie.
+fn token_guard(
+    settings: Arc<ServerSettings>,
+) -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::query::<HashMap<String, String>>()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("cookie"))
+        .and_then(
+            move |mut query: HashMap<String, String>,
+                  authorization: Option<String>,
+                  cookie: Option<String>| {
+                let query_token = query.remove(TOKEN_QUERY_PARAM);
+                let accepted = settings.token_accepted(server_config::presented_token(
+                    query_token.as_deref(),
+                    authorization.as_deref(),
+                    cookie.as_deref(),
+                ));
+                let query_token = query_token.filter(|_| settings.requires_token());
+                async move {
+                    if accepted {
+                        Ok(query_token)
+                    } else {
+                        Err(warp::reject::custom(Denied::Token))
+                    }
+                }
+            },
+        )
+}
+
+fn require_token(
+    settings: Arc<ServerSettings>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    token_guard(settings)
+        .map(|_: Option<String>| ())
+        .untuple_one()
+}
+
+fn token_cookie(token: &str) -> Option<HeaderValue> {
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict",
+        TOKEN_COOKIE, token
+    ))
+    .ok()
+}
+
+async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    let (status, message) = match rejection.find::<Denied>() {
+        Some(Denied::Token) => (StatusCode::UNAUTHORIZED, "missing or invalid token"),
+        Some(Denied::Origin) => (StatusCode::FORBIDDEN, "origin not allowed"),
+        None => return Err(rejection),
+    };
+    Ok(warp::reply::with_status(message, status))
+}
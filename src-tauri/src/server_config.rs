@@ -0,0 +1,242 @@
+use anyhow::Context;
+use std::net::{IpAddr, SocketAddr};
+
+/// Ports tried after the configured one when it's taken.
+pub const PORT_FALLBACK_ATTEMPTS: u16 = 10;
+/// Query parameter that carries the token, e.g. `/app/v1/overlay?token=...`.
+pub const TOKEN_QUERY_PARAM: &str = "token";
+/// Set on pages opened with the token in the query, so the page's assets and
+/// WebSocket are let in without repeating it.
+pub const TOKEN_COOKIE: &str = "scrybe_token";
+
+/// Where the local server listens and who it lets in.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// `127.0.0.1` keeps the server to this machine; `0.0.0.0` lets another
+    /// PC, such as a streaming PC, reach it.
+    pub bind_address: String,
+    pub port: u16,
+    /// Tries the next few ports when `port` is taken.
+    pub port_fallback: bool,
+    /// Shared secret required on `/ws`, `/app`, `/api` and `/v1`; empty lets
+    /// anyone in.
+    pub token: String,
+    /// Origins, such as `http://192.168.1.20:8080`, that pages on other
+    /// hosts may call the server from. Empty allows only the pages it serves
+    /// itself.
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1".to_owned(),
+            port: 3030,
+            port_fallback: true,
+            token: String::new(),
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn bind_ip(&self) -> anyhow::Result<IpAddr> {
+        self.bind_address
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid bind address {:?}", self.bind_address))
+    }
+
+    /// The configured port, then the ones after it when fallback is on.
+    pub fn candidate_ports(&self) -> Vec<u16> {
+        let attempts = if self.port_fallback && self.port != 0 {
+            PORT_FALLBACK_ATTEMPTS
+        } else {
+            0
+        };
+        (0..=attempts)
+            .map_while(|offset| self.port.checked_add(offset))
+            .collect()
+    }
+
+    pub fn requires_token(&self) -> bool {
+        !self.token.is_empty()
+    }
+
+    /// Whether the token presented with a request, if any, lets it in.
+    pub fn token_accepted(&self, presented: Option<&str>) -> bool {
+        if !self.requires_token() {
+            return true;
+        }
+        presented.is_some_and(|presented| constant_time_eq(presented, &self.token))
+    }
+
+    /// Requests without an `Origin` header don't come from a browser page,
+    /// and pages served by this server are always allowed to call it back.
+    /// Pages elsewhere must be listed, or send the token themselves in
+    /// `presented`; the cookie doesn't count, since a browser attaches it to
+    /// requests the page didn't mean to authorize.
+    pub fn origin_allowed(
+        &self,
+        origin: Option<&str>,
+        host: Option<&str>,
+        presented: Option<&str>,
+    ) -> bool {
+        let Some(origin) = origin else {
+            return true;
+        };
+        let origin = normalize_origin(origin);
+        if host.is_some_and(|host| origin_host(&origin) == Some(host.to_ascii_lowercase())) {
+            return true;
+        }
+        if self
+            .allowed_origins
+            .iter()
+            .any(|allowed| normalize_origin(allowed) == origin)
+        {
+            return true;
+        }
+        self.requires_token() && self.token_accepted(presented)
+    }
+}
+
+/// The token from the query parameter, an `Authorization: Bearer` header, or
+/// the cookie, in that order.
+pub fn presented_token<'a>(
+    query: Option<&'a str>,
+    authorization: Option<&'a str>,
+    cookie: Option<&'a str>,
+) -> Option<&'a str> {
+    query
+        .or_else(|| authorization.and_then(bearer_token))
+        .or_else(|| cookie.and_then(|cookie| cookie_value(cookie, TOKEN_COOKIE)))
+}
+
+/// The address to show users and hand to browser sources. An unspecified
+/// bind address is reported as `host`, the machine's address on the network.
+pub fn server_url(addr: SocketAddr, host: Option<IpAddr>) -> String {
+    let ip = if addr.ip().is_unspecified() {
+        host.unwrap_or(match addr {
+            SocketAddr::V4(_) => IpAddr::from([127, 0, 0, 1]),
+            SocketAddr::V6(_) => IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]),
+        })
+    } else {
+        addr.ip()
+    };
+    format!("http://{}", SocketAddr::new(ip, addr.port()))
+}
+
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+fn cookie_value<'a>(cookie: &'a str, name: &str) -> Option<&'a str> {
+    cookie
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+fn origin_host(origin: &str) -> Option<String> {
+    origin.split_once("://").map(|(_, host)| host.to_owned())
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't reveal how much of a guessed token was right.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> ServerSettings {
+        ServerSettings {
+            token: "s3cret".to_owned(),
+            allowed_origins: vec!["http://192.168.1.20:8080/".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_following_ports() {
+        let mut settings = ServerSettings::default();
+        assert_eq!(settings.candidate_ports().len(), 11);
+        assert_eq!(settings.candidate_ports()[1], 3031);
+
+        settings.port = u16::MAX - 1;
+        assert_eq!(settings.candidate_ports(), [u16::MAX - 1, u16::MAX]);
+
+        settings.port_fallback = false;
+        assert_eq!(settings.candidate_ports(), [u16::MAX - 1]);
+    }
+
+    #[test]
+    fn accepts_the_token_from_the_query_a_header_or_the_cookie() {
+        let settings = settings();
+
+        let from_header = presented_token(None, Some("Bearer s3cret"), None);
+        let from_cookie = presented_token(None, None, Some("theme=dark; scrybe_token=s3cret"));
+        assert!(settings.token_accepted(presented_token(Some("s3cret"), None, None)));
+        assert!(settings.token_accepted(from_header));
+        assert!(settings.token_accepted(from_cookie));
+
+        assert!(!settings.token_accepted(None));
+        assert!(!settings.token_accepted(Some("s3cre")));
+        assert!(!settings.token_accepted(presented_token(None, Some("Basic s3cret"), None)));
+        assert!(ServerSettings::default().token_accepted(None));
+    }
+
+    #[test]
+    fn allows_listed_and_same_host_origins() {
+        let settings = settings();
+        let host = Some("127.0.0.1:3030");
+
+        assert!(settings.origin_allowed(None, None, None));
+        assert!(settings.origin_allowed(Some("http://192.168.1.20:8080"), None, None));
+        assert!(settings.origin_allowed(Some("http://127.0.0.1:3030"), host, None));
+        assert!(!settings.origin_allowed(Some("http://evil.example"), host, None));
+        assert!(!settings.origin_allowed(Some("http://evil.example"), host, Some("guess")));
+        assert!(settings.origin_allowed(Some("http://evil.example"), host, Some("s3cret")));
+    }
+
+    #[test]
+    fn refuses_other_origins_by_default() {
+        let settings = ServerSettings::default();
+        let host = Some("127.0.0.1:3030");
+
+        assert!(settings.origin_allowed(None, host, None));
+        assert!(settings.origin_allowed(Some("http://127.0.0.1:3030"), host, None));
+        assert!(!settings.origin_allowed(Some("http://evil.example"), host, None));
+        // Without a token set, presenting one proves nothing.
+        assert!(!settings.origin_allowed(Some("http://evil.example"), host, Some("")));
+    }
+
+    #[test]
+    fn reports_a_reachable_url() {
+        let addr: SocketAddr = "0.0.0.0:3031".parse().unwrap();
+        let lan = Some(IpAddr::from([192, 168, 1, 10]));
+
+        assert_eq!(server_url(addr, lan), "http://192.168.1.10:3031");
+        assert_eq!(server_url(addr, None), "http://127.0.0.1:3031");
+        assert_eq!(
+            server_url("[::1]:3030".parse().unwrap(), lan),
+            "http://[::1]:3030"
+        );
+    }
+}
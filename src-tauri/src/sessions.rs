@@ -0,0 +1,610 @@
+use anyhow::{anyhow, Context};
+use scrybe_core::{
+    devices::AudioDevice,
+    subtitles::TranscriptSegment,
+    whisper::{WhisperParams, WhisperSegment, WhisperText, SPEAKER_CHANGE_MARKER},
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+pub const SESSIONS_DIR: &str = "sessions";
+const SESSION_EXTENSION: &str = "jsonl";
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+#[serde(default)]
+pub struct SessionHistorySettings {
+    pub enabled: bool,
+    /// Sessions older than this are deleted; 0 keeps them regardless of age.
+    pub max_age_days: u32,
+    /// Oldest sessions are deleted until the history fits; 0 means no limit.
+    pub max_total_mb: u32,
+}
+
+impl Default for SessionHistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: 30,
+            max_total_mb: 200,
+        }
+    }
+}
+
+/// What a transcription run was started with.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct SessionInfo {
+    pub id: String,
+    pub started_at_ms: u64,
+    pub device: AudioDevice,
+    pub model_path: String,
+    pub model_preset_id: Option<String>,
+    pub whisper_params: WhisperParams,
+    pub audio_segment_size: u64,
+}
+
+/// A caption segment as it stood when it was finalized. Item timestamps are
+/// whisper's, relative to the start of the segment's audio.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct SessionSegment {
+    pub segment_id: String,
+    pub index: u64,
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    pub items: Vec<WhisperText>,
+    /// When each run of the segment's audio was captured. Silence is trimmed
+    /// before audio reaches whisper, so its timestamps skip the pauses these
+    /// put back. Empty in sessions recorded before they were kept.
+    #[serde(default)]
+    pub capture_anchors: Vec<CaptureAnchor>,
+}
+
+/// Audio from `audio_ms` into a segment on, up to the next anchor, was
+/// captured from `captured_at_ms` on without a gap.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct CaptureAnchor {
+    pub audio_ms: u64,
+    pub captured_at_ms: u64,
+}
+
+impl SessionSegment {
+    pub fn from_segment(
+        segment: &WhisperSegment,
+        started_at_ms: u64,
+        ended_at_ms: u64,
+        capture_anchors: &[CaptureAnchor],
+    ) -> Self {
+        Self {
+            segment_id: segment.id.clone(),
+            index: segment.index,
+            started_at_ms,
+            ended_at_ms,
+            items: segment.items.clone(),
+            capture_anchors: capture_anchors.to_vec(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        spoken_text(&self.items)
+    }
+
+    /// How long after `started_at_ms` the audio `audio_ms` into the segment
+    /// was captured.
+    fn capture_offset_ms(&self, audio_ms: u64) -> u64 {
+        let anchor = self
+            .capture_anchors
+            .iter()
+            .rev()
+            .find(|anchor| anchor.audio_ms <= audio_ms);
+        match anchor {
+            Some(anchor) => (anchor.captured_at_ms + (audio_ms - anchor.audio_ms))
+                .saturating_sub(self.started_at_ms),
+            None => audio_ms,
+        }
+    }
+
+    /// The items with whisper's centisecond timestamps moved to when their
+    /// audio was captured.
+    fn captured_items(&self) -> Vec<WhisperText> {
+        let place = |centiseconds: i64| {
+            let audio_ms = centiseconds.max(0) as u64 * 10;
+            (self.capture_offset_ms(audio_ms) / 10) as i64
+        };
+        self.items
+            .iter()
+            .map(|item| WhisperText {
+                start_time: place(item.start_time),
+                end_time: place(item.end_time),
+                ..item.clone()
+            })
+            .collect()
+    }
+}
+
+/// Segment items as one line, without speaker change markers.
+pub fn spoken_text(items: &[WhisperText]) -> String {
+    items
+        .iter()
+        .map(|item| item.text.trim())
+        .filter(|text| !text.is_empty() && *text != SPEAKER_CHANGE_MARKER)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct SessionSummary {
+    pub info: SessionInfo,
+    /// Unset while the session is running or if the app exited mid-session.
+    pub stopped_at_ms: Option<u64>,
+    pub segment_count: u32,
+    pub size_bytes: u64,
+}
+
+impl SessionSummary {
+    fn last_activity_ms(&self, segments_ended_at_ms: u64) -> u64 {
+        self.stopped_at_ms
+            .unwrap_or(segments_ended_at_ms)
+            .max(self.info.started_at_ms)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct Session {
+    pub summary: SessionSummary,
+    pub segments: Vec<SessionSegment>,
+}
+
+impl Session {
+    /// The segments on a timeline that starts when the session did.
+    pub fn transcript(&self) -> Vec<TranscriptSegment> {
+        let started_at_ms = self.summary.info.started_at_ms;
+        self.segments
+            .iter()
+            .map(|segment| TranscriptSegment {
+                start_ms: segment.started_at_ms.saturating_sub(started_at_ms),
+                end_ms: segment.ended_at_ms.saturating_sub(started_at_ms),
+                items: segment.captured_items(),
+            })
+            .collect()
+    }
+}
+
+/// One line of a session file.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionRecord {
+    Start(SessionInfo),
+    Segment(SessionSegment),
+    Stop { stopped_at_ms: u64 },
+}
+
+/// Appends one session's records to `<id>.jsonl`. Every record is written
+/// as a single line, so a crash loses at most the line being written.
+pub struct SessionWriter {
+    file: File,
+    path: PathBuf,
+}
+
+impl SessionWriter {
+    pub fn create(sessions_dir: &Path, info: &SessionInfo) -> Result<Self, anyhow::Error> {
+        validate_session_id(&info.id)?;
+        fs::create_dir_all(sessions_dir)
+            .with_context(|| format!("failed to create {}", sessions_dir.display()))?;
+        let path = session_path(sessions_dir, &info.id);
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+
+        let mut writer = Self { file, path };
+        writer.write(&SessionRecord::Start(info.clone()))?;
+        Ok(writer)
+    }
+
+    pub fn append_segment(&mut self, segment: &SessionSegment) -> Result<(), anyhow::Error> {
+        self.write(&SessionRecord::Segment(segment.clone()))
+    }
+
+    pub fn finish(mut self, stopped_at_ms: u64) -> Result<(), anyhow::Error> {
+        self.write(&SessionRecord::Stop { stopped_at_ms })
+    }
+
+    fn write(&mut self, record: &SessionRecord) -> Result<(), anyhow::Error> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+pub fn list_sessions(sessions_dir: &Path) -> Vec<SessionSummary> {
+    let mut summaries: Vec<SessionSummary> = session_files(sessions_dir)
+        .into_iter()
+        .filter_map(|path| match read_session_file(&path) {
+            Ok(session) => Some(session.summary),
+            Err(err) => {
+                warn!("skipping session file {}: {:#}", path.display(), err);
+                None
+            }
+        })
+        .collect();
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.info.started_at_ms));
+    summaries
+}
+
+pub fn load_session(sessions_dir: &Path, id: &str) -> Result<Session, anyhow::Error> {
+    validate_session_id(id)?;
+    read_session_file(&session_path(sessions_dir, id))
+}
+
+pub fn delete_session(
+    sessions_dir: &Path,
+    id: &str,
+    active_session_id: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    validate_session_id(id)?;
+    if active_session_id == Some(id) {
+        return Err(anyhow!("cannot delete the session that is being recorded"));
+    }
+    let path = session_path(sessions_dir, id);
+    fs::remove_file(&path).with_context(|| format!("failed to delete {}", path.display()))
+}
+
+/// Deletes sessions past `max_age_days`, then the oldest ones until the
+/// history fits in `max_total_mb`. The active session is never deleted.
+/// Returns the ids of the deleted sessions.
+pub fn apply_retention(
+    sessions_dir: &Path,
+    settings: &SessionHistorySettings,
+    now_ms: u64,
+    active_session_id: Option<&str>,
+) -> Vec<String> {
+    let mut sessions: Vec<(u64, SessionSummary)> = session_files(sessions_dir)
+        .into_iter()
+        .filter_map(|path| read_session_file(&path).ok())
+        .map(|session| {
+            let segments_ended_at_ms = session
+                .segments
+                .iter()
+                .map(|segment| segment.ended_at_ms)
+                .max()
+                .unwrap_or(0);
+            (
+                session.summary.last_activity_ms(segments_ended_at_ms),
+                session.summary,
+            )
+        })
+        .filter(|(_, summary)| Some(summary.info.id.as_str()) != active_session_id)
+        .collect();
+    sessions.sort_by_key(|(last_activity_ms, _)| *last_activity_ms);
+
+    let max_age_ms = settings.max_age_days as u64 * MS_PER_DAY;
+    let max_total_bytes = settings.max_total_mb as u64 * BYTES_PER_MB;
+    let active_bytes = active_session_id
+        .and_then(|id| fs::metadata(session_path(sessions_dir, id)).ok())
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let mut total_bytes = active_bytes + sessions.iter().map(|(_, s)| s.size_bytes).sum::<u64>();
+
+    let mut deleted = Vec::new();
+    for (last_activity_ms, summary) in sessions {
+        let expired = max_age_ms > 0 && now_ms.saturating_sub(last_activity_ms) > max_age_ms;
+        let over_size = max_total_bytes > 0 && total_bytes > max_total_bytes;
+        if !expired && !over_size {
+            continue;
+        }
+        match delete_session(sessions_dir, &summary.info.id, active_session_id) {
+            Ok(()) => {
+                total_bytes = total_bytes.saturating_sub(summary.size_bytes);
+                deleted.push(summary.info.id);
+            }
+            Err(err) => warn!("session retention: {:#}", err),
+        }
+    }
+    if !deleted.is_empty() {
+        info!("session retention deleted {} sessions", deleted.len());
+    }
+    deleted
+}
+
+fn session_path(sessions_dir: &Path, id: &str) -> PathBuf {
+    sessions_dir.join(format!("{}.{}", id, SESSION_EXTENSION))
+}
+
+fn session_files(sessions_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(sessions_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == SESSION_EXTENSION)
+        })
+        .collect()
+}
+
+fn validate_session_id(id: &str) -> Result<(), anyhow::Error> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("invalid session id {:?}", id));
+    }
+    Ok(())
+}
+
+/// Reads a session file. A final line that doesn't parse is treated as a
+/// write interrupted by a crash and ignored.
+fn read_session_file(path: &Path) -> Result<Session, anyhow::Error> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let size_bytes = file.metadata()?.len();
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    let mut info = None;
+    let mut stopped_at_ms = None;
+    let mut segments = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match serde_json::from_str::<SessionRecord>(line) {
+            Ok(record) => record,
+            Err(_) if number + 1 == lines.len() => {
+                warn!("ignoring truncated last line of {}", path.display());
+                break;
+            }
+            Err(err) => return Err(anyhow!("{} line {}: {}", path.display(), number + 1, err)),
+        };
+        match record {
+            SessionRecord::Start(start) if info.is_none() => info = Some(start),
+            SessionRecord::Start(_) => {
+                return Err(anyhow!("{} has more than one start", path.display()))
+            }
+            SessionRecord::Segment(segment) => segments.push(segment),
+            SessionRecord::Stop { stopped_at_ms: at } => stopped_at_ms = Some(at),
+        }
+    }
+
+    let info = info.ok_or_else(|| anyhow!("{} has no start record", path.display()))?;
+    Ok(Session {
+        summary: SessionSummary {
+            info,
+            stopped_at_ms,
+            segment_count: segments.len() as u32,
+            size_bytes,
+        },
+        segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &str, started_at_ms: u64) -> SessionInfo {
+        SessionInfo {
+            id: id.to_owned(),
+            started_at_ms,
+            device: AudioDevice::default(),
+            model_path: "/models/ggml-base.bin".to_owned(),
+            model_preset_id: Some("base".to_owned()),
+            whisper_params: WhisperParams::default(),
+            audio_segment_size: 15,
+        }
+    }
+
+    fn segment(index: u64, text: &str, ended_at_ms: u64) -> SessionSegment {
+        SessionSegment {
+            segment_id: format!("segment-{}", index),
+            index,
+            started_at_ms: ended_at_ms.saturating_sub(1000),
+            ended_at_ms,
+            items: vec![WhisperText {
+                index: 0,
+                start_time: 0,
+                end_time: 100,
+                text: text.to_owned(),
+            }],
+            capture_anchors: Vec::new(),
+        }
+    }
+
+    fn record_session(dir: &Path, id: &str, started_at_ms: u64, segments: u64) {
+        let mut writer = SessionWriter::create(dir, &info(id, started_at_ms)).unwrap();
+        for index in 0..segments {
+            writer
+                .append_segment(&segment(index, "hello there", started_at_ms + index))
+                .unwrap();
+        }
+        writer.finish(started_at_ms + segments).unwrap();
+    }
+
+    #[test]
+    fn round_trips_a_recorded_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::create(dir.path(), &info("a", 1_000)).unwrap();
+        writer.append_segment(&segment(0, " hello", 2_000)).unwrap();
+        writer.append_segment(&segment(1, "world ", 3_000)).unwrap();
+        writer.finish(4_000).unwrap();
+
+        let session = load_session(dir.path(), "a").unwrap();
+
+        assert_eq!(session.summary.info.id, "a");
+        assert_eq!(
+            session.summary.info.model_preset_id.as_deref(),
+            Some("base")
+        );
+        assert_eq!(session.summary.stopped_at_ms, Some(4_000));
+        assert_eq!(session.summary.segment_count, 2);
+        assert_eq!(session.segments[1].text(), "world");
+        assert!(session.summary.size_bytes > 0);
+        let transcript = session.transcript();
+        assert_eq!(transcript[0].start_ms, 0);
+        assert_eq!(transcript[1].end_ms, 2_000);
+    }
+
+    #[test]
+    fn places_items_after_trimmed_pauses_at_their_capture_time() {
+        let items = [("hello", 0, 50), ("again", 100, 150)].map(|(text, start_time, end_time)| {
+            WhisperText {
+                text: text.to_owned(),
+                start_time,
+                end_time,
+                ..Default::default()
+            }
+        });
+        let segment = SessionSegment {
+            started_at_ms: 10_000,
+            ended_at_ms: 15_000,
+            items: items.to_vec(),
+            // Two seconds of silence between the words were trimmed away.
+            capture_anchors: vec![
+                CaptureAnchor {
+                    audio_ms: 0,
+                    captured_at_ms: 10_200,
+                },
+                CaptureAnchor {
+                    audio_ms: 1_000,
+                    captured_at_ms: 13_200,
+                },
+            ],
+            ..Default::default()
+        };
+        let session = Session {
+            summary: SessionSummary {
+                info: info("a", 9_000),
+                stopped_at_ms: Some(16_000),
+                segment_count: 1,
+                size_bytes: 0,
+            },
+            segments: vec![segment],
+        };
+
+        let transcript = session.transcript();
+
+        assert_eq!(transcript[0].start_ms, 1_000);
+        let times: Vec<(i64, i64)> = transcript[0]
+            .items
+            .iter()
+            .map(|item| (item.start_time, item.end_time))
+            .collect();
+        assert_eq!(times, [(20, 70), (320, 370)]);
+    }
+
+    #[test]
+    fn joins_segment_text_without_speaker_changes() {
+        let items = [" hello", SPEAKER_CHANGE_MARKER, " there "].map(|text| WhisperText {
+            text: text.to_owned(),
+            ..Default::default()
+        });
+
+        assert_eq!(spoken_text(&items), "hello there");
+    }
+
+    #[test]
+    fn tolerates_a_truncated_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = SessionWriter::create(dir.path(), &info("a", 1_000)).unwrap();
+        writer.append_segment(&segment(0, "hello", 2_000)).unwrap();
+        drop(writer);
+        let path = session_path(dir.path(), "a");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"segment","segm"#).unwrap();
+
+        let session = load_session(dir.path(), "a").unwrap();
+
+        assert_eq!(session.summary.stopped_at_ms, None);
+        assert_eq!(session.segments.len(), 1);
+    }
+
+    #[test]
+    fn lists_newest_first_and_deletes_inactive_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        record_session(dir.path(), "old", 1_000, 1);
+        record_session(dir.path(), "new", 5_000, 2);
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let ids: Vec<String> = list_sessions(dir.path())
+            .into_iter()
+            .map(|summary| summary.info.id)
+            .collect();
+
+        assert_eq!(ids, vec!["new", "old"]);
+        assert!(delete_session(dir.path(), "new", Some("new")).is_err());
+        assert!(delete_session(dir.path(), "../new", None).is_err());
+        delete_session(dir.path(), "old", Some("new")).unwrap();
+        assert_eq!(list_sessions(dir.path()).len(), 1);
+    }
+
+    #[test]
+    fn retention_deletes_expired_then_oldest_until_under_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = MS_PER_DAY;
+        record_session(dir.path(), "expired", 0, 1);
+        record_session(dir.path(), "older", 9 * day, 1);
+        record_session(dir.path(), "newer", 10 * day, 1);
+        record_session(dir.path(), "active", 10 * day, 1);
+
+        let by_age = apply_retention(
+            dir.path(),
+            &SessionHistorySettings {
+                enabled: true,
+                max_age_days: 5,
+                max_total_mb: 0,
+            },
+            10 * day,
+            Some("active"),
+        );
+        assert_eq!(by_age, vec!["expired"]);
+
+        let by_size = apply_retention(
+            dir.path(),
+            &SessionHistorySettings {
+                enabled: true,
+                max_age_days: 0,
+                max_total_mb: 1,
+            },
+            10 * day,
+            Some("active"),
+        );
+        assert!(by_size.is_empty());
+
+        for index in 0..8_000 {
+            let mut writer = OpenOptions::new()
+                .append(true)
+                .open(session_path(dir.path(), "newer"))
+                .unwrap();
+            let line = serde_json::to_string(&SessionRecord::Segment(segment(
+                index,
+                "padding padding padding padding padding padding",
+                10 * day,
+            )))
+            .unwrap();
+            writeln!(writer, "{}", line).unwrap();
+        }
+        let by_size = apply_retention(
+            dir.path(),
+            &SessionHistorySettings {
+                enabled: true,
+                max_age_days: 0,
+                max_total_mb: 1,
+            },
+            10 * day,
+            Some("active"),
+        );
+
+        assert_eq!(by_size, vec!["older", "newer"]);
+        assert_eq!(list_sessions(dir.path()).len(), 1);
+    }
+}
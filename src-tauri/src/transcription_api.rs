@@ -0,0 +1,259 @@
+use futures::{StreamExt, TryStreamExt};
+use scrybe_core::{
+    wav,
+    whisper::{is_cancelled_error, WhisperParams},
+};
+use tauri::{AppHandle, Manager};
+use tauri_svelte_synced_store::StateSyncer;
+use tracing::{debug, info};
+use warp::{
+    http::StatusCode,
+    hyper::body::Buf,
+    multipart::{FormData, Part},
+    reject::Rejection,
+    reply::Reply,
+    Filter,
+};
+
+use crate::{
+    batch_transcription::{self, BatchTranscript, ResponseFormat},
+    model_priority::ModelPriority,
+    types::AppState,
+    SharedWhisperManager,
+};
+
+/// OpenAI's own limit for uploads.
+const MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Uploads are transcribed one at a time, in the order they arrived.
+#[derive(Default)]
+pub struct TranscriptionQueue {
+    turn: tokio::sync::Mutex<()>,
+}
+
+impl TranscriptionQueue {
+    /// Waits for every transcription queued before this one to finish.
+    pub async fn turn(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.turn.lock().await
+    }
+}
+
+struct TranscriptionRequest {
+    audio: Vec<u8>,
+    file_name: String,
+    language: Option<String>,
+    prompt: Option<String>,
+    response_format: ResponseFormat,
+}
+
+/// A failed request, answered in the shape OpenAI's clients expect.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    fn invalid_request(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn unavailable(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: message.into(),
+        }
+    }
+
+    fn into_response(self) -> warp::reply::Response {
+        let error_type = if self.status.is_client_error() {
+            "invalid_request_error"
+        } else {
+            "server_error"
+        };
+        let body = serde_json::json!({
+            "error": {
+                "message": self.message,
+                "type": error_type,
+                "param": null,
+                "code": null,
+            }
+        });
+        warp::reply::with_status(warp::reply::json(&body), self.status).into_response()
+    }
+}
+
+/// `POST /v1/audio/transcriptions` in the shape of OpenAI's API, answered
+/// with the model already loaded for captions. The file must be an
+/// uncompressed WAV; MP3, FLAC and other formats OpenAI accepts are refused
+/// with a 400. The `model` field is accepted and ignored.
+pub fn route(
+    app: &AppHandle,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + Send + Sync + 'static {
+    let app = app.clone();
+    warp::post()
+        .and(warp::multipart::form().max_length(MAX_UPLOAD_BYTES))
+        .then(move |form: FormData| {
+            let app = app.clone();
+            async move {
+                match transcribe(app, form).await {
+                    Ok(reply) => reply,
+                    Err(err) => err.into_response(),
+                }
+            }
+        })
+}
+
+async fn transcribe(app: AppHandle, form: FormData) -> Result<warp::reply::Response, ApiError> {
+    let request = read_form(form).await?;
+    let format = request.response_format;
+
+    let queue = app.state::<TranscriptionQueue>();
+    let _turn = queue.turn().await;
+    info!(
+        "transcribing upload {} ({} bytes)",
+        request.file_name,
+        request.audio.len()
+    );
+
+    let app_ref = app.clone();
+    let transcript = tauri::async_runtime::spawn_blocking(move || run(&app_ref, request))
+        .await
+        .map_err(|err| ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: err.to_string(),
+        })??;
+
+    Ok(warp::reply::with_header(
+        transcript.render(format),
+        "content-type",
+        format.content_type(),
+    )
+    .into_response())
+}
+
+/// Decodes the upload and transcribes it with the requested language.
+fn run(app: &AppHandle, request: TranscriptionRequest) -> Result<BatchTranscript, ApiError> {
+    let audio = wav::decode_wav_bytes(&request.audio).map_err(|err| {
+        ApiError::invalid_request(format!(
+            "unable to decode {}: only uncompressed WAV files are supported ({:#})",
+            request.file_name, err
+        ))
+    })?;
+    let params = batch_transcription::batch_params(
+        &app.state::<StateSyncer>()
+            .snapshot::<AppState>("app_state")
+            .whisper_params,
+        request.language.as_deref(),
+    );
+    transcribe_samples(
+        app,
+        &audio.samples,
+        &params,
+        request.prompt.as_deref(),
+        audio.duration_ms,
+    )
+}
+
+/// Runs 16kHz mono samples through the loaded model a chunk at a time,
+/// letting live captioning steps go first. Each chunk is prompted with the
+/// text of the one before it. Callers hold a turn from the
+/// `TranscriptionQueue`.
+pub fn transcribe_samples(
+    app: &AppHandle,
+    samples: &[f32],
+    params: &WhisperParams,
+    prompt: Option<&str>,
+    duration_ms: u64,
+) -> Result<BatchTranscript, ApiError> {
+    let mut transcript = BatchTranscript {
+        language: params.language.clone(),
+        duration_ms,
+        chunks: Vec::new(),
+    };
+    let model_priority = app.state::<ModelPriority>();
+    let whisper_manager = app.state::<SharedWhisperManager>();
+    for range in batch_transcription::chunk_ranges(samples.len()) {
+        let mut manager_ref = model_priority.lock_for_batch(&whisper_manager);
+        let Some(manager) = manager_ref.as_mut() else {
+            return Err(ApiError::unavailable("the model isn't loaded"));
+        };
+        debug!("transcribing samples {:?}", range);
+        let chunk_prompt = transcript.next_prompt(prompt);
+        let items = manager
+            .process_samples_with_prompt(
+                samples[range.clone()].to_vec(),
+                params.clone(),
+                chunk_prompt.as_deref(),
+                model_priority.batch_cancellation(),
+            )
+            .map_err(|err| {
+                if is_cancelled_error(&err) {
+                    ApiError::unavailable("the model was reloaded during transcription")
+                } else {
+                    ApiError {
+                        status: StatusCode::INTERNAL_SERVER_ERROR,
+                        message: format!("transcription failed: {:#}", err),
+                    }
+                }
+            })?;
+        transcript.push_chunk(range, items);
+    }
+    Ok(transcript)
+}
+
+async fn read_form(form: FormData) -> Result<TranscriptionRequest, ApiError> {
+    let mut audio = None;
+    let mut language = None;
+    let mut prompt = None;
+    let mut response_format = ResponseFormat::Json;
+
+    let mut parts = form.map_err(|err| ApiError::invalid_request(err.to_string()));
+    while let Some(part) = parts.next().await {
+        let part = part?;
+        let name = part.name().to_owned();
+        match name.as_str() {
+            "file" => {
+                let file_name = part.filename().unwrap_or("upload.wav").to_owned();
+                audio = Some((read_part(part).await?, file_name));
+            }
+            "language" => language = Some(read_text(part).await?),
+            "prompt" => prompt = Some(read_text(part).await?),
+            "response_format" => {
+                response_format = ResponseFormat::parse(read_text(part).await?.trim())
+                    .map_err(ApiError::invalid_request)?
+            }
+            // `model`, `temperature` and the rest don't apply to the loaded
+            // model.
+            _ => debug!("ignoring transcription form field {}", name),
+        }
+    }
+
+    let (audio, file_name) =
+        audio.ok_or_else(|| ApiError::invalid_request("the `file` field is required"))?;
+    Ok(TranscriptionRequest {
+        audio,
+        file_name,
+        language: language.map(|language| language.trim().to_owned()),
+        prompt,
+        response_format,
+    })
+}
+
+async fn read_part(part: Part) -> Result<Vec<u8>, ApiError> {
+    part.stream()
+        .try_fold(Vec::new(), |mut bytes, mut buf| async move {
+            bytes.extend_from_slice(&buf.copy_to_bytes(buf.remaining()));
+            Ok::<_, warp::Error>(bytes)
+        })
+        .await
+        .map_err(|err| ApiError::invalid_request(err.to_string()))
+}
+
+async fn read_text(part: Part) -> Result<String, ApiError> {
+    String::from_utf8(read_part(part).await?)
+        .map_err(|_| ApiError::invalid_request("form fields must be UTF-8 text"))
+}
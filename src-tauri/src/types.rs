@@ -4,11 +4,18 @@ pub struct AppState {
     pub current_device: scrybe_core::devices::AudioDevice,
     pub audio_format: scrybe_core::devices::AudioFormat,
     pub model_path: String,
+    /// Extra folders scanned for model files by the model library.
+    pub model_folders: Vec<String>,
+    pub model_adaptation: crate::model_adaptation::ModelAdaptationMode,
     pub audio_segment_size: u64,
     pub overlay_config: OverlayConfig,
     pub home_right_rail: HomeRightRailSettings,
     pub whisper_params: scrybe_core::whisper::WhisperParams,
     pub advanced_settings: AdvancedSettings,
+    pub session_history: crate::sessions::SessionHistorySettings,
+    pub caption_file: crate::caption_file::CaptionFileSettings,
+    pub server: crate::server_config::ServerSettings,
+    pub wyoming: crate::wyoming::WyomingSettings,
 }
 
 impl Default for AppState {
@@ -17,11 +24,17 @@ impl Default for AppState {
             current_device: scrybe_core::devices::AudioDevice::default(),
             audio_format: scrybe_core::devices::AudioFormat::default(),
             model_path: Default::default(),
+            model_folders: Vec::new(),
+            model_adaptation: Default::default(),
             audio_segment_size: 15,
             overlay_config: OverlayConfig::default(),
             home_right_rail: HomeRightRailSettings::default(),
             whisper_params: scrybe_core::whisper::WhisperParams::default(),
             advanced_settings: AdvancedSettings::default(),
+            session_history: Default::default(),
+            caption_file: Default::default(),
+            server: Default::default(),
+            wyoming: Default::default(),
         }
     }
 }
@@ -203,6 +216,23 @@ impl<'de> serde::Deserialize<'de> for OverlayConfig {
 }
 
 impl OverlayConfig {
+    /// The overlay's look, for subtitle formats that carry styling.
+    pub fn subtitle_style(&self) -> scrybe_core::subtitles::SubtitleStyle {
+        scrybe_core::subtitles::SubtitleStyle {
+            align: match self.style.align.as_str() {
+                "left" => scrybe_core::subtitles::SubtitleAlign::Left,
+                "right" => scrybe_core::subtitles::SubtitleAlign::Right,
+                _ => scrybe_core::subtitles::SubtitleAlign::Center,
+            },
+            font_size_px: self.style.font_size,
+            text_color: self.style.text_color.clone(),
+            background_color: self.style.background_color.clone(),
+            background_opacity: self.style.background_opacity,
+            canvas_width: self.canvas.width,
+            canvas_height: self.canvas.height,
+        }
+    }
+
     fn from_wire(wire: OverlayConfigWire) -> Self {
         let mut config = OverlayConfig::default();
         let has_v2_canvas = wire.canvas.is_some();
@@ -452,7 +482,16 @@ pub struct ModelPreset {
     pub description: String,
     pub repo: String,
     pub filename: String,
+    /// Absolute path of a model file on disk, for catalog presets that
+    /// aren't downloaded from the Hub. `repo` is empty when this is set.
+    pub local_path: Option<String>,
     pub size_mb: u16,
+    /// Exact size of the model file, checked after download. When unset, the
+    /// size reported by the Hub for the LFS object is used.
+    pub size_bytes: Option<u64>,
+    /// Lowercase hex SHA-256 of the model file, checked after download. When
+    /// unset, the checksum reported by the Hub for the LFS object is used.
+    pub sha256: Option<String>,
     pub resource_rank: u8,
     pub auto_selectable: bool,
 }
@@ -474,7 +513,10 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Fastest, lowest quality. ~44 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-tiny-q8_0.bin".to_string(),
+            local_path: None,
             size_mb: 44,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 1,
             auto_selectable: true,
         },
@@ -484,7 +526,10 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Very fast, basic quality. ~82 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-base-q8_0.bin".to_string(),
+            local_path: None,
             size_mb: 82,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 2,
             auto_selectable: true,
         },
@@ -494,7 +539,10 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Balanced speed and quality. ~264 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-small-q8_0.bin".to_string(),
+            local_path: None,
             size_mb: 264,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 3,
             auto_selectable: false,
         },
@@ -504,7 +552,10 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Higher quality, slower. ~823 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-medium-q8_0.bin".to_string(),
+            local_path: None,
             size_mb: 823,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 4,
             auto_selectable: false,
         },
@@ -514,7 +565,10 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Large-v3 quality, ~8× faster than non-turbo. ~574 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-large-v3-turbo-q5_0.bin".to_string(),
+            local_path: None,
             size_mb: 574,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 5,
             auto_selectable: true,
         },
@@ -524,24 +578,13 @@ pub fn model_presets() -> Vec<ModelPreset> {
             description: "Highest quality turbo variant. ~874 MB.".to_string(),
             repo: repo.clone(),
             filename: "ggml-large-v3-turbo-q8_0.bin".to_string(),
+            local_path: None,
             size_mb: 874,
+            size_bytes: None,
+            sha256: None,
             resource_rank: 6,
             auto_selectable: false,
         },
     ]
 }
 
-#[derive(Debug, Default, serde::Serialize, serde::Deserialize, Clone, specta::Type)]
-#[serde(default)]
-pub struct WebsocketRequest {
-    pub kind: String,
-    pub data: String,
-}
-
-#[derive(Debug, Default, serde::Serialize, serde::Deserialize, Clone, specta::Type)]
-#[serde(default)]
-pub struct WebsocketResponse {
-    pub kind: String,
-    pub data: String,
-    pub is_error: bool,
-}
@@ -1,187 +1,450 @@
-use futures::{FutureExt, StreamExt};
-use serde::Serialize;
-use std::{collections::HashMap, sync::Arc};
+use futures::{
+    stream::{self, SplitSink},
+    SinkExt, Stream, StreamExt,
+};
+use scrybe_core::{metrics::LatencyWindow, whisper::WhisperSegment};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tauri::AppHandle;
 use tauri_svelte_synced_store::StateSyncer;
-use tokio::sync::{mpsc, Mutex};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::{mpsc, Notify};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 use warp::{filters::ws::WebSocket, reject::Rejection, ws::Message};
 
 use crate::{
-    types::{AppState, WebsocketRequest, WebsocketResponse},
+    remote_control,
+    segment_replay::SegmentReplay,
+    types::AppState,
+    ws_fanout::{ClientQueue, Delivery, FanoutCounters, CLIENT_QUEUE_CAPACITY},
+    ws_protocol::{self, ClientRequest, ErrorCode, ServerEnvelope, ServerMessage, Topic},
     InternalState,
 };
 
 pub type _WSResult<T> = std::result::Result<T, Rejection>;
 type Clients = Arc<Mutex<HashMap<String, Client>>>;
 
-#[derive(Debug, Clone)]
+/// How often clients are pinged to check they're still there.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// A client that hasn't sent anything, pongs included, for this long is
+/// disconnected.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+/// A write that takes longer than this means the client has stalled.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
 pub struct Client {
     pub client_id: String,
-    pub sender: mpsc::UnboundedSender<std::result::Result<Message, warp::Error>>,
+    pub queue: ClientQueue<Message>,
+    pub subscriptions: HashSet<Topic>,
+}
+
+/// Removes an event stream's client once the stream is dropped.
+struct EventStreamClient {
+    manager: WebsocketManager,
+    client_id: String,
+}
+
+impl Drop for EventStreamClient {
+    fn drop(&mut self) {
+        self.manager.unregister(&self.client_id);
+        debug!("[{}] event stream closed", self.client_id);
+    }
 }
 
 #[derive(Clone)]
 pub struct WebsocketManager {
     pub clients: Clients,
     pub store: StateSyncer,
+    /// Commands from clients act on the app as the frontend would.
+    app: AppHandle,
+    /// How long segment updates took to reach every client's send queue.
+    pub segment_fanout: Arc<Mutex<LatencyWindow>>,
+    pub fanout_counters: Arc<FanoutCounters>,
+    /// Replayed to clients as they connect. Locked before `clients`, so a
+    /// new client gets the replay before any later segment update.
+    segment_replay: Arc<Mutex<SegmentReplay>>,
 }
 
 impl WebsocketManager {
-    pub fn new(store: StateSyncer) -> Result<Self, anyhow::Error> {
+    pub fn new(app: AppHandle, store: StateSyncer) -> Result<Self, anyhow::Error> {
         let clients = Arc::new(Mutex::new(HashMap::new()));
-        Ok(WebsocketManager { clients, store })
+        Ok(WebsocketManager {
+            clients,
+            store,
+            app,
+            segment_fanout: Arc::new(Mutex::new(LatencyWindow::default())),
+            fanout_counters: Arc::new(FanoutCounters::default()),
+            segment_replay: Arc::new(Mutex::new(SegmentReplay::default())),
+        })
     }
 
+    /// Serves one client until it disconnects, stops answering pings, or
+    /// falls too far behind. `broadcast` and request replies queue messages
+    /// for the client without waiting; they're written out here.
     pub async fn client_connection(self, ws: WebSocket) {
         info!("establishing client connection... {:?}", ws);
 
-        let (client_ws_sender, mut client_ws_rcv) = ws.split();
-        let (client_sender, client_rcv) = mpsc::unbounded_channel();
-
-        let client_rcv = UnboundedReceiverStream::new(client_rcv);
+        let (mut client_ws_sender, mut client_ws_rcv) = ws.split();
+        let (uuid, mut outgoing, lagging) = self.register(HashSet::from(Topic::DEFAULT));
 
-        tokio::task::spawn(client_rcv.forward(client_ws_sender).map(|result| {
-            if let Err(e) = result {
-                error!("error sending websocket msg: {}", e);
+        let mut keepalive = tokio::time::interval(PING_INTERVAL);
+        let mut last_heard = Instant::now();
+        loop {
+            tokio::select! {
+                _ = lagging.notified() => {
+                    warn!("[{}] disconnecting client that stopped keeping up", uuid);
+                    break;
+                }
+                message = outgoing.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    if !Self::write(&uuid, &mut client_ws_sender, message).await {
+                        break;
+                    }
+                }
+                _ = keepalive.tick() => {
+                    if last_heard.elapsed() > CLIENT_TIMEOUT {
+                        warn!("[{}] disconnecting client that stopped responding", uuid);
+                        break;
+                    }
+                    let ping = Message::ping(Vec::new());
+                    if !Self::write(&uuid, &mut client_ws_sender, ping).await {
+                        break;
+                    }
+                }
+                result = client_ws_rcv.next() => {
+                    let msg = match result {
+                        Some(Ok(msg)) => msg,
+                        Some(Err(e)) => {
+                            error!("error receiving message for id {}): {}", uuid.clone(), e);
+                            break;
+                        }
+                        None => break,
+                    };
+                    last_heard = Instant::now();
+                    if msg.is_close() {
+                        break;
+                    }
+                    if !self.handle_websocket_message(&uuid, msg) {
+                        // Deliver the reply explaining why before closing.
+                        while let Ok(message) = outgoing.try_recv() {
+                            Self::write(&uuid, &mut client_ws_sender, message).await;
+                        }
+                        break;
+                    }
+                }
             }
-        }));
+        }
 
-        let uuid = Uuid::new_v4().simple().to_string();
+        self.unregister(&uuid);
+        let _ = client_ws_sender.close().await;
+        debug!("{} disconnected", uuid);
+    }
 
-        let new_client = Client {
-            client_id: uuid.clone(),
-            sender: client_sender,
+    /// The updates a WebSocket client subscribed to `topics` would get, as
+    /// serialized envelopes, for clients that can't speak WebSocket. Ends
+    /// when the client falls too far behind; dropping it disconnects the
+    /// client.
+    pub fn event_stream(&self, topics: HashSet<Topic>) -> impl Stream<Item = String> + Send {
+        let (client_id, outgoing, lagging) = self.register(topics);
+        info!("[{}] event stream opened", client_id);
+        let client = EventStreamClient {
+            manager: self.clone(),
+            client_id,
         };
+        stream::unfold(
+            (client, outgoing, lagging),
+            |(client, mut outgoing, lagging)| async move {
+                let message = tokio::select! {
+                    _ = lagging.notified() => {
+                        warn!("[{}] closing event stream that stopped keeping up", client.client_id);
+                        None
+                    }
+                    message = outgoing.recv() => message,
+                };
+                let text = message?.to_str().ok()?.to_owned();
+                Some((text, (client, outgoing, lagging)))
+            },
+        )
+    }
 
-        self.clients
-            .lock()
-            .await
-            .insert(uuid.clone(), new_client.clone());
-
-        while let Some(result) = client_ws_rcv.next().await {
-            let msg = match result {
-                Ok(msg) => msg,
-                Err(e) => {
-                    error!("error receiving message for id {}): {}", uuid.clone(), e);
-                    break;
-                }
-            };
-            self.clone()
-                .handle_websocket_message(new_client.clone(), msg.clone());
+    /// Adds a client, queueing the recent segments for it ahead of any later
+    /// update when it is subscribed to them.
+    fn register(
+        &self,
+        subscriptions: HashSet<Topic>,
+    ) -> (String, mpsc::Receiver<Message>, Arc<Notify>) {
+        let (queue, outgoing) = ClientQueue::new(CLIENT_QUEUE_CAPACITY);
+        let lagging = queue.disconnect_signal();
+        let client_id = Uuid::new_v4().simple().to_string();
+
+        let segment_replay = self.segment_replay.lock().unwrap();
+        let replay = subscriptions.contains(&Topic::Segments) && !segment_replay.is_empty();
+        self.clients.lock().unwrap().insert(
+            client_id.clone(),
+            Client {
+                client_id: client_id.clone(),
+                queue,
+                subscriptions,
+            },
+        );
+        if replay {
+            self.send(
+                &client_id,
+                &ServerEnvelope {
+                    id: None,
+                    message: ServerMessage::RecentSegments {
+                        segments: segment_replay.recent(),
+                    },
+                },
+            );
         }
+        (client_id, outgoing, lagging)
+    }
 
-        self.clients.lock().await.remove(&uuid);
-        debug!("{} disconnected", uuid);
+    fn unregister(&self, client_id: &str) {
+        let removed = self.clients.lock().unwrap().remove(client_id);
+        if let Some(client) = removed {
+            if client.queue.dropped() > 0 {
+                info!(
+                    "[{}] missed {} messages while connected",
+                    client_id,
+                    client.queue.dropped()
+                );
+            }
+        }
     }
 
-    pub fn broadcast(self, msg: String) {
+    async fn write(
+        client_id: &str,
+        sink: &mut SplitSink<WebSocket, Message>,
+        message: Message,
+    ) -> bool {
+        match tokio::time::timeout(SEND_TIMEOUT, sink.send(message)).await {
+            Ok(Ok(())) => true,
+            Ok(Err(err)) => {
+                error!("[{}] error sending websocket msg: {}", client_id, err);
+                false
+            }
+            Err(_) => {
+                warn!("[{}] timed out sending websocket msg", client_id);
+                false
+            }
+        }
+    }
+
+    /// Broadcasts a segment update and keeps it for replay.
+    pub fn publish_segment(&self, segment: WhisperSegment) {
+        let mut segment_replay = self.segment_replay.lock().unwrap();
+        segment_replay.record(segment.clone());
+        self.broadcast(ServerMessage::SegmentUpdate { segment });
+    }
+
+    /// The segment on the overlay right now.
+    pub fn current_segment(&self) -> Option<WhisperSegment> {
+        self.segment_replay.lock().unwrap().current().cloned()
+    }
+
+    /// Queues an update for every client subscribed to its topic. Never
+    /// waits on a client: one whose queue is full misses the update.
+    pub fn broadcast(&self, message: ServerMessage) {
+        let topic = message.topic();
+        let mut clients = self.clients.lock().unwrap();
+        let recipients: Vec<String> = clients
+            .values()
+            .filter(|client| topic.is_none_or(|topic| client.subscriptions.contains(&topic)))
+            .map(|client| client.client_id.clone())
+            .collect();
+        if recipients.is_empty() {
+            return;
+        }
+
+        let envelope = ServerEnvelope { id: None, message };
+        let msg = match serde_json::to_string(&envelope) {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("failed to serialize websocket message: {}", err);
+                return;
+            }
+        };
+
         debug!("broadcasting: {}", msg);
-        match self.clients.blocking_lock() {
-            guard => {
-                for (id, client) in guard.iter() {
-                    debug!("sending to {}", id);
-
-                    match client.sender.send(Ok(Message::text(msg.clone()))) {
-                        Ok(_) => {}
-                        Err(err) => error!("failed to send to client: {}", err),
-                    };
+        for id in recipients {
+            debug!("sending to {}", id);
+            self.deliver(&mut clients, &id, Message::text(msg.clone()));
+        }
+    }
+
+    /// Queues a message for one client, removing the client if it has fallen
+    /// too far behind.
+    fn deliver(&self, clients: &mut HashMap<String, Client>, client_id: &str, message: Message) {
+        let Some(client) = clients.get_mut(client_id) else {
+            return;
+        };
+        match client.queue.push(message, &self.fanout_counters) {
+            Delivery::Queued => {}
+            Delivery::Dropped => debug!("[{}] queue full, dropped message", client_id),
+            Delivery::Disconnect => {
+                if let Some(client) = clients.remove(client_id) {
+                    client.queue.disconnect();
                 }
             }
         }
     }
 
-    fn handle_websocket_message(self, client: Client, message: Message) {
+    /// Handles one message from a client, returning false when the
+    /// connection should be closed.
+    fn handle_websocket_message(&self, client_id: &str, message: Message) -> bool {
         // Skip any non-Text messages...
         let msg = if let Ok(s) = message.to_str() {
             s
         } else {
             debug!("ping-pong");
-            return;
+            return true;
         };
 
-        info!("[{}] got request {}", client.client_id, msg);
+        info!("[{}] got request {}", client_id, msg);
 
-        let request: WebsocketRequest = match serde_json::from_str(msg) {
-            Ok(result) => result,
-            Err(err) => {
-                error!("failed to parse websocket request: {}", err);
-                return;
+        let envelope = match ws_protocol::parse_client_message(msg) {
+            Ok(envelope) => envelope,
+            Err(reply) => {
+                warn!("[{}] rejected websocket request", client_id);
+                self.send(client_id, &reply);
+                return true;
             }
         };
 
-        match request.kind.as_str() {
-            "get_appstate" => {
-                debug!("get_appstate from websocket");
-
-                {
-                    let app_state_ref = self.store.get::<AppState>("app_state");
-                    let app_state = app_state_ref.lock().unwrap();
-
-                    let response =
-                        Self::to_ws_response("app_state_update".to_owned(), app_state.clone());
-
-                    let response_str = match serde_json::to_string(&response) {
-                        Ok(data) => data,
-                        Err(err) => {
-                            error!("failed to serialize response: {}", err);
-                            return;
-                        }
-                    };
-
-                    match client.sender.send(Ok(Message::text(response_str.clone()))) {
-                        Ok(_) => {}
-                        Err(err) => error!("failed to send to client: {}", err),
-                    };
+        let mut keep_open = true;
+        let message = match envelope.request {
+            ClientRequest::Hello {
+                protocol_version,
+                client: client_name,
+            } => {
+                info!(
+                    "[{}] hello from {} speaking protocol version {}",
+                    client_id,
+                    client_name.as_deref().unwrap_or("unnamed client"),
+                    protocol_version
+                );
+                match ws_protocol::check_protocol_version(protocol_version) {
+                    Ok(()) => ServerMessage::Hello {
+                        protocol_version: ws_protocol::PROTOCOL_VERSION,
+                        min_protocol_version: ws_protocol::MIN_PROTOCOL_VERSION,
+                        server_version: self
+                            .store
+                            .snapshot::<InternalState>("internal_state")
+                            .version,
+                    },
+                    Err(error) => {
+                        keep_open = false;
+                        error
+                    }
                 }
             }
-            "get_internalstate" => {
-                debug!("get_internalstate from websocket");
+            ClientRequest::GetAppState => ServerMessage::AppState {
+                state: Box::new(self.store.snapshot::<AppState>("app_state")),
+            },
+            ClientRequest::GetInternalState => ServerMessage::InternalState {
+                state: Box::new(self.store.snapshot::<InternalState>("internal_state")),
+            },
+            ClientRequest::GetRecentSegments => ServerMessage::RecentSegments {
+                segments: self.segment_replay.lock().unwrap().recent(),
+            },
+            ClientRequest::StartTranscribe => {
+                Self::command_reply(remote_control::start_transcription(&self.app))
+            }
+            ClientRequest::StopTranscribe => {
+                remote_control::stop_transcription(&self.app);
+                ServerMessage::Ack
+            }
+            ClientRequest::SetDevice { device_id } => {
+                Self::command_reply(remote_control::set_device(&self.app, &device_id))
+            }
+            ClientRequest::SetModelPreset { preset_id } => {
+                Self::command_reply(remote_control::set_model_preset(&self.app, &preset_id))
+            }
+            ClientRequest::SetWhisperParams { params } => {
+                remote_control::set_whisper_params(&self.app, params);
+                ServerMessage::Ack
+            }
+            ClientRequest::SetOverlayConfig { config } => {
+                remote_control::set_overlay_config(&self.app, config);
+                ServerMessage::Ack
+            }
+            ClientRequest::ShowTestText { text, duration_ms } => {
+                remote_control::show_test_text(&self.app, text, duration_ms);
+                ServerMessage::Ack
+            }
+            ClientRequest::ClearTestText => {
+                remote_control::clear_test_text(&self.app);
+                ServerMessage::Ack
+            }
+            ClientRequest::Subscribe { topics } => {
+                self.update_subscriptions(client_id, |subscriptions| subscriptions.extend(topics))
+            }
+            ClientRequest::Unsubscribe { topics } => self
+                .update_subscriptions(client_id, |subscriptions| {
+                    subscriptions.retain(|topic| !topics.contains(topic))
+                }),
+        };
 
-                {
-                    let internal_state_ref = self.store.get::<InternalState>("internal_state");
-                    let internal_state = internal_state_ref.lock().unwrap();
+        self.send(
+            client_id,
+            &ServerEnvelope {
+                id: envelope.id,
+                message,
+            },
+        );
+        keep_open
+    }
 
-                    let response = Self::to_ws_response(
-                        "internal_state_update".to_owned(),
-                        internal_state.clone(),
-                    );
+    fn update_subscriptions(
+        &self,
+        client_id: &str,
+        update: impl FnOnce(&mut HashSet<Topic>),
+    ) -> ServerMessage {
+        let mut clients = self.clients.lock().unwrap();
+        let Some(client) = clients.get_mut(client_id) else {
+            return ServerMessage::Subscriptions { topics: Vec::new() };
+        };
+        update(&mut client.subscriptions);
+        let mut topics: Vec<Topic> = client.subscriptions.iter().copied().collect();
+        topics.sort();
+        ServerMessage::Subscriptions { topics }
+    }
 
-                    let response_str = match serde_json::to_string(&response) {
-                        Ok(data) => data,
-                        Err(err) => {
-                            error!("failed to serialize response: {}", err);
-                            return;
-                        }
-                    };
+    /// Whether any client would receive a broadcast on `topic`, so callers
+    /// can skip building updates nobody is listening for.
+    pub fn has_subscribers(&self, topic: Topic) -> bool {
+        self.clients
+            .lock()
+            .unwrap()
+            .values()
+            .any(|client| client.subscriptions.contains(&topic))
+    }
 
-                    match client.sender.send(Ok(Message::text(response_str.clone()))) {
-                        Ok(_) => {}
-                        Err(err) => error!("failed to send to client: {}", err),
-                    };
-                }
-            }
-            "" => warn!("missing kind"),
-            _ => warn!("unknown kind"),
+    fn command_reply(result: Result<(), String>) -> ServerMessage {
+        match result {
+            Ok(()) => ServerMessage::Ack,
+            Err(reason) => ServerMessage::error(ErrorCode::CommandFailed, reason),
         }
     }
 
-    pub fn to_ws_response<T>(kind: String, object: T) -> WebsocketResponse
-    where
-        T: for<'a> Serialize,
-    {
-        let mut response = WebsocketResponse::default();
-        response.kind = kind;
-        response.data = match serde_json::to_string(&object) {
+    fn send(&self, client_id: &str, envelope: &ServerEnvelope) {
+        let response_str = match serde_json::to_string(envelope) {
             Ok(data) => data,
             Err(err) => {
-                error!("failed to serialize object: {}", err);
-                response.is_error = true;
-                err.to_string()
+                error!("failed to serialize response: {}", err);
+                return;
             }
         };
 
-        response
+        let mut clients = self.clients.lock().unwrap();
+        self.deliver(&mut clients, client_id, Message::text(response_str));
     }
 }
@@ -0,0 +1,140 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    Notify,
+};
+
+/// Messages waiting to be written to a client. Past this, new messages for
+/// the client are dropped instead of queued.
+pub const CLIENT_QUEUE_CAPACITY: usize = 64;
+/// A client that has missed this many messages in a row is disconnected;
+/// it's most likely a browser source that stopped reading.
+pub const MAX_CONSECUTIVE_DROPS: u32 = 32;
+
+/// Totals across every client since the server started.
+#[derive(Debug, Default)]
+pub struct FanoutCounters {
+    dropped_messages: AtomicU64,
+    lagging_disconnects: AtomicU64,
+}
+
+impl FanoutCounters {
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.load(Ordering::Relaxed)
+    }
+
+    pub fn lagging_disconnects(&self) -> u64 {
+        self.lagging_disconnects.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Queued,
+    Dropped,
+    /// The client has fallen too far behind or is gone, and should be
+    /// removed.
+    Disconnect,
+}
+
+/// The sending side of one client's bounded queue.
+#[derive(Debug)]
+pub struct ClientQueue<T> {
+    sender: mpsc::Sender<T>,
+    consecutive_drops: u32,
+    dropped: u64,
+    disconnect: Arc<Notify>,
+}
+
+impl<T> ClientQueue<T> {
+    pub fn new(capacity: usize) -> (Self, mpsc::Receiver<T>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let queue = ClientQueue {
+            sender,
+            consecutive_drops: 0,
+            dropped: 0,
+            disconnect: Arc::new(Notify::new()),
+        };
+        (queue, receiver)
+    }
+
+    /// Queues a message without waiting. A full queue drops the message, and
+    /// enough drops in a row ask for the client to be disconnected.
+    pub fn push(&mut self, message: T, counters: &FanoutCounters) -> Delivery {
+        match self.sender.try_send(message) {
+            Ok(()) => {
+                self.consecutive_drops = 0;
+                Delivery::Queued
+            }
+            Err(TrySendError::Full(_)) => {
+                self.consecutive_drops += 1;
+                self.dropped += 1;
+                counters.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                if self.consecutive_drops < MAX_CONSECUTIVE_DROPS {
+                    return Delivery::Dropped;
+                }
+                counters.lagging_disconnects.fetch_add(1, Ordering::Relaxed);
+                Delivery::Disconnect
+            }
+            Err(TrySendError::Closed(_)) => Delivery::Disconnect,
+        }
+    }
+
+    /// Messages this client has missed.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Notified when the client is disconnected for lagging, so its
+    /// connection can stop even while a write to it is stuck.
+    pub fn disconnect_signal(&self) -> Arc<Notify> {
+        self.disconnect.clone()
+    }
+
+    pub fn disconnect(&self) {
+        self.disconnect.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_when_full_and_disconnects_after_too_many_drops() {
+        let counters = FanoutCounters::default();
+        let (mut queue, mut receiver) = ClientQueue::new(2);
+
+        assert_eq!(queue.push(1, &counters), Delivery::Queued);
+        assert_eq!(queue.push(2, &counters), Delivery::Queued);
+        for _ in 1..MAX_CONSECUTIVE_DROPS {
+            assert_eq!(queue.push(3, &counters), Delivery::Dropped);
+        }
+
+        // Reading resets the run of drops.
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(queue.push(4, &counters), Delivery::Queued);
+        for _ in 1..MAX_CONSECUTIVE_DROPS {
+            assert_eq!(queue.push(5, &counters), Delivery::Dropped);
+        }
+        assert_eq!(queue.push(6, &counters), Delivery::Disconnect);
+
+        assert_eq!(queue.dropped(), 2 * MAX_CONSECUTIVE_DROPS as u64 - 1);
+        assert_eq!(counters.dropped_messages(), queue.dropped());
+        assert_eq!(counters.lagging_disconnects(), 1);
+    }
+
+    #[test]
+    fn disconnects_clients_that_are_gone_without_counting_them_as_lagging() {
+        let counters = FanoutCounters::default();
+        let (mut queue, receiver) = ClientQueue::new(2);
+        drop(receiver);
+
+        assert_eq!(queue.push(1, &counters), Delivery::Disconnect);
+        assert_eq!(counters.dropped_messages(), 0);
+        assert_eq!(counters.lagging_disconnects(), 0);
+    }
+}
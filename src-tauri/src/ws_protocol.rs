@@ -0,0 +1,418 @@
+use scrybe_core::{
+    metrics::AudioMetricsState,
+    segments::GateEvaluationTelemetryEntry,
+    whisper::{WhisperParams, WhisperSegment},
+};
+use serde::{Deserialize, Serialize};
+use specta::TypeCollection;
+
+use crate::{
+    json_schema,
+    types::{AppState, OverlayConfig},
+    InternalState,
+};
+
+/// Bumped whenever a message changes in a way older clients can't handle.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest protocol version the server still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// A message sent by a client. When `id` is set, the reply carries the same
+/// id so clients can match replies to requests.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ClientEnvelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub request: ClientRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientRequest {
+    /// Should be the first message on a connection. Clients that never send
+    /// it are assumed to speak the current version.
+    Hello {
+        protocol_version: u32,
+        /// Shows up in the logs, e.g. "my-overlay/1.2".
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client: Option<String>,
+    },
+    GetAppState,
+    GetInternalState,
+    /// Replied to with `recent_segments`.
+    GetRecentSegments,
+    StartTranscribe,
+    StopTranscribe,
+    /// Selects a capture device by id; an empty id selects the system
+    /// default. A running transcription keeps its device until restarted.
+    SetDevice {
+        device_id: String,
+    },
+    /// Switches to a model preset that has already been downloaded.
+    SetModelPreset {
+        preset_id: String,
+    },
+    SetWhisperParams {
+        params: WhisperParams,
+    },
+    SetOverlayConfig {
+        config: OverlayConfig,
+    },
+    /// Shows text on the overlay in place of captions, for a minute unless
+    /// `duration_ms` says otherwise.
+    ShowTestText {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+    },
+    ClearTestText,
+    /// Adds topics to the ones the client receives updates for.
+    Subscribe {
+        topics: Vec<Topic>,
+    },
+    Unsubscribe {
+        topics: Vec<Topic>,
+    },
+}
+
+/// A kind of unprompted update. Replies to a client's own requests are
+/// always sent, whatever it is subscribed to.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, specta::Type,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    /// `segment_update` messages. There is a single stream for now; per
+    /// stream topics will join these once there are several.
+    Segments,
+    AppState,
+    InternalState,
+    /// `audio_metrics` messages, sent after every transcription step.
+    AudioMetrics,
+    /// `gate_evaluation` messages, one per segment emission decision.
+    GateTelemetry,
+}
+
+impl Topic {
+    /// What a new client is subscribed to, matching what every client
+    /// received before subscriptions existed.
+    pub const DEFAULT: [Topic; 3] = [Topic::Segments, Topic::AppState, Topic::InternalState];
+
+    /// Parses a comma-separated list of topic names, such as
+    /// `segments,audio_metrics`, for clients that pick topics in a URL.
+    pub fn parse_list(list: &str) -> Result<Vec<Topic>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                serde_json::from_value(serde_json::Value::String(name.to_owned()))
+                    .map_err(|_| format!("unknown topic {}", name))
+            })
+            .collect()
+    }
+}
+
+/// A message sent by the server, either a reply carrying the request's id
+/// or an unprompted update without one.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ServerEnvelope {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Hello {
+        protocol_version: u32,
+        min_protocol_version: u32,
+        server_version: String,
+    },
+    /// Sent in reply to a command that was carried out. The state it
+    /// changed is broadcast to every client as usual.
+    Ack,
+    /// Sent in reply to `get_app_state` and whenever the settings change.
+    AppState {
+        state: Box<AppState>,
+    },
+    /// Sent in reply to `get_internal_state` and whenever it changes.
+    InternalState {
+        state: Box<InternalState>,
+    },
+    /// The segment being transcribed, sent each time its text changes.
+    SegmentUpdate {
+        segment: WhisperSegment,
+    },
+    /// The last few finished segments, oldest first, followed by the one
+    /// being transcribed. Sent when a client connects and in reply to
+    /// `get_recent_segments`.
+    RecentSegments {
+        segments: Vec<WhisperSegment>,
+    },
+    AudioMetrics {
+        metrics: Box<AudioMetricsState>,
+    },
+    GateEvaluation {
+        entry: Box<GateEvaluationTelemetryEntry>,
+    },
+    /// Sent in reply to `subscribe` and `unsubscribe` with the topics the
+    /// client now receives.
+    Subscriptions {
+        topics: Vec<Topic>,
+    },
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The message isn't JSON or doesn't match the protocol.
+    InvalidMessage,
+    UnknownType,
+    /// The command was understood but couldn't be carried out, e.g.
+    /// starting transcription before a model has loaded.
+    CommandFailed,
+    /// The server closes the connection after sending this.
+    UnsupportedVersion,
+}
+
+impl ServerMessage {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        ServerMessage::Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The topic a client must be subscribed to for this message to be
+    /// broadcast to it. Messages without one go to every client.
+    pub fn topic(&self) -> Option<Topic> {
+        match self {
+            ServerMessage::AppState { .. } => Some(Topic::AppState),
+            ServerMessage::InternalState { .. } => Some(Topic::InternalState),
+            ServerMessage::SegmentUpdate { .. } | ServerMessage::RecentSegments { .. } => {
+                Some(Topic::Segments)
+            }
+            ServerMessage::AudioMetrics { .. } => Some(Topic::AudioMetrics),
+            ServerMessage::GateEvaluation { .. } => Some(Topic::GateTelemetry),
+            ServerMessage::Hello { .. }
+            | ServerMessage::Ack
+            | ServerMessage::Subscriptions { .. }
+            | ServerMessage::Error { .. } => None,
+        }
+    }
+}
+
+/// Parses a client message. A message that can't be parsed yields the error
+/// reply instead, echoing the message's id when one could be read.
+pub fn parse_client_message(text: &str) -> Result<ClientEnvelope, ServerEnvelope> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|err| ServerEnvelope {
+        id: None,
+        message: ServerMessage::error(ErrorCode::InvalidMessage, err.to_string()),
+    })?;
+    let id = value
+        .get("id")
+        .and_then(|id| id.as_str())
+        .map(str::to_owned);
+    let unknown_type = value
+        .get("type")
+        .and_then(|kind| kind.as_str())
+        .map(|kind| format!("unknown variant `{}`", kind));
+
+    serde_json::from_value(value).map_err(|err| {
+        let message = err.to_string();
+        // Unknown topics and the like are reported the same way as unknown
+        // types, so the type has to match too.
+        let code = if unknown_type.is_some_and(|unknown| message.starts_with(&unknown)) {
+            ErrorCode::UnknownType
+        } else {
+            ErrorCode::InvalidMessage
+        };
+        ServerEnvelope {
+            id,
+            message: ServerMessage::error(code, message),
+        }
+    })
+}
+
+/// Checks a client's `hello`, returning the error to send before closing the
+/// connection when the server can't speak its version.
+pub fn check_protocol_version(protocol_version: u32) -> Result<(), ServerMessage> {
+    if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) {
+        return Ok(());
+    }
+    Err(ServerMessage::error(
+        ErrorCode::UnsupportedVersion,
+        format!(
+            "protocol version {} is not supported, the server speaks versions {} to {}",
+            protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        ),
+    ))
+}
+
+/// JSON Schema for both directions of the protocol, for overlay authors
+/// who don't use the TypeScript bindings.
+pub fn json_schema() -> serde_json::Value {
+    let mut types = TypeCollection::default();
+    types
+        .register::<ClientEnvelope>()
+        .register::<ServerEnvelope>();
+    let mut schema = json_schema::export(
+        "Scrybe WebSocket protocol",
+        &types,
+        &["ClientEnvelope", "ServerEnvelope"],
+    );
+    schema["x-protocol-version"] = PROTOCOL_VERSION.into();
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error_reply(text: &str) -> serde_json::Value {
+        let reply = parse_client_message(text).expect_err("message should be rejected");
+        serde_json::to_value(reply).unwrap()
+    }
+
+    #[test]
+    fn parses_requests_with_and_without_ids() {
+        let hello = parse_client_message(
+            r#"{"type":"hello","id":"1","protocol_version":1,"client":"test"}"#,
+        )
+        .unwrap();
+        let get = parse_client_message(r#"{"type":"get_app_state"}"#).unwrap();
+
+        assert_eq!(hello.id.as_deref(), Some("1"));
+        assert!(matches!(
+            hello.request,
+            ClientRequest::Hello {
+                protocol_version: 1,
+                client: Some(_)
+            }
+        ));
+        assert_eq!(get.id, None);
+        assert!(matches!(get.request, ClientRequest::GetAppState));
+    }
+
+    #[test]
+    fn parses_commands() {
+        let show = parse_client_message(r#"{"type":"show_test_text","text":"Testing"}"#).unwrap();
+        let preset =
+            parse_client_message(r#"{"type":"set_model_preset","preset_id":"base","id":"2"}"#)
+                .unwrap();
+
+        assert!(matches!(
+            show.request,
+            ClientRequest::ShowTestText {
+                duration_ms: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            preset.request,
+            ClientRequest::SetModelPreset { preset_id } if preset_id == "base"
+        ));
+        assert_eq!(
+            error_reply(r#"{"type":"set_device"}"#)["code"],
+            "invalid_message"
+        );
+    }
+
+    #[test]
+    fn parses_subscriptions_and_rejects_unknown_topics() {
+        let subscribe =
+            parse_client_message(r#"{"type":"subscribe","topics":["audio_metrics"]}"#).unwrap();
+
+        assert!(matches!(
+            subscribe.request,
+            ClientRequest::Subscribe { topics } if topics == [Topic::AudioMetrics]
+        ));
+        assert_eq!(
+            error_reply(r#"{"type":"subscribe","topics":["weather"]}"#)["code"],
+            "invalid_message"
+        );
+        assert_eq!(
+            ServerMessage::SegmentUpdate {
+                segment: WhisperSegment::default()
+            }
+            .topic(),
+            Some(Topic::Segments)
+        );
+        assert_eq!(ServerMessage::Ack.topic(), None);
+
+        assert_eq!(
+            Topic::parse_list("segments, audio_metrics,"),
+            Ok(vec![Topic::Segments, Topic::AudioMetrics])
+        );
+        assert_eq!(
+            Topic::parse_list("segments,weather"),
+            Err("unknown topic weather".to_owned())
+        );
+    }
+
+    #[test]
+    fn replies_to_bad_messages_with_structured_errors() {
+        assert_eq!(
+            error_reply(r#"{"type":"get_everything","id":"7"}"#)["code"],
+            "unknown_type"
+        );
+        assert_eq!(
+            error_reply(r#"{"type":"get_everything","id":"7"}"#)["id"],
+            "7"
+        );
+        assert_eq!(error_reply(r#"{"id":"8"}"#)["code"], "invalid_message");
+        assert_eq!(error_reply("not json")["code"], "invalid_message");
+        assert_eq!(error_reply("not json").get("id"), None);
+    }
+
+    #[test]
+    fn serializes_server_messages_flat_with_the_echoed_id() {
+        let reply = ServerEnvelope {
+            id: Some("3".to_owned()),
+            message: ServerMessage::error(ErrorCode::UnsupportedVersion, "too new"),
+        };
+
+        assert_eq!(
+            serde_json::to_value(reply).unwrap(),
+            json!({
+                "id": "3",
+                "type": "error",
+                "code": "unsupported_version",
+                "message": "too new",
+            })
+        );
+        assert!(check_protocol_version(PROTOCOL_VERSION).is_ok());
+        assert!(check_protocol_version(PROTOCOL_VERSION + 1).is_err());
+    }
+
+    #[test]
+    fn exports_a_schema_for_both_directions() {
+        let schema = json_schema();
+        let defs = &schema["$defs"];
+
+        assert_eq!(
+            schema["oneOf"][0],
+            json!({ "$ref": "#/$defs/ClientEnvelope" })
+        );
+        assert!(defs["AppState"].is_object());
+        assert!(defs["WhisperSegment"].is_object());
+        let segment_update = defs["ServerEnvelope"]["allOf"][1]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|variant| variant["properties"]["type"]["const"] == "segment_update")
+            .expect("segment_update is described");
+        assert_eq!(
+            segment_update["properties"]["segment"],
+            json!({ "$ref": "#/$defs/WhisperSegment" })
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use anyhow::{anyhow, bail, Context};
+use scrybe_core::audio::{self, WHISPER_SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::future::Future;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tracing::{debug, warn};
+
+/// The Wyoming protocol version events are written with.
+pub const PROTOCOL_VERSION: &str = "1.5.2";
+/// Longest event header line accepted.
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
+/// Longest data or payload section accepted.
+const MAX_SECTION_BYTES: usize = 8 * 1024 * 1024;
+/// Audio past this much in one utterance is dropped.
+const MAX_UTTERANCE_SECS: usize = 300;
+
+/// The Wyoming speech-to-text server Home Assistant and other voice
+/// assistants connect to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+#[serde(default)]
+pub struct WyomingSettings {
+    pub enabled: bool,
+    pub bind_address: String,
+    pub port: u16,
+}
+
+impl Default for WyomingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_owned(),
+            port: 10300,
+        }
+    }
+}
+
+/// One Wyoming event: a JSON header line, optional JSON data, and an
+/// optional binary payload such as PCM audio.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Event {
+    pub event_type: String,
+    pub data: Map<String, Value>,
+    pub payload: Vec<u8>,
+}
+
+impl Event {
+    pub fn new(event_type: &str, data: Value) -> Self {
+        Event {
+            event_type: event_type.to_owned(),
+            data: match data {
+                Value::Object(data) => data,
+                _ => Map::new(),
+            },
+            payload: Vec::new(),
+        }
+    }
+
+    fn str(&self, key: &str) -> Option<&str> {
+        self.data.get(key).and_then(Value::as_str)
+    }
+
+    fn u64(&self, key: &str) -> Option<u64> {
+        self.data.get(key).and_then(Value::as_u64)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Map<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+/// Reads the next event, or `None` once the client has disconnected.
+pub async fn read_event<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<Event>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = (&mut *reader)
+            .take(MAX_HEADER_BYTES)
+            .read_line(&mut line)
+            .await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if !line.ends_with('\n') && read as u64 == MAX_HEADER_BYTES {
+            bail!("event header is longer than {} bytes", MAX_HEADER_BYTES);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let header: Header = serde_json::from_str(&line).context("invalid event header")?;
+    let mut data = header.data.unwrap_or_default();
+    if let Some(length) = header.data_length.filter(|length| *length > 0) {
+        let section = read_section(reader, length).await?;
+        let extra: Map<String, Value> =
+            serde_json::from_slice(&section).context("invalid event data")?;
+        data.extend(extra);
+    }
+    let payload = match header.payload_length {
+        Some(length) if length > 0 => read_section(reader, length).await?,
+        _ => Vec::new(),
+    };
+
+    Ok(Some(Event {
+        event_type: header.event_type,
+        data,
+        payload,
+    }))
+}
+
+async fn read_section<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    length: usize,
+) -> anyhow::Result<Vec<u8>> {
+    if length > MAX_SECTION_BYTES {
+        bail!("event section of {} bytes is too large", length);
+    }
+    let mut section = vec![0; length];
+    reader.read_exact(&mut section).await?;
+    Ok(section)
+}
+
+/// Writes an event the way the reference implementation does, with the data
+/// after the header rather than inside it.
+pub async fn write_event<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    event: &Event,
+) -> anyhow::Result<()> {
+    let data = if event.data.is_empty() {
+        Vec::new()
+    } else {
+        serde_json::to_vec(&event.data)?
+    };
+    let header = Header {
+        event_type: event.event_type.clone(),
+        data: None,
+        data_length: (!data.is_empty()).then_some(data.len()),
+        payload_length: (!event.payload.is_empty()).then_some(event.payload.len()),
+        version: Some(PROTOCOL_VERSION.to_owned()),
+    };
+    let mut message = serde_json::to_vec(&header)?;
+    message.push(b'\n');
+    message.extend_from_slice(&data);
+    message.extend_from_slice(&event.payload);
+    writer.write_all(&message).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Whisper's language codes, for models that aren't English-only.
+const WHISPER_LANGUAGES: &[&str] = &[
+    "en", "zh", "de", "es", "ru", "ko", "fr", "ja", "pt", "tr", "pl", "ca", "nl", "ar", "sv", "it",
+    "id", "hi", "fi", "vi", "he", "uk", "el", "ms", "cs", "ro", "da", "hu", "ta", "no", "th", "ur",
+    "hr", "bg", "lt", "la", "mi", "ml", "cy", "sk", "te", "fa", "lv", "bn", "sr", "az", "sl", "kn",
+    "et", "mk", "br", "eu", "is", "hy", "ne", "mn", "bs", "kk", "sq", "sw", "gl", "mr", "pa", "si",
+    "km", "sn", "yo", "so", "af", "oc", "ka", "be", "tg", "sd", "gu", "am", "yi", "lo", "uz", "fo",
+    "ht", "ps", "tk", "nn", "mt", "sa", "lb", "my", "bo", "tl", "mg", "as", "tt", "haw", "ln",
+    "ha", "ba", "jw", "su", "yue",
+];
+
+/// The `info` reply to `describe`, offering the loaded model as the only
+/// speech-to-text model. Without one, the service reports itself as not
+/// installed so clients don't send it audio.
+pub fn info_event(app_version: &str, model_name: Option<&str>) -> Event {
+    let attribution = json!({ "name": "scrybe", "url": "https://github.com/synthlabs/scrybe" });
+    let models: Vec<Value> = model_name
+        .map(|model_name| {
+            let languages: Vec<&str> = if model_name.contains(".en") {
+                vec!["en"]
+            } else {
+                WHISPER_LANGUAGES.to_vec()
+            };
+            json!({
+                "name": model_name,
+                "description": model_name,
+                "attribution": attribution,
+                "installed": true,
+                "version": null,
+                "languages": languages,
+            })
+        })
+        .into_iter()
+        .collect();
+    Event::new(
+        "info",
+        json!({
+            "asr": [{
+                "name": "scrybe",
+                "description": "Local Whisper transcription from scrybe",
+                "attribution": attribution,
+                "installed": !models.is_empty(),
+                "version": app_version,
+                "supports_transcript_streaming": false,
+                "models": models,
+            }],
+        }),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AudioFormat {
+    rate: u32,
+    width: u16,
+    channels: u16,
+}
+
+impl AudioFormat {
+    fn from_event(event: &Event) -> Option<Self> {
+        Some(AudioFormat {
+            rate: event.u64("rate")?.try_into().ok()?,
+            width: event.u64("width")?.try_into().ok()?,
+            channels: event.u64("channels")?.try_into().ok()?,
+        })
+        .filter(|format| format.rate > 0 && format.channels > 0)
+    }
+}
+
+/// Converts little-endian signed PCM to mono samples. The payload must hold
+/// whole frames, one sample for every channel.
+fn pcm_to_mono(payload: &[u8], format: AudioFormat) -> Result<Vec<f32>, String> {
+    if !matches!(format.width, 2 | 4) {
+        return Err(format!("unsupported sample width {}", format.width));
+    }
+    let frame_bytes = format.width as usize * format.channels as usize;
+    if !payload.len().is_multiple_of(frame_bytes) {
+        return Err(format!(
+            "audio-chunk of {} bytes isn't a whole number of {}-byte frames",
+            payload.len(),
+            frame_bytes
+        ));
+    }
+    let samples: Vec<f32> = if format.width == 2 {
+        payload
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+            .collect()
+    } else {
+        payload
+            .chunks_exact(4)
+            .map(|bytes| {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                    / i32::MAX as f32
+            })
+            .collect()
+    };
+    Ok(audio::mono_from_interleaved(&samples, format.channels))
+}
+
+/// What to do after an event.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Reply(Event),
+    Transcribe {
+        samples: Vec<f32>,
+        rate: u32,
+        language: Option<String>,
+    },
+    Nothing,
+}
+
+/// One client's state between events: the language it asked for and the
+/// utterance being streamed in.
+#[derive(Debug, Default)]
+struct Session {
+    language: Option<String>,
+    format: Option<AudioFormat>,
+    samples: Vec<f32>,
+}
+
+impl Session {
+    fn handle(&mut self, event: Event, info: &Event) -> Action {
+        match event.event_type.as_str() {
+            "describe" => Action::Reply(info.clone()),
+            "ping" => Action::Reply(Event::new("pong", Value::Object(event.data))),
+            "transcribe" => {
+                self.language = event
+                    .str("language")
+                    .filter(|language| !language.is_empty())
+                    .map(str::to_owned);
+                Action::Nothing
+            }
+            "audio-start" => {
+                self.format = AudioFormat::from_event(&event);
+                self.samples.clear();
+                Action::Nothing
+            }
+            "audio-chunk" => self.push_chunk(&event),
+            "audio-stop" => {
+                let rate = self
+                    .format
+                    .take()
+                    .map_or(WHISPER_SAMPLE_RATE, |format| format.rate);
+                Action::Transcribe {
+                    samples: std::mem::take(&mut self.samples),
+                    rate,
+                    language: self.language.clone(),
+                }
+            }
+            other => {
+                debug!("ignoring wyoming event {}", other);
+                Action::Nothing
+            }
+        }
+    }
+
+    fn push_chunk(&mut self, event: &Event) -> Action {
+        // Chunks carry their own format; one sent without `audio-start`
+        // starts the utterance.
+        let Some(format) = AudioFormat::from_event(event).or(self.format) else {
+            return error_event("audio-chunk is missing a valid rate, width or channels");
+        };
+        if *self.format.get_or_insert(format) != format {
+            return error_event("audio-chunk format differs from audio-start");
+        }
+        if self.samples.len() >= format.rate as usize * MAX_UTTERANCE_SECS {
+            warn!("dropping wyoming audio past {}s", MAX_UTTERANCE_SECS);
+            return Action::Nothing;
+        }
+        match pcm_to_mono(&event.payload, format) {
+            Ok(samples) => {
+                self.samples.extend(samples);
+                Action::Nothing
+            }
+            Err(reason) => error_event(&reason),
+        }
+    }
+}
+
+fn error_event(text: &str) -> Action {
+    Action::Reply(Event::new("error", json!({ "text": text })))
+}
+
+/// Answers one client's events until it disconnects. `transcribe` is handed
+/// each utterance as mono samples at the rate the client sent, with the
+/// language it asked for, and returns the text or why it couldn't.
+pub async fn serve_client<S, T, F>(stream: S, info: &Event, transcribe: T) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: Fn(Vec<f32>, u32, Option<String>) -> F,
+    F: Future<Output = Result<String, String>>,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut session = Session::default();
+    while let Some(event) = read_event(&mut reader).await? {
+        debug!("wyoming event {}", event.event_type);
+        let reply = match session.handle(event, info) {
+            Action::Reply(event) => event,
+            Action::Transcribe {
+                samples,
+                rate,
+                language,
+            } => match transcribe(samples, rate, language.clone()).await {
+                Ok(text) => Event::new("transcript", json!({ "text": text, "language": language })),
+                Err(reason) => Event::new(
+                    "error",
+                    json!({ "text": reason, "code": "transcription-failed" }),
+                ),
+            },
+            Action::Nothing => continue,
+        };
+        write_event(&mut writer, &reply)
+            .await
+            .map_err(|err| anyhow!("unable to reply to wyoming client: {:#}", err))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trips_events_with_data_and_payload() {
+        let mut event = Event::new("audio-chunk", json!({ "rate": 16000 }));
+        event.payload = vec![1, 2, 3];
+        let mut bytes = Vec::new();
+        write_event(&mut bytes, &event).await.unwrap();
+
+        let header = std::str::from_utf8(&bytes).unwrap().lines().next().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(header).unwrap()["data_length"],
+            14
+        );
+        let mut reader = BufReader::new(&bytes[..]);
+        assert_eq!(read_event(&mut reader).await.unwrap(), Some(event));
+        assert_eq!(read_event(&mut reader).await.unwrap(), None);
+
+        // Data may also be sent inside the header.
+        let inline = b"{\"type\":\"transcribe\",\"data\":{\"language\":\"en\"}}\n";
+        let event = read_event(&mut BufReader::new(&inline[..]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.str("language"), Some("en"));
+    }
+
+    #[tokio::test]
+    async fn transcribes_streamed_audio_for_a_local_client() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let info = info_event("1.0.0", Some("ggml-base.en.bin"));
+        let server = tokio::spawn(async move {
+            serve_client(server, &info, |samples, rate, language| async move {
+                Ok(format!(
+                    "{} samples at {}Hz in {}",
+                    samples.len(),
+                    rate,
+                    language.unwrap_or_default()
+                ))
+            })
+            .await
+        });
+
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut reader = BufReader::new(reader);
+        let format = json!({ "rate": 16000, "width": 2, "channels": 1 });
+        let mut chunk = Event::new("audio-chunk", format.clone());
+        chunk.payload = vec![0; 3200];
+        for event in [
+            Event::new("describe", json!({})),
+            Event::new("transcribe", json!({ "language": "en" })),
+            Event::new("audio-start", format),
+            chunk.clone(),
+            chunk,
+            Event::new("audio-stop", json!({})),
+        ] {
+            write_event(&mut writer, &event).await.unwrap();
+        }
+
+        let info = read_event(&mut reader).await.unwrap().unwrap();
+        assert_eq!(info.event_type, "info");
+        assert_eq!(info.data["asr"][0]["models"][0]["languages"], json!(["en"]));
+
+        let transcript = read_event(&mut reader).await.unwrap().unwrap();
+        assert_eq!(transcript.event_type, "transcript");
+        assert_eq!(
+            transcript.str("text"),
+            Some("3200 samples at 16000Hz in en")
+        );
+
+        writer.shutdown().await.unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn converts_pcm_to_mono_samples() {
+        let format = AudioFormat {
+            rate: 16000,
+            width: 2,
+            channels: 2,
+        };
+        let payload = [i16::MAX, 0, i16::MIN + 1, 0]
+            .map(i16::to_le_bytes)
+            .concat();
+
+        assert_eq!(pcm_to_mono(&payload, format), Ok(vec![0.5, -0.5]));
+        assert!(pcm_to_mono(&payload, AudioFormat { width: 3, ..format }).is_err());
+        assert!(pcm_to_mono(&payload[..6], format).is_err());
+    }
+
+    #[test]
+    fn rejects_chunks_that_dont_match_audio_start() {
+        let info = info_event("1.0.0", Some("ggml-base.bin"));
+        let mut session = Session::default();
+        let format = json!({ "rate": 22050, "width": 2, "channels": 1 });
+        session.handle(Event::new("audio-start", format.clone()), &info);
+
+        let mut chunk = Event::new("audio-chunk", format);
+        chunk.payload = vec![0; 4];
+        assert_eq!(session.handle(chunk.clone(), &info), Action::Nothing);
+        chunk.data.insert("rate".to_owned(), json!(16000));
+        assert!(matches!(
+            session.handle(chunk, &info),
+            Action::Reply(event) if event.event_type == "error"
+        ));
+
+        assert_eq!(
+            session.handle(Event::new("audio-stop", json!({})), &info),
+            Action::Transcribe {
+                samples: vec![0.0; 2],
+                rate: 22050,
+                language: None,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_no_model_as_not_installed() {
+        let info = info_event("1.0.0", None);
+
+        assert_eq!(info.data["asr"][0]["installed"], false);
+        assert_eq!(info.data["asr"][0]["models"], json!([]));
+        assert_eq!(
+            info_event("1.0.0", Some("ggml-base.bin")).data["asr"][0]["installed"],
+            true
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Context;
+use scrybe_core::audio::{self, WHISPER_SAMPLE_RATE};
+use std::path::Path;
+use tauri::{async_runtime::JoinHandle, AppHandle, Manager};
+use tauri_svelte_synced_store::StateSyncer;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    batch_transcription,
+    transcription_api::{self, TranscriptionQueue},
+    types::AppState,
+    wyoming::{self, WyomingSettings},
+};
+
+/// The running Wyoming listener. Like the local server, clients it has
+/// accepted keep going when it is replaced.
+#[derive(Default)]
+pub struct WyomingServer {
+    task: tokio::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Stops the running listener, if any, and starts one when `settings` enable
+/// it, reporting where it listens in `InternalState::wyoming_uri`.
+pub fn restart(app: &AppHandle, settings: WyomingSettings) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let wyoming_server = app.state::<WyomingServer>();
+        let mut task = wyoming_server.task.lock().await;
+        if let Some(running) = task.take() {
+            running.abort();
+            let _ = running.await;
+        }
+
+        let wyoming_uri = if settings.enabled {
+            match bind(&settings).await {
+                Ok(listener) => {
+                    let uri = listener
+                        .local_addr()
+                        .map(|addr| format!("tcp://{}", addr))
+                        .ok();
+                    info!("wyoming server listening on {:?}", uri);
+                    *task = Some(tauri::async_runtime::spawn(accept(app.clone(), listener)));
+                    uri
+                }
+                Err(err) => {
+                    error!("failed to start wyoming server: {:#}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        crate::update_internal_state(&app, |state| state.wyoming_uri = wyoming_uri);
+    });
+}
+
+async fn bind(settings: &WyomingSettings) -> anyhow::Result<TcpListener> {
+    let ip: std::net::IpAddr = settings
+        .bind_address
+        .parse()
+        .with_context(|| format!("invalid bind address {:?}", settings.bind_address))?;
+    TcpListener::bind((ip, settings.port))
+        .await
+        .with_context(|| format!("unable to bind {}:{}", ip, settings.port))
+}
+
+async fn accept(app: AppHandle, listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                debug!("wyoming client {} connected", peer);
+                tauri::async_runtime::spawn(serve(app.clone(), stream, peer));
+            }
+            Err(err) => warn!("unable to accept wyoming client: {}", err),
+        }
+    }
+}
+
+async fn serve(app: AppHandle, stream: TcpStream, peer: std::net::SocketAddr) {
+    let model_path = app
+        .state::<StateSyncer>()
+        .snapshot::<AppState>("app_state")
+        .model_path;
+    let model_name = (!model_path.is_empty()).then(|| {
+        Path::new(&model_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| model_path.clone())
+    });
+    let info = wyoming::info_event(
+        &app.package_info().version.to_string(),
+        model_name.as_deref(),
+    );
+
+    let result = wyoming::serve_client(stream, &info, |samples, rate, language| {
+        transcribe(app.clone(), samples, rate, language)
+    })
+    .await;
+    match result {
+        Ok(()) => debug!("wyoming client {} disconnected", peer),
+        Err(err) => warn!("wyoming client {} failed: {:#}", peer, err),
+    }
+}
+
+/// Transcribes an utterance with the loaded model, queued behind uploads to
+/// the transcription API.
+async fn transcribe(
+    app: AppHandle,
+    samples: Vec<f32>,
+    rate: u32,
+    language: Option<String>,
+) -> Result<String, String> {
+    let params = batch_transcription::batch_params(
+        &app.state::<StateSyncer>()
+            .snapshot::<AppState>("app_state")
+            .whisper_params,
+        language.as_deref(),
+    );
+    let duration_ms = samples.len() as u64 * 1000 / rate as u64;
+
+    let queue = app.state::<TranscriptionQueue>();
+    let _turn = queue.turn().await;
+    info!("transcribing {}ms of wyoming audio", duration_ms);
+
+    let app_ref = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let samples = if rate == WHISPER_SAMPLE_RATE {
+            samples
+        } else {
+            audio::resample_audio(&samples, rate, WHISPER_SAMPLE_RATE, 1)
+        };
+        transcription_api::transcribe_samples(&app_ref, &samples, &params, None, duration_ms)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map(|transcript| transcript.text())
+    .map_err(|err| err.message)
+}
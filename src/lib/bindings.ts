@@ -35,6 +35,17 @@ export const commands = {
     async listModelPresets(): Promise<ModelPreset[]> {
         return await TAURI_INVOKE("list_model_presets");
     },
+    async reloadModelCatalog(): Promise<Result<ModelCatalogReport, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("reload_model_catalog"),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
     async downloadModelPreset(
         presetId: string,
     ): Promise<Result<string, string>> {
@@ -48,6 +59,137 @@ export const commands = {
             else return { status: "error", error: e as any };
         }
     },
+    async cancelModelDownload(presetId: string): Promise<boolean> {
+        return await TAURI_INVOKE("cancel_model_download", { presetId });
+    },
+    async runModelCalibration(
+        downloadMissing: boolean,
+    ): Promise<Result<CalibrationReport, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("run_model_calibration", {
+                    downloadMissing,
+                }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async getModelCalibration(): Promise<CalibrationReport | null> {
+        return await TAURI_INVOKE("get_model_calibration");
+    },
+    async listLocalModels(): Promise<Result<LocalModel[], string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("list_local_models"),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async verifyLocalModel(path: string): Promise<Result<LocalModel, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("verify_local_model", { path }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async deleteLocalModel(path: string): Promise<Result<null, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("delete_local_model", { path }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async revealLocalModel(path: string): Promise<Result<null, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("reveal_local_model", { path }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async importLocalModel(path: string): Promise<Result<LocalModel, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("import_local_model", { path }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async listSessions(): Promise<Result<SessionSummary[], string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("list_sessions"),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async loadSession(id: string): Promise<Result<Session, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("load_session", { id }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    /**
+     * Renders a recorded session as subtitles for the frontend to save.
+     */
+    async exportSession(
+        id: string,
+        format: SubtitleFormat,
+        options: CueOptions | null,
+    ): Promise<Result<string, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("export_session", {
+                    id,
+                    format,
+                    options,
+                }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
+    async deleteSession(id: string): Promise<Result<null, string>> {
+        try {
+            return {
+                status: "ok",
+                data: await TAURI_INVOKE("delete_session", { id }),
+            };
+        } catch (e) {
+            if (e instanceof Error) throw e;
+            else return { status: "error", error: e as any };
+        }
+    },
     async emitState(name: string): Promise<boolean> {
         return await TAURI_INVOKE("emit_state", { name });
     },
@@ -62,16 +204,26 @@ export const commands = {
 
 /** user-defined types **/
 
+export type AdaptationDirection = "downgrade" | "upgrade";
 export type AdvancedSettings = Record<string, never>;
 export type AppState = {
     current_device: AudioDevice;
     audio_format: AudioFormat;
     model_path: string;
+    /**
+     * Extra folders scanned for model files by the model library.
+     */
+    model_folders: string[];
+    model_adaptation: ModelAdaptationMode;
     audio_segment_size: number;
     overlay_config: OverlayConfig;
     home_right_rail: HomeRightRailSettings;
     whisper_params: WhisperParams;
     advanced_settings: AdvancedSettings;
+    session_history: SessionHistorySettings;
+    caption_file: CaptionFileSettings;
+    server: ServerSettings;
+    wyoming: WyomingSettings;
 };
 export type AudioDevice = { name: string; id: string };
 export type AudioFormat = { name: string; id: string };
@@ -84,10 +236,185 @@ export type AudioMetricsState = {
     inference_p90_ms: number;
     inference_p95_ms: number;
     inference_p99_ms: number;
+    /**
+     * Inference percentiles over roughly the last one and five minutes.
+     */
+    inference_p50_1m_ms: number;
+    inference_p95_1m_ms: number;
+    inference_p50_5m_ms: number;
+    inference_p95_5m_ms: number;
+    inference_sum_ms: number;
+    /**
+     * Inference runs at or below each of `INFERENCE_HISTOGRAM_BOUNDS_MS`.
+     */
+    inference_histogram: number[];
+    inference_cancelled_count: number;
     gate_total_evaluations: number;
     gate_total_emits: number;
     gate_emit_rate: number;
+    /**
+     * Transcription loop passes per second, smoothed.
+     */
+    effective_step_rate_hz: number;
+    /**
+     * Captured audio still waiting to be processed when the last step ended.
+     */
+    step_lag_ms: number;
+    /**
+     * Delay the scheduler chose before the next step.
+     */
+    next_step_delay_ms: number;
+    /**
+     * Time from capturing the oldest speech in a caption to emitting it.
+     */
+    last_speech_to_emit_ms: number;
+    speech_to_emit_p50_ms: number;
+    speech_to_emit_p90_ms: number;
+    speech_to_emit_p99_ms: number;
+    /**
+     * Captions held back by `PendingDrasticChange` and for how long.
+     */
+    gate_hold_count: number;
+    last_gate_hold_ms: number;
+    gate_hold_p95_ms: number;
+    /**
+     * Time to queue a segment update for every WebSocket client.
+     */
+    last_ws_fanout_ms: number;
+    ws_fanout_p95_ms: number;
+    system: SystemMetricsState;
+};
+export type CalibrationFingerprint = {
+    app_version: string;
+    accelerated_backend: boolean;
+    logical_cpus: number | null;
+    total_memory_gib: number | null;
+};
+export type CalibrationReport = {
+    fingerprint: CalibrationFingerprint;
+    audio_step_size_ms: number;
+    fixture_duration_ms: number;
+    benchmarks: PresetBenchmark[];
+    selected_preset_id: string | null;
+    calibrated_at_ms: number;
+};
+/**
+ * Writes the live caption to a text file for OBS, vMix and other tools
+ * that can show a file but not host a browser source.
+ */
+export type CaptionFileSettings = {
+    enabled: boolean;
+    path: string;
+    /**
+     * Only the most recent lines are kept.
+     */
+    max_lines: number;
+    max_line_chars: number;
+    /**
+     * Empties the file after this much silence; 0 leaves the last caption up.
+     */
+    clear_after_silence_secs: number;
 };
+/**
+ * Audio from `audio_ms` into a segment on, up to the next anchor, was
+ * captured from `captured_at_ms` on without a gap.
+ */
+export type CaptureAnchor = { audio_ms: number; captured_at_ms: number };
+export type ChecksumStatus =
+    /**
+     * The file hash matches the reference checksum.
+     */
+    | "verified"
+    /**
+     * The file hash differs from the reference checksum.
+     */
+    | "mismatch"
+    /**
+     * A reference checksum exists but the file hasn't been hashed yet.
+     */
+    | "unchecked"
+    /**
+     * There is no reference checksum to compare against.
+     */
+    | "unknown";
+/**
+ * A message sent by a client. When `id` is set, the reply carries the same
+ * id so clients can match replies to requests.
+ */
+export type ClientEnvelope = (
+    /**
+     * Should be the first message on a connection. Clients that never send
+     * it are assumed to speak the current version.
+     */
+    | {
+          type: "hello";
+          protocol_version: number;
+          /**
+           * Shows up in the logs, e.g. "my-overlay/1.2".
+           */
+          client?: string | null;
+      }
+    | { type: "get_app_state" }
+    | { type: "get_internal_state" }
+    /**
+     * Replied to with `recent_segments`.
+     */
+    | { type: "get_recent_segments" }
+    | { type: "start_transcribe" }
+    | { type: "stop_transcribe" }
+    /**
+     * Selects a capture device by id; an empty id selects the system
+     * default. A running transcription keeps its device until restarted.
+     */
+    | { type: "set_device"; device_id: string }
+    /**
+     * Switches to a model preset that has already been downloaded.
+     */
+    | { type: "set_model_preset"; preset_id: string }
+    | { type: "set_whisper_params"; params: WhisperParams }
+    | { type: "set_overlay_config"; config: OverlayConfig }
+    /**
+     * Shows text on the overlay in place of captions, for a minute unless
+     * `duration_ms` says otherwise.
+     */
+    | { type: "show_test_text"; text: string; duration_ms?: number | null }
+    | { type: "clear_test_text" }
+    /**
+     * Adds topics to the ones the client receives updates for.
+     */
+    | { type: "subscribe"; topics: Topic[] }
+    | { type: "unsubscribe"; topics: Topic[] }
+) & { id?: string | null };
+export type CueOptions = {
+    max_line_chars: number;
+    max_lines: number;
+    max_duration_ms: number;
+    /**
+     * Labels cues with "Speaker N", counting the speaker turns whisper
+     * reports when tinydiarize is enabled.
+     */
+    speaker_labels: boolean;
+};
+export type DownloadProgress = {
+    preset_id: string;
+    downloaded_bytes: number;
+    total_bytes: number | null;
+};
+export type ErrorCode =
+    /**
+     * The message isn't JSON or doesn't match the protocol.
+     */
+    | "invalid_message"
+    | "unknown_type"
+    /**
+     * The command was understood but couldn't be carried out, e.g.
+     * starting transcription before a model has loaded.
+     */
+    | "command_failed"
+    /**
+     * The server closes the connection after sending this.
+     */
+    | "unsupported_version";
 export type GateEvaluationTelemetryEntry = {
     sequence: number;
     segment_id: string;
@@ -104,6 +431,11 @@ export type GateEvaluationTelemetryEntry = {
     evaluate_ms: number;
 };
 export type GateTelemetryState = { entries: GateEvaluationTelemetryEntry[] };
+export type GgmlModelInfo = {
+    model_type: string;
+    quantization: string;
+    english_only: boolean;
+};
 export type HomeRightRailSettings = {
     session: boolean;
     audio_metrics: boolean;
@@ -116,15 +448,97 @@ export type InternalState = {
     version: string;
     name: string;
     runtime_dependency: RuntimeDependencyState;
+    model_load: ModelLoadState;
+    model_adaptation: ModelAdaptationState;
     overlay_test: OverlayTestState;
+    /**
+     * Where the local server ended up listening, or `None` when it couldn't
+     * start.
+     */
+    server_url: string | null;
+    /**
+     * Where the Wyoming server listens, or `None` when it is off or
+     * couldn't start.
+     */
+    wyoming_uri: string | null;
+};
+export type LocalModel = {
+    path: string;
+    file_name: string;
+    size_bytes: number;
+    source: LocalModelSource;
+    repo: string | null;
+    preset_id: string | null;
+    ggml: GgmlModelInfo | null;
+    checksum: ChecksumStatus;
+    in_use: boolean;
+};
+export type LocalModelSource =
+    | "hf_cache"
+    | "downloaded"
+    | "imported"
+    | "user_folder";
+export type ModelAdaptation = {
+    direction: AdaptationDirection;
+    from_preset_id: string;
+    to_preset_id: string;
+    reason: string;
+    at_ms: number;
+};
+export type ModelAdaptationMode =
+    | "off"
+    /**
+     * Report a recommended preset in `InternalState` but keep the model.
+     */
+    | "suggest"
+    /**
+     * Switch to the recommended preset when it is already on disk.
+     */
+    | "automatic";
+export type ModelAdaptationState = {
+    /**
+     * Suggested switch that hasn't been applied yet.
+     */
+    recommendation: ModelAdaptation | null;
+    /**
+     * Most recent switch made automatically.
+     */
+    last_switch: ModelAdaptation | null;
+};
+export type ModelCatalogReport = {
+    files: string[];
+    custom_preset_count: number;
+    errors: string[];
 };
+export type ModelLoadState =
+    | { state: "idle" }
+    | { state: "downloading"; bytes: number; total: number | null }
+    | { state: "loading" }
+    | { state: "warming_up" }
+    | { state: "ready" }
+    | { state: "failed"; reason: string };
 export type ModelPreset = {
     id: string;
     label: string;
     description: string;
     repo: string;
     filename: string;
+    /**
+     * Absolute path of a model file on disk, for catalog presets that
+     * aren't downloaded from the Hub. `repo` is empty when this is set.
+     */
+    local_path: string | null;
     size_mb: number;
+    /**
+     * Exact size of the model file, checked after download. When unset, the
+     * size reported by the Hub for the LFS object is used.
+     */
+    size_bytes: number | null;
+    /**
+     * Lowercase hex SHA-256 of the model file, checked after download. When
+     * unset, the checksum reported by the Hub for the LFS object is used.
+     */
+    sha256: string | null;
     resource_rank: number;
     auto_selectable: boolean;
 };
@@ -150,6 +564,19 @@ export type OverlayTestState = {
     text: string;
     expires_at_ms: number | null;
 };
+export type PresetBenchmark = {
+    preset_id: string;
+    /**
+     * Time to load and warm up the model.
+     */
+    load_ms: number;
+    inference_p95_ms: number;
+    /**
+     * Mean inference time divided by the fixture duration.
+     */
+    real_time_factor: number;
+    error: string | null;
+};
 export type RuntimeDependencyState = {
     status: RuntimeDependencyStatus;
     has_nvidia_gpu: boolean;
@@ -166,17 +593,194 @@ export type SegmentSuppressionReason =
     | "Empty"
     | "DuplicateNormalizedText"
     | "PendingDrasticChange";
+/**
+ * A message sent by the server, either a reply carrying the request's id
+ * or an unprompted update without one.
+ */
+export type ServerEnvelope = (
+    | {
+          type: "hello";
+          protocol_version: number;
+          min_protocol_version: number;
+          server_version: string;
+      }
+    /**
+     * Sent in reply to a command that was carried out. The state it
+     * changed is broadcast to every client as usual.
+     */
+    | { type: "ack" }
+    /**
+     * Sent in reply to `get_app_state` and whenever the settings change.
+     */
+    | { type: "app_state"; state: AppState }
+    /**
+     * Sent in reply to `get_internal_state` and whenever it changes.
+     */
+    | { type: "internal_state"; state: InternalState }
+    /**
+     * The segment being transcribed, sent each time its text changes.
+     */
+    | { type: "segment_update"; segment: WhisperSegment }
+    /**
+     * The last few finished segments, oldest first, followed by the one
+     * being transcribed. Sent when a client connects and in reply to
+     * `get_recent_segments`.
+     */
+    | { type: "recent_segments"; segments: WhisperSegment[] }
+    | { type: "audio_metrics"; metrics: AudioMetricsState }
+    | { type: "gate_evaluation"; entry: GateEvaluationTelemetryEntry }
+    /**
+     * Sent in reply to `subscribe` and `unsubscribe` with the topics the
+     * client now receives.
+     */
+    | { type: "subscriptions"; topics: Topic[] }
+    | { type: "error"; code: ErrorCode; message: string }
+) & { id?: string | null };
+/**
+ * Where the local server listens and who it lets in.
+ */
+export type ServerSettings = {
+    /**
+     * `127.0.0.1` keeps the server to this machine; `0.0.0.0` lets another
+     * PC, such as a streaming PC, reach it.
+     */
+    bind_address: string;
+    port: number;
+    /**
+     * Tries the next few ports when `port` is taken.
+     */
+    port_fallback: boolean;
+    /**
+     * Shared secret required on `/ws`, `/app`, `/api` and `/v1`; empty lets
+     * anyone in.
+     */
+    token: string;
+    /**
+     * Origins, such as `http://192.168.1.20:8080`, that pages on other
+     * hosts may call the server from. Empty allows only the pages it serves
+     * itself.
+     */
+    allowed_origins: string[];
+};
+export type Session = {
+    summary: SessionSummary;
+    segments: SessionSegment[];
+};
+export type SessionHistorySettings = {
+    enabled: boolean;
+    /**
+     * Sessions older than this are deleted; 0 keeps them regardless of age.
+     */
+    max_age_days: number;
+    /**
+     * Oldest sessions are deleted until the history fits; 0 means no limit.
+     */
+    max_total_mb: number;
+};
+/**
+ * What a transcription run was started with.
+ */
+export type SessionInfo = {
+    id: string;
+    started_at_ms: number;
+    device: AudioDevice;
+    model_path: string;
+    model_preset_id: string | null;
+    whisper_params: WhisperParams;
+    audio_segment_size: number;
+};
+/**
+ * A caption segment as it stood when it was finalized. Item timestamps are
+ * whisper's, relative to the start of the segment's audio.
+ */
+export type SessionSegment = {
+    segment_id: string;
+    index: number;
+    started_at_ms: number;
+    ended_at_ms: number;
+    items: WhisperText[];
+    /**
+     * When each run of the segment's audio was captured. Silence is trimmed
+     * before audio reaches whisper, so its timestamps skip the pauses these
+     * put back. Empty in sessions recorded before they were kept.
+     */
+    capture_anchors: CaptureAnchor[];
+};
+export type SessionSummary = {
+    info: SessionInfo;
+    /**
+     * Unset while the session is running or if the app exited mid-session.
+     */
+    stopped_at_ms: number | null;
+    segment_count: number;
+    size_bytes: number;
+};
 export type StateUpdate = {
     version: number | null;
     name: string;
     value: string;
 };
-export type WebsocketRequest = { kind: string; data: string };
-export type WebsocketResponse = {
-    kind: string;
-    data: string;
-    is_error: boolean;
+/**
+ * Resource usage of the scrybe process and the machine it runs on, to tell
+ * scrybe's own load apart from OBS or a game competing for the same CPU.
+ */
+export type SubtitleFormat =
+    | "srt"
+    | "webvtt"
+    /**
+     * TTML 1, also accepted where DFXP is asked for.
+     */
+    | "ttml"
+    /**
+     * YouTube's SubViewer format.
+     */
+    | "sbv"
+    /**
+     * Scenarist CEA-608 pop-on captions.
+     */
+    | "scc";
+export type SystemMetricsState = {
+    /**
+     * Share of one core, so a busy process can exceed 100.
+     */
+    process_cpu_percent: number;
+    process_rss_bytes: number;
+    /**
+     * Unavailable on macOS.
+     */
+    process_threads: number | null;
+    /**
+     * Average across all cores.
+     */
+    system_cpu_percent: number;
+    logical_cpus: number;
+    total_memory_bytes: number;
+    available_memory_bytes: number;
+    /**
+     * Fraction of memory in use, from 0 to 1.
+     */
+    memory_pressure: number;
 };
+/**
+ * A kind of unprompted update. Replies to a client's own requests are
+ * always sent, whatever it is subscribed to.
+ */
+export type Topic =
+    /**
+     * `segment_update` messages. There is a single stream for now; per
+     * stream topics will join these once there are several.
+     */
+    | "segments"
+    | "app_state"
+    | "internal_state"
+    /**
+     * `audio_metrics` messages, sent after every transcription step.
+     */
+    | "audio_metrics"
+    /**
+     * `gate_evaluation` messages, one per segment emission decision.
+     */
+    | "gate_telemetry";
 export type WhisperParams = { toggles: WhisperToggles; language: string };
 export type WhisperSegment = {
     id: string;
@@ -199,6 +803,15 @@ export type WhisperToggles = {
     split_on_word: boolean;
     tdrz_enable: boolean;
 };
+/**
+ * The Wyoming speech-to-text server Home Assistant and other voice
+ * assistants connect to.
+ */
+export type WyomingSettings = {
+    enabled: boolean;
+    bind_address: string;
+    port: number;
+};
 
 /** tauri-specta globals **/
 
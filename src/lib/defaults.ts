@@ -21,6 +21,8 @@ export let DefaultAppState: AppState = {
         id: "",
     },
     model_path: "",
+    model_folders: [],
+    model_adaptation: "suggest",
     audio_segment_size: 15,
     overlay_config: {
         canvas: { ...DEFAULT_OVERLAY_CONFIG.canvas },
@@ -46,6 +48,26 @@ export let DefaultAppState: AppState = {
         language: "auto",
     },
     advanced_settings: {},
+    session_history: { enabled: true, max_age_days: 30, max_total_mb: 200 },
+    caption_file: {
+        enabled: false,
+        path: "",
+        max_lines: 2,
+        max_line_chars: 42,
+        clear_after_silence_secs: 0,
+    },
+    server: {
+        bind_address: "127.0.0.1",
+        port: 3030,
+        port_fallback: true,
+        token: "",
+        allowed_origins: [],
+    },
+    wyoming: {
+        enabled: false,
+        bind_address: "127.0.0.1",
+        port: 10300,
+    },
 };
 
 export let DefaultInternalState: InternalState = {
@@ -60,11 +82,15 @@ export let DefaultInternalState: InternalState = {
         reason: "",
         action_url: null,
     },
+    model_load: { state: "idle" },
+    model_adaptation: { recommendation: null, last_switch: null },
     overlay_test: {
         visible: false,
         text: "",
         expires_at_ms: null,
     },
+    server_url: null,
+    wyoming_uri: null,
 };
 
 export let DefaultGateTelemetryState: GateTelemetryState = {
@@ -80,7 +106,36 @@ export let DefaultAudioMetricsState: AudioMetricsState = {
     inference_p90_ms: 0,
     inference_p95_ms: 0,
     inference_p99_ms: 0,
+    inference_p50_1m_ms: 0,
+    inference_p95_1m_ms: 0,
+    inference_p50_5m_ms: 0,
+    inference_p95_5m_ms: 0,
+    inference_sum_ms: 0,
+    inference_histogram: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    inference_cancelled_count: 0,
     gate_total_evaluations: 0,
     gate_total_emits: 0,
     gate_emit_rate: 0,
+    effective_step_rate_hz: 0,
+    step_lag_ms: 0,
+    next_step_delay_ms: 0,
+    last_speech_to_emit_ms: 0,
+    speech_to_emit_p50_ms: 0,
+    speech_to_emit_p90_ms: 0,
+    speech_to_emit_p99_ms: 0,
+    gate_hold_count: 0,
+    last_gate_hold_ms: 0,
+    gate_hold_p95_ms: 0,
+    last_ws_fanout_ms: 0,
+    ws_fanout_p95_ms: 0,
+    system: {
+        process_cpu_percent: 0,
+        process_rss_bytes: 0,
+        process_threads: null,
+        system_cpu_percent: 0,
+        logical_cpus: 0,
+        total_memory_bytes: 0,
+        available_memory_bytes: 0,
+        memory_pressure: 0,
+    },
 };